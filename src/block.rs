@@ -1,16 +1,22 @@
 use super::*;
+use crate::encoding::{self, Decode, DecodeError, Decoder, Encode, Encoder, ENCODING_VERSION};
+use crate::merkle::{self, MerkleProof};
 use std::fmt::{self, Debug, Formatter};
 use std::io::ErrorKind;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Block {
     pub index: u32,
+    #[serde(with = "serde_utils::u128_string")]
     pub timestamp: u128,
+    #[serde(with = "serde_utils::hex_bytes")]
     pub hash: Hash,
+    #[serde(with = "serde_utils::hex_bytes")]
     pub prev_block_hash: Hash,
     pub nonce: u64,
     pub transactions: Vec<Transaction>,
+    #[serde(with = "serde_utils::u128_string")]
     pub difficulty: u128,
 }
 
@@ -47,46 +53,140 @@ impl Block {
         }
     }
 
+    #[tracing::instrument(name = "mine_block", skip_all, fields(index = self.index))]
     pub fn mine(&mut self) -> std::io::Result<()> {
+        // 默克尔根只计算一次，之后只改变区块头中的 nonce
+        let mut header = self.header();
         for nonce_attempt in 0..u64::MAX {
-            self.nonce = nonce_attempt;
-            let hash = self.hash();
+            header.nonce = nonce_attempt;
+            let hash = header.hash();
             if check_difficulty(&hash, self.difficulty) {
+                tracing::debug!(nonce = nonce_attempt, hash = %hex::encode(&hash), "找到有效哈希");
+                self.nonce = nonce_attempt;
                 self.hash = hash;
                 return Ok(());
             }
         }
         Err(std::io::Error::new(ErrorKind::InvalidData, "Couldn't find valid hash"))
     }
+
+    // 规范编码后的字节数
+    pub fn size(&self) -> usize {
+        encoding::encode(self).len()
+    }
+
+    // 默克尔树的叶子：含解锁数据的交易规范编码
+    fn merkle_leaves(&self) -> Vec<Vec<u8>> {
+        self.transactions.iter().map(encoding::encode).collect()
+    }
+
+    // 交易的默克尔根
+    pub fn merkle_root(&self) -> Hash {
+        merkle::root(&self.merkle_leaves())
+    }
+
+    // 第 `index` 个交易到默克尔根的证明
+    pub fn merkle_proof(&self, index: usize) -> Option<MerkleProof> {
+        MerkleProof::new(&self.merkle_leaves(), index)
+    }
+
+    // 区块头，区块哈希即区块头的哈希
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            index: self.index,
+            timestamp: self.timestamp,
+            prev_block_hash: self.prev_block_hash.clone(),
+            merkle_root: self.merkle_root(),
+            nonce: self.nonce,
+            difficulty: self.difficulty,
+        }
+    }
 }
 
-impl Hashable for Block {
+// 定义区块头：通过默克尔根承诺全部交易，可以不依赖完整区块证明交易被打包
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub index: u32,
+    #[serde(with = "serde_utils::u128_string")]
+    pub timestamp: u128,
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub prev_block_hash: Hash,
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub merkle_root: Hash,
+    pub nonce: u64,
+    #[serde(with = "serde_utils::u128_string")]
+    pub difficulty: u128,
+}
+
+impl Hashable for BlockHeader {
     fn bytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
-
-        bytes.extend(&u32_bytes(&self.index));
-        bytes.extend(&u128_bytes(&self.timestamp));
-        bytes.extend(&self.prev_block_hash);
-        bytes.extend(&u64_bytes(&self.nonce));
-        bytes.extend(
-            self.transactions
-                .iter()
-                .flat_map(|transaction| transaction.bytes())
-                .collect::<Vec<u8>>(),
-        );
-        bytes.extend(&u128_bytes(&self.difficulty));
+        let mut enc = Encoder::new();
+        enc.put_u8(ENCODING_VERSION);
+        enc.put_u32(self.index);
+        enc.put_u128(self.timestamp);
+        enc.put_bytes(&self.prev_block_hash);
+        enc.put_bytes(&self.merkle_root);
+        enc.put_u64(self.nonce);
+        enc.put_u128(self.difficulty);
+        enc.into_bytes()
+    }
+}
 
-        bytes
+// 实现规范编码，用于存储与网络传输，包含区块哈希
+impl Encode for Block {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_u32(self.index);
+        enc.put_u128(self.timestamp);
+        enc.put_bytes(&self.prev_block_hash);
+        enc.put_u64(self.nonce);
+        enc.put_seq(&self.transactions);
+        enc.put_u128(self.difficulty);
+        enc.put_bytes(&self.hash);
+    }
+}
+
+impl Decode for Block {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        let index = dec.get_u32()?;
+        let timestamp = dec.get_u128()?;
+        let prev_block_hash = dec.get_bytes()?;
+        let nonce = dec.get_u64()?;
+        let transactions = dec.get_seq()?;
+        let difficulty = dec.get_u128()?;
+        let hash = dec.get_bytes()?;
+
+        let block = Block {
+            index,
+            timestamp,
+            hash,
+            prev_block_hash,
+            nonce,
+            transactions,
+            difficulty,
+        };
+        // 编码中的哈希必须与区块头一致
+        if block.hash != block.header().hash() {
+            return Err(DecodeError::MismatchedHash);
+        }
+        Ok(block)
+    }
+}
+
+impl Hashable for Block {
+    fn bytes(&self) -> Vec<u8> {
+        self.header().bytes()
     }
 }
 
 pub fn check_difficulty(hash: &Hash, difficulty: u128) -> bool {
-    difficulty > difficulty_bytes_as_u128(&hash)
+    difficulty > difficulty_bytes_as_u128(hash)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::script::Unlock;
+    use crate::transaction::{Input, OutPoint};
 
     #[test]
     fn test_mine_block() {
@@ -98,9 +198,25 @@ mod tests {
             0x0000ffffffffffffffffffffffffffff,
         );
 
-        block.mine();
+        block.mine().unwrap();
 
         assert_eq!(block.hash.len(), 32);
         assert!(check_difficulty(&block.hash, block.difficulty));
+        assert_eq!(block.header().hash(), block.hash);
+
+        // 区块哈希通过默克尔根承诺交易，包括不影响 txid 的解锁数据
+        let spend = Transaction {
+            inputs: vec![Input::from(OutPoint { txid: vec![0; 32], index: 0 })],
+            outputs: vec![],
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        };
+        block.transactions.push(spend);
+        assert_ne!(block.hash(), block.hash);
+        let root = block.merkle_root();
+        block.transactions[0].inputs[0].unlock = Unlock::Preimage { preimage: b"secret".to_vec() };
+        assert_ne!(block.merkle_root(), root);
+        assert_eq!(block.merkle_proof(0).unwrap().root(&encoding::encode(&block.transactions[0])), block.merkle_root());
     }
 }
@@ -1,115 +1,1490 @@
 use super::*;
-use std::collections::HashSet;
+use crate::encoding::{Decode, DecodeError, Decoder, Encode, Encoder};
+use crate::fees::{self, BlockFeeStats, FeeEstimator};
+use crate::params::ChainParams;
+use crate::script::{Lock, ScriptContext, ScriptError, Unlock};
+use crate::transaction::{Issuance, LockTime, OutPoint, Output, RelativeLock, TransactionError};
+use std::collections::BTreeMap;
+use crate::snapshot::{self, AssetRecord, SnapshotError, UtxoSnapshot, SNAPSHOT_RECENT_BLOCKS};
+use crate::utxo::{Balance, UtxoEntry};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+// 计算过去中位时间所用的区块数
+pub const MEDIAN_TIME_SPAN: usize = 11;
+// 区块时间戳允许超前于网络调整时间的最大值（毫秒）
+pub const MAX_FUTURE_DRIFT: u128 = 2 * 60 * 60 * 1000;
+// 区块规范编码后的最大字节数
+pub const MAX_BLOCK_SIZE: usize = 1_000_000;
+// 单个交易规范编码后的最大字节数
+pub const MAX_TRANSACTION_SIZE: usize = 100_000;
+// 单个区块最多包含的交易数（含 Coinbase 交易）
+pub const MAX_BLOCK_TRANSACTIONS: usize = 10_000;
+// 网络时间偏移的调整上限（毫秒），超过时认为对端时钟不可信
+pub const MAX_TIME_OFFSET: i64 = 70 * 60 * 1000;
 
 // 定义区块链验证错误
 #[derive(Debug, PartialEq)]
 pub enum BlockValidationErr {
     MismatchedIndex,
+    // 区块声明的哈希与区块头的哈希不一致
+    MismatchedHash,
     InvalidHash,
-    AchronologicalTimestamp,
+    TimestampNotAfterMedianTimePast,
+    TimestampTooFarInFuture,
     MismatchedPreviousHash,
     InvalidGenesisBlockFormat,
     InvalidInput,
     InsufficientInputValue,
     InvalidCoinbaseTransaction,
+    InvalidDifficulty,
+    BlockTooLarge,
+    TooManyTransactions,
+    InvalidTransaction(TransactionError),
+    ImmatureCoinbaseSpend,
+    DuplicateTransaction,
+    ScriptFailed(ScriptError),
+    LockTimeNotReached,
+    SequenceLockNotReached,
+    AssetNotConserved,
+}
+
+impl BlockValidationErr {
+    // 是否由区块中的某一个交易引起，区块级错误（哈希、难度、时间戳等）与交易无关
+    pub fn is_transaction_error(&self) -> bool {
+        matches!(
+            self,
+            BlockValidationErr::InvalidInput
+                | BlockValidationErr::InsufficientInputValue
+                | BlockValidationErr::InvalidCoinbaseTransaction
+                | BlockValidationErr::InvalidTransaction(_)
+                | BlockValidationErr::ImmatureCoinbaseSpend
+                | BlockValidationErr::DuplicateTransaction
+                | BlockValidationErr::ScriptFailed(_)
+                | BlockValidationErr::LockTimeNotReached
+                | BlockValidationErr::SequenceLockNotReached
+                | BlockValidationErr::AssetNotConserved
+        )
+    }
 }
 
 // 定义区块链结构体
 pub struct Blockchain {
+    // 从快照启动时只保留快照附带的最近区块，`base_height` 为其中第一个区块的索引
     pub blocks: Vec<Block>,
-    unspent_outputs: HashSet<Hash>,
+    unspent_outputs: HashMap<OutPoint, UtxoEntry>,
+    base_height: usize,
+    params: ChainParams,
+    // 根据对端时钟得到的本地时间偏移（毫秒）
+    time_offset: i64,
+    // 最近区块中已确认交易的费率
+    fee_estimator: FeeEstimator,
+    // 数据输出的索引：按附带的数据查找其所在的交易
+    data_index: HashMap<Vec<u8>, Vec<DataRecord>>,
+    // 已发行的资产
+    assets: HashMap<Hash, AssetInfo>,
+}
+
+// 定义已发行资产的信息
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AssetInfo {
+    pub name: String,
+    pub issuer: Lock,
+    // 累计发行数量
+    pub issued: u64,
+    // 首次发行的区块索引
+    pub height: u32,
+}
+
+impl Encode for AssetInfo {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_str(&self.name);
+        self.issuer.encode(enc);
+        enc.put_u64(self.issued);
+        enc.put_u32(self.height);
+    }
+}
+
+impl Decode for AssetInfo {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(AssetInfo {
+            name: dec.get_str()?,
+            issuer: Lock::decode(dec)?,
+            issued: dec.get_u64()?,
+            height: dec.get_u32()?,
+        })
+    }
+}
+
+// 定义数据输出的索引记录
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DataRecord {
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub txid: Vec<u8>,
+    pub index: u32,
+    pub height: u32,
+    pub timestamp: u128,
 }
 
 // 实现区块链结构体
 impl Blockchain {
-    // 创建新的区块链
-    pub fn new() -> Self {
+    // 创建新的区块链，第一个区块必须是参数中的创世区块
+    pub fn new(params: ChainParams) -> Self {
         Blockchain {
             blocks: vec![],
-            unspent_outputs: HashSet::new(),
+            unspent_outputs: HashMap::new(),
+            base_height: 0,
+            params,
+            time_offset: 0,
+            fee_estimator: FeeEstimator::new(),
+            data_index: HashMap::new(),
+            assets: HashMap::new(),
+        }
+    }
+
+    // 从 UTXO 快照创建区块链，校验快照承诺与附带的区块：
+    // 区块哈希必须与区块头一致，并满足链参数规定的难度
+    pub fn from_snapshot(snapshot: UtxoSnapshot, params: ChainParams) -> Result<Self, SnapshotError> {
+        if snapshot.compute_commitment() != snapshot.commitment {
+            return Err(SnapshotError::CommitmentMismatch);
+        }
+
+        let height = snapshot.height as usize;
+        let base_height = height
+            .checked_sub(snapshot.recent_blocks.len())
+            .ok_or(SnapshotError::InvalidRecentBlocks)?;
+        for (i, block) in snapshot.recent_blocks.iter().enumerate() {
+            if block.index as usize != base_height + i
+                || block.hash != block.hash()
+                || block.difficulty != params.difficulty
+                || !block::check_difficulty(&block.hash, params.difficulty)
+                || (i > 0 && block.prev_block_hash != snapshot.recent_blocks[i - 1].hash)
+                || (block.index == 0 && block.hash != params.genesis.hash)
+            {
+                return Err(SnapshotError::InvalidRecentBlocks);
+            }
+        }
+        if height > 0 && snapshot.recent_blocks.is_empty() {
+            return Err(SnapshotError::InvalidRecentBlocks);
+        }
+
+        let mut blockchain = Blockchain {
+            blocks: snapshot.recent_blocks,
+            unspent_outputs: snapshot
+                .utxos
+                .into_iter()
+                .map(|entry| (entry.outpoint.clone(), entry))
+                .collect(),
+            base_height,
+            params,
+            time_offset: 0,
+            fee_estimator: FeeEstimator::new(),
+            data_index: HashMap::new(),
+            assets: snapshot
+                .assets
+                .into_iter()
+                .map(|record| (record.asset, record.info))
+                .collect(),
+        };
+        // 快照只附带最近的区块，更早的数据输出无法查找
+        for block in blockchain.blocks.clone() {
+            blockchain.index_data(&block);
+        }
+        Ok(blockchain)
+    }
+
+    // 在当前链顶生成 UTXO 快照
+    pub fn snapshot(&self) -> UtxoSnapshot {
+        let keep = self.blocks.len().min(SNAPSHOT_RECENT_BLOCKS);
+        UtxoSnapshot::new(
+            self.height() as u32,
+            self.blocks[self.blocks.len() - keep..].to_vec(),
+            self.unspent_outputs.values().cloned().collect(),
+            self.assets
+                .iter()
+                .map(|(asset, info)| AssetRecord {
+                    asset: asset.clone(),
+                    info: info.clone(),
+                })
+                .collect(),
+        )
+    }
+
+    // 当前 UTXO 集合的承诺哈希
+    pub fn utxo_commitment(&self) -> Hash {
+        let tip_hash = self.tip().map(|block| block.hash.as_slice()).unwrap_or_default();
+        snapshot::utxo_commitment(self.height() as u32, tip_hash, self.unspent_outputs.values(), self.assets.iter())
+    }
+
+    // 获取链参数
+    pub fn params(&self) -> &ChainParams {
+        &self.params
+    }
+
+    // 获取最近区块的手续费率统计
+    pub fn fee_estimator(&self) -> &FeeEstimator {
+        &self.fee_estimator
+    }
+
+    // 按附带的数据查找数据输出，按区块顺序返回
+    pub fn data_records(&self, data: &[u8]) -> &[DataRecord] {
+        self.data_index.get(data).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    // 获取区块数量，即下一个区块的索引
+    pub fn height(&self) -> usize {
+        self.base_height + self.blocks.len()
+    }
+
+    // 按索引获取区块，从快照启动时更早的区块不可用
+    pub fn block(&self, index: usize) -> Option<&Block> {
+        index
+            .checked_sub(self.base_height)
+            .and_then(|i| self.blocks.get(i))
+    }
+
+    // 获取链顶区块
+    pub fn tip(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    // 链顶之前最近 `MEDIAN_TIME_SPAN` 个区块时间戳的中位数
+    pub fn median_time_past(&self) -> Option<u128> {
+        let start = self.blocks.len().saturating_sub(MEDIAN_TIME_SPAN);
+        let mut timestamps: Vec<u128> = self.blocks[start..].iter().map(|b| b.timestamp).collect();
+        timestamps.sort_unstable();
+        timestamps.get(timestamps.len() / 2).copied()
+    }
+
+    // 设置网络时间偏移，超出上限时截断
+    pub fn set_time_offset(&mut self, offset: i64) {
+        self.time_offset = offset.clamp(-MAX_TIME_OFFSET, MAX_TIME_OFFSET);
+    }
+
+    // 经网络时间偏移调整后的当前时间
+    pub fn adjusted_time(&self) -> u128 {
+        now().saturating_add_signed(self.time_offset as i128)
+    }
+
+    // 新区块可用的时间戳：不早于过去中位时间之后
+    pub fn next_timestamp(&self) -> u128 {
+        let adjusted = self.adjusted_time();
+        match self.median_time_past() {
+            Some(median) => adjusted.max(median + 1),
+            None => adjusted,
         }
     }
 
+    // 查询地址余额，按下一个区块的高度判断 Coinbase 奖励是否成熟
+    pub fn balance(&self, address: &str) -> Balance {
+        let mut balance = Balance::default();
+        for entry in self.unspent_outputs.values() {
+            if entry.output.receiver != address || entry.output.asset.is_some() {
+                continue;
+            }
+            if entry.is_mature(self.height(), self.params.coinbase_maturity) {
+                balance.spendable = balance.spendable.saturating_add(entry.output.value);
+            } else {
+                balance.immature = balance.immature.saturating_add(entry.output.value);
+            }
+        }
+        balance
+    }
+
+    // 查询地址持有的各资产数量；资产只能由普通交易创建，不受 Coinbase 成熟度限制
+    pub fn asset_balances(&self, address: &str) -> BTreeMap<Hash, u64> {
+        let mut balances: BTreeMap<Hash, u64> = BTreeMap::new();
+        for entry in self.unspent_outputs.values() {
+            if let Some(asset) = &entry.output.asset
+                && entry.output.receiver == address
+            {
+                let balance = balances.entry(asset.clone()).or_default();
+                *balance = balance.saturating_add(entry.output.value);
+            }
+        }
+        balances
+    }
+
+    // 查询已发行资产的信息
+    pub fn asset(&self, asset: &[u8]) -> Option<&AssetInfo> {
+        self.assets.get(asset)
+    }
+
+    // 获取未花费输出数量
+    pub fn utxo_count(&self) -> usize {
+        self.unspent_outputs.len()
+    }
+
+    // 按输出位置查询未花费输出
+    pub fn utxo(&self, outpoint: &OutPoint) -> Option<&UtxoEntry> {
+        self.unspent_outputs.get(outpoint)
+    }
+
+    // 列出地址的全部未花费输出，按输出位置排序
+    pub fn utxos_for(&self, address: &str) -> Vec<&UtxoEntry> {
+        let mut utxos: Vec<&UtxoEntry> = self
+            .unspent_outputs
+            .values()
+            .filter(|entry| entry.output.receiver == address)
+            .collect();
+        utxos.sort_by(|a, b| a.outpoint.cmp(&b.outpoint));
+        utxos
+    }
+
+    // 根据 UTXO 集合计算交易的手续费，输入不存在或金额不足时返回 None
+    pub fn fee(&self, transaction: &Transaction) -> Option<u64> {
+        let input_value = transaction
+            .inputs
+            .iter()
+            .try_fold(0u64, |sum, input| sum.checked_add(self.utxo(&input.outpoint())?.output.native_value()))?;
+        input_value.checked_sub(transaction.output_value())
+    }
+
+    // 计算一组交易各自的手续费，输入可以来自 UTXO 集合或组内的其他交易；无法计算时记为 0
+    fn package_fees(&self, transactions: &[Transaction]) -> Vec<u64> {
+        let outputs: HashMap<OutPoint, &Output> = transactions.iter().flat_map(Transaction::outpoints).collect();
+        transactions
+            .iter()
+            .map(|transaction| {
+                transaction
+                    .inputs
+                    .iter()
+                    .try_fold(0u64, |sum, input| {
+                        let outpoint = input.outpoint();
+                        let value = match outputs.get(&outpoint) {
+                            Some(output) => output.native_value(),
+                            None => self.utxo(&outpoint)?.output.native_value(),
+                        };
+                        sum.checked_add(value)
+                    })
+                    .and_then(|value| value.checked_sub(transaction.output_value()))
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    // 按交易包的手续费率排序：每个交易与其尚未选中的祖先交易组成一个包，每次选出手续费率最高的包，
+    // 包内祖先在前，使高手续费的子交易带动低手续费的父交易一起打包；手续费率相同时保持原有顺序
+    pub fn package_order(&self, transactions: Vec<Transaction>) -> Vec<Transaction> {
+        let fees = self.package_fees(&transactions);
+        let sizes: Vec<u64> = transactions.iter().map(|t| t.size() as u64).collect();
+        let positions: HashMap<Vec<u8>, usize> = transactions
+            .iter()
+            .enumerate()
+            .map(|(position, t)| (t.txid(), position))
+            .collect();
+        let mut parents: Vec<Vec<usize>> = vec![];
+        let mut children: Vec<Vec<usize>> = vec![vec![]; transactions.len()];
+        for (position, transaction) in transactions.iter().enumerate() {
+            let mut own: Vec<usize> = transaction
+                .inputs
+                .iter()
+                .filter_map(|input| positions.get(&input.txid).copied())
+                .collect();
+            own.sort_unstable();
+            own.dedup();
+            for &parent in &own {
+                children[parent].push(position);
+            }
+            parents.push(own);
+        }
+
+        // 每个交易所在包的成员与手续费率，选中一个包后只需重新计算其后代的包
+        let mut selected = vec![false; transactions.len()];
+        let mut packages: Vec<(Vec<usize>, u64, u64)> = vec![];
+        for position in 0..transactions.len() {
+            packages.push(ancestor_package(position, &parents, &selected, &fees, &sizes));
+        }
+        let mut order = Vec::with_capacity(transactions.len());
+        while order.len() < transactions.len() {
+            let mut best: Option<usize> = None;
+            for position in (0..transactions.len()).filter(|&position| !selected[position]) {
+                let (_, fee, size) = &packages[position];
+                if best.is_none_or(|best| {
+                    let (_, best_fee, best_size) = &packages[best];
+                    compare_fee_rate(*fee, *size, *best_fee, *best_size) == Ordering::Greater
+                }) {
+                    best = Some(position);
+                }
+            }
+            let Some(best) = best else {
+                break;
+            };
+            let members = std::mem::take(&mut packages[best].0);
+            let mut stale: Vec<usize> = vec![];
+            for &member in &members {
+                selected[member] = true;
+                stale.extend(&children[member]);
+            }
+            order.extend(members);
+            while let Some(position) = stale.pop() {
+                if !selected[position] {
+                    packages[position] = ancestor_package(position, &parents, &selected, &fees, &sizes);
+                    stale.extend(&children[position]);
+                }
+            }
+        }
+
+        let mut transactions: Vec<Option<Transaction>> = transactions.into_iter().map(Some).collect();
+        order.into_iter().filter_map(|position| transactions[position].take()).collect()
+    }
+
+    // 检查交易的解锁数据能否满足被花费输出（按输入顺序给出）的锁定条件，以下一个区块为执行环境；交易池接收交易时使用
+    pub fn check_unlocks(&self, transaction: &Transaction, spent: &[Output]) -> Result<(), ScriptError> {
+        let txid = transaction.txid();
+        let ctx = ScriptContext {
+            sighash: &txid,
+            height: self.height() as u32,
+            time: self.median_time_past().unwrap_or(0),
+        };
+        transaction
+            .inputs
+            .iter()
+            .zip(spent)
+            .try_for_each(|(input, output)| check_unlock(output, &input.unlock, &ctx))?;
+        check_issuance(transaction, &ctx)
+    }
+
+    // 在链顶之上构造待挖矿的区块：按交易包的手续费率打包不超出区块限制的交易，返回未能打包的交易；
+    // Coinbase 领取出块奖励与全部手续费，时间戳晚于过去中位时间
+    pub fn block_template(&self, address: &str, transactions: Vec<Transaction>) -> Option<(Block, Vec<Transaction>)> {
+        self.block_template_with_output(address, transactions, None)
+    }
+
+    // 与 `block_template` 相同，Coinbase 交易附加一个输出；该输出在打包交易之前加入，计入区块大小
+    pub fn block_template_with_output(
+        &self,
+        address: &str,
+        transactions: Vec<Transaction>,
+        coinbase_output: Option<Output>,
+    ) -> Option<(Block, Vec<Transaction>)> {
+        let tip = self.tip()?;
+        let mut outputs = vec![Output {
+            receiver: address.to_owned(),
+            value: self.params.subsidy,
+            asset: None,
+            lock: None,
+        }];
+        outputs.extend(coinbase_output);
+        let coinbase = Transaction::coinbase(tip.index + 1, outputs);
+        let mut block = Block::new(
+            tip.index + 1,
+            self.next_timestamp(),
+            tip.hash.clone(),
+            vec![coinbase],
+            self.params.difficulty,
+        );
+
+        // 尚未满足时间锁的交易及其子交易留待之后的区块
+        let (mut ready, mut waiting): (Vec<Transaction>, Vec<Transaction>) =
+            transactions.into_iter().partition(|t| self.check_final(t).is_ok());
+        loop {
+            let waiting_txids: HashSet<Vec<u8>> = waiting.iter().map(Transaction::txid).collect();
+            let (blocked, rest): (Vec<Transaction>, Vec<Transaction>) = ready
+                .into_iter()
+                .partition(|t| t.inputs.iter().any(|input| waiting_txids.contains(&input.txid)));
+            ready = rest;
+            if blocked.is_empty() {
+                break;
+            }
+            waiting.extend(blocked);
+        }
+        let mut remaining = fill_block(&mut block, self.package_order(ready));
+        remaining.extend(waiting);
+        let fees = self
+            .package_fees(&block.transactions[1..])
+            .into_iter()
+            .fold(0u64, u64::saturating_add);
+        let coinbase = &mut block.transactions[0].outputs[0];
+        coinbase.value = coinbase.value.saturating_add(fees);
+        Some((block, remaining))
+    }
+
+    // 检查交易能否打包进下一个区块，交易池接收交易时使用
+    pub fn check_final(&self, transaction: &Transaction) -> Result<(), BlockValidationErr> {
+        self.check_time_locks(transaction, self.height(), self.median_time_past().unwrap_or(0), &HashMap::new())
+    }
+
+    // 检查交易的绝对与相对时间锁：`height` 为打包交易的区块索引，`median_time` 为该区块之前的过去中位时间，
+    // `created` 为同一区块中排在前面的交易创建的输出；花费未确认输出的相对时间锁视为尚未到期
+    fn check_time_locks(
+        &self,
+        transaction: &Transaction,
+        height: usize,
+        median_time: u128,
+        created: &HashMap<OutPoint, UtxoEntry>,
+    ) -> Result<(), BlockValidationErr> {
+        let reached = match transaction.lock_time {
+            None => true,
+            Some(LockTime::Height(lock_height)) => height >= lock_height as usize,
+            Some(LockTime::Timestamp(timestamp)) => median_time >= timestamp as u128,
+        };
+        if !reached {
+            return Err(BlockValidationErr::LockTimeNotReached);
+        }
+
+        for input in &transaction.inputs {
+            let Some(sequence) = input.sequence else {
+                continue;
+            };
+            let outpoint = input.outpoint();
+            let Some(entry) = self.unspent_outputs.get(&outpoint).or_else(|| created.get(&outpoint)) else {
+                return Err(BlockValidationErr::SequenceLockNotReached);
+            };
+            let reached = match sequence {
+                RelativeLock::Blocks(blocks) => height >= entry.height as usize + blocks as usize,
+                RelativeLock::Millis(millis) => median_time >= entry.time.saturating_add(millis as u128),
+            };
+            if !reached {
+                return Err(BlockValidationErr::SequenceLockNotReached);
+            }
+        }
+        Ok(())
+    }
+
+    // 挖出并添加 `count` 个区块，奖励发往 `address`；`transactions` 依次打包，未能打包的交易留在其中。
+    // 出错时未被添加的区块中的交易也放回其中，已添加的区块保留
+    pub fn generate(
+        &mut self,
+        count: usize,
+        address: &str,
+        transactions: &mut Vec<Transaction>,
+    ) -> Result<Vec<Block>, BlockValidationErr> {
+        self.generate_with_output(count, address, transactions, None)
+    }
+
+    // 与 `generate` 相同，第一个区块的 Coinbase 交易附加一个输出，例如存证承诺
+    pub fn generate_with_output(
+        &mut self,
+        count: usize,
+        address: &str,
+        transactions: &mut Vec<Transaction>,
+        mut coinbase_output: Option<Output>,
+    ) -> Result<Vec<Block>, BlockValidationErr> {
+        if self.height() == 0 {
+            self.update_with_block(self.params.genesis.clone())?;
+        }
+
+        let mut blocks = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (mut block, remaining) = self
+                .block_template_with_output(address, std::mem::take(transactions), coinbase_output.take())
+                .expect("chain has a tip after genesis");
+            *transactions = remaining;
+            let result = block
+                .mine()
+                .map_err(|_| BlockValidationErr::InvalidHash)
+                .and_then(|_| self.update_with_block(block.clone()));
+            if let Err(e) = result {
+                transactions.splice(0..0, block.transactions.drain(1..));
+                return Err(e);
+            }
+            blocks.push(block);
+        }
+        Ok(blocks)
+    }
+
     // 更新区块链
+    #[tracing::instrument(name = "validate_block", skip_all, fields(index = block.index), err(Debug, level = "warn"))]
     pub fn update_with_block(&mut self, block: Block) -> Result<(), BlockValidationErr> {
-        let block_num = self.blocks.len();
+        let block_num = self.height();
+        let block_size = block.size();
+
+        // 区块声明的哈希必须是区块头的哈希，之后的链接检查依赖该字段
+        if block.hash != block.hash() {
+            return Err(BlockValidationErr::MismatchedHash);
+        }
+
+        check_context_free(&block)?;
 
         // 检查区块是否有效
         if block.index != block_num as u32 {
             return Err(BlockValidationErr::MismatchedIndex);
         } else if !block::check_difficulty(&block.hash(), block.difficulty) {
             return Err(BlockValidationErr::InvalidHash);
-        } else if block_num != 0 {
+        } else if let Some(prev_block) = self.tip() {
             // 非 Genesis 区块
-            let prev_block = &self.blocks[block_num - 1];
-            if block.timestamp <= prev_block.timestamp {
-                return Err(BlockValidationErr::AchronologicalTimestamp);
+            if block.difficulty != self.params.difficulty {
+                return Err(BlockValidationErr::InvalidDifficulty);
+            } else if self.median_time_past().is_some_and(|median| block.timestamp <= median) {
+                return Err(BlockValidationErr::TimestampNotAfterMedianTimePast);
+            } else if block.timestamp > self.adjusted_time() + MAX_FUTURE_DRIFT {
+                return Err(BlockValidationErr::TimestampTooFarInFuture);
             } else if block.prev_block_hash != prev_block.hash {
                 return Err(BlockValidationErr::MismatchedPreviousHash);
             }
         } else {
-            // Genesis 区块
-            if block.prev_block_hash != vec![0; 32] {
+            // Genesis 区块，必须与链参数中的创世区块一致
+            if block.prev_block_hash != vec![0; 32] || block.hash() != self.params.genesis.hash {
                 return Err(BlockValidationErr::InvalidGenesisBlockFormat);
             }
         }
 
+        if let Some(update) = self.connect_transactions(&block)? {
+            // 更新 UTXO 集合，本区块中创建并花费的输出不再加入
+            let BlockUpdate { spent, mut created, fee_rates, issued } = update;
+            self.unspent_outputs.retain(|outpoint, _| !spent.contains(outpoint));
+            created.retain(|outpoint, _| !spent.contains(outpoint));
+            self.unspent_outputs.extend(created);
+            self.fee_estimator
+                .record(BlockFeeStats::new(fee_rates, block_size, block.transactions.len()));
+            for (asset, issuance, amount) in issued {
+                let info = self.assets.entry(asset).or_insert_with(|| AssetInfo {
+                    name: issuance.name,
+                    issuer: issuance.issuer,
+                    issued: 0,
+                    height: block_num as u32,
+                });
+                info.issued = info.issued.saturating_add(amount);
+            }
+        }
+
+        tracing::debug!(transactions = block.transactions.len(), "区块验证通过");
+        self.index_data(&block);
+        self.blocks.push(block);
+
+        Ok(())
+    }
+
+    // 验证尚未挖出的下一个区块中的交易，不检查工作量证明与区块头，用于在挖矿之前拒绝无效交易
+    pub fn check_block_transactions(&self, block: &Block) -> Result<(), BlockValidationErr> {
+        if block.index != self.height() as u32 {
+            return Err(BlockValidationErr::MismatchedIndex);
+        }
+        check_context_free(block)?;
+        self.connect_transactions(block).map(|_| ())
+    }
+
+    // 按当前 UTXO 集合验证区块中的交易，返回区块对 UTXO 集合与资产的更新；区块没有交易时返回 None
+    fn connect_transactions(&self, block: &Block) -> Result<Option<BlockUpdate>, BlockValidationErr> {
+        let block_num = self.height();
+
+        // 同一区块中的交易 ID 不可重复
+        let mut txids = HashSet::new();
+        if !block.transactions.iter().all(|transaction| txids.insert(transaction.txid())) {
+            return Err(BlockValidationErr::DuplicateTransaction);
+        }
+
         // 检查交易是否有效
-        if let Some((coinbase, transactions)) = block.transactions.split_first() {
-            // 检查 Coinbase 交易
-            if !coinbase.is_coinbase() {
-                return Err(BlockValidationErr::InvalidCoinbaseTransaction);
-            }
-
-            // 检查双花问题
-            let mut block_spent: HashSet<Hash> = HashSet::new();
-            let mut block_created: HashSet<Hash> = HashSet::new();
-            let mut total_fee = 0;
-
-            // 遍历区块中的交易
-            for transaction in transactions {
-                let input_hashes = transaction.input_hashes();
-
-                // 检查输入是否有效且未被重复花费
-                if !(&input_hashes - &self.unspent_outputs).is_empty()
-                    || !(&input_hashes & &block_spent).is_empty()
-                {
-                    return Err(BlockValidationErr::InvalidInput);
-                }
+        let Some((coinbase, transactions)) = block.transactions.split_first() else {
+            return Ok(None);
+        };
+        // 检查 Coinbase 交易：必须提交本区块的索引，且只能是第一个交易
+        if coinbase.coinbase.as_ref().is_none_or(|data| data.height != block.index)
+            || transactions.iter().any(Transaction::is_coinbase)
+        {
+            return Err(BlockValidationErr::InvalidCoinbaseTransaction);
+        }
 
-                // 计算输入和输出金额
-                let input_value = transaction.input_value();
-                let output_value = transaction.output_value();
+        // 时间锁以本区块之前的过去中位时间为准，不受本区块时间戳影响
+        let median_time = self.median_time_past().unwrap_or(0);
 
-                // 输出金额不可超过输入金额
-                if output_value > input_value {
-                    return Err(BlockValidationErr::InsufficientInputValue);
-                }
+        self.check_time_locks(coinbase, block_num, median_time, &HashMap::new())?;
+
+        // 检查双花问题
+        let mut block_spent: HashSet<OutPoint> = HashSet::new();
+        let mut block_created: HashMap<OutPoint, UtxoEntry> = HashMap::new();
+        let mut total_fee: u64 = 0;
+        let mut fee_rates = vec![];
+        let mut issued = vec![];
 
-                // 累加手续费
-                let fee = input_value - output_value;
-                total_fee += fee;
+        // 遍历区块中的交易
+        for transaction in transactions {
+            let outpoints = transaction.input_outpoints();
 
-                // 记录已花费和新生成的 UTXO
-                block_spent.extend(input_hashes);
-                block_created.extend(transaction.output_hashes());
+            // 检查输入是否有效且未被重复花费：可以花费 UTXO 集合中的输出，或本区块中排在前面的交易创建的输出
+            let spent_entries = transaction
+                .inputs
+                .iter()
+                .map(|input| {
+                    let outpoint = input.outpoint();
+                    self.unspent_outputs.get(&outpoint).or_else(|| block_created.get(&outpoint))
+                })
+                .collect::<Option<Vec<&UtxoEntry>>>()
+                .ok_or(BlockValidationErr::InvalidInput)?;
+            if !(&outpoints & &block_spent).is_empty() {
+                return Err(BlockValidationErr::InvalidInput);
             }
 
-            // Coinbase 交易必须覆盖手续费
-            if coinbase.output_value() < total_fee {
-                return Err(BlockValidationErr::InvalidCoinbaseTransaction);
-            } else {
-                block_created.extend(coinbase.output_hashes());
+            // Coinbase 输出需要经过足够的区块才能花费
+            if spent_entries
+                .iter()
+                .any(|entry| !entry.is_mature(block_num, self.params.coinbase_maturity))
+            {
+                return Err(BlockValidationErr::ImmatureCoinbaseSpend);
             }
 
-            // 更新 UTXO 集合
-            self.unspent_outputs
-                .retain(|output| !block_spent.contains(output));
-            self.unspent_outputs.extend(block_created);
+            // 检查绝对与相对时间锁
+            self.check_time_locks(transaction, block_num, median_time, &block_created)?;
+
+            // 每个输入的解锁数据必须满足被花费输出的锁定条件，未加锁的输出不接受解锁数据
+            let txid = transaction.txid();
+            let ctx = ScriptContext {
+                sighash: &txid,
+                height: block_num as u32,
+                time: median_time,
+            };
+            for (input, entry) in transaction.inputs.iter().zip(&spent_entries) {
+                check_unlock(&entry.output, &input.unlock, &ctx).map_err(BlockValidationErr::ScriptFailed)?;
+            }
+            check_issuance(transaction, &ctx).map_err(BlockValidationErr::ScriptFailed)?;
+
+            // 每种资产的输出必须等于输入，发行的资产可以增发
+            let spent_outputs = spent_entries.iter().map(|entry| &entry.output);
+            if !transaction.assets_balanced(spent_outputs.clone()) {
+                return Err(BlockValidationErr::AssetNotConserved);
+            }
+            if let Some((asset, amount)) = transaction.issued_amount(spent_outputs) {
+                issued.push((asset, transaction.issuance.clone().unwrap(), amount));
+            }
+
+            // 计算原生币的输入和输出金额
+            let input_value = spent_entries
+                .iter()
+                .try_fold(0u64, |sum, entry| checked_value(sum.checked_add(entry.output.native_value())))?;
+            let output_value = transaction.output_value();
+
+            // 输出金额不可超过输入金额
+            if output_value > input_value {
+                return Err(BlockValidationErr::InsufficientInputValue);
+            }
+
+            // 累加手续费
+            let fee = input_value - output_value;
+            total_fee = checked_value(total_fee.checked_add(fee))?;
+            fee_rates.push(fees::fee_rate(fee, transaction.size()));
+
+            // 记录已花费和新生成的 UTXO
+            block_spent.extend(outpoints);
+            block_created.extend(utxo_entries(transaction, block_num, block.timestamp, false));
         }
 
-        self.blocks.push(block);
+        // Coinbase 交易必须覆盖手续费，且不能超过出块奖励与手续费之和；创世区块的分配不受限制
+        let max_reward = checked_value(self.params.subsidy.checked_add(total_fee))?;
+        if coinbase.output_value() < total_fee || (block_num > 0 && coinbase.output_value() > max_reward) {
+            return Err(BlockValidationErr::InvalidCoinbaseTransaction);
+        } else {
+            // 创世区块的分配不是挖矿奖励，无需等待成熟
+            block_created.extend(utxo_entries(coinbase, block_num, block.timestamp, block_num > 0));
+        }
 
-        Ok(())
+        // 新输出不可覆盖尚未花费的输出
+        if block_created.keys().any(|outpoint| self.unspent_outputs.contains_key(outpoint)) {
+            return Err(BlockValidationErr::DuplicateTransaction);
+        }
+
+        Ok(Some(BlockUpdate {
+            spent: block_spent,
+            created: block_created,
+            fee_rates,
+            issued,
+        }))
+    }
+
+    // 将区块中的数据输出加入索引
+    fn index_data(&mut self, block: &Block) {
+        for transaction in &block.transactions {
+            for (outpoint, output) in transaction.outpoints() {
+                if let Some(data) = output.carried_data() {
+                    self.data_index.entry(data.to_vec()).or_default().push(DataRecord {
+                        txid: outpoint.txid,
+                        index: outpoint.index,
+                        height: block.index,
+                        timestamp: block.timestamp,
+                    });
+                }
+            }
+        }
+    }
+}
+
+// 区块中的交易对 UTXO 集合与资产的更新，验证通过后应用
+struct BlockUpdate {
+    spent: HashSet<OutPoint>,
+    created: HashMap<OutPoint, UtxoEntry>,
+    fee_rates: Vec<u64>,
+    issued: Vec<(Hash, Issuance, u64)>,
+}
+
+// 检查区块大小与交易数量，并逐个执行与上下文无关的交易检查
+fn check_context_free(block: &Block) -> Result<(), BlockValidationErr> {
+    if block.transactions.len() > MAX_BLOCK_TRANSACTIONS {
+        return Err(BlockValidationErr::TooManyTransactions);
+    } else if block.size() > MAX_BLOCK_SIZE {
+        return Err(BlockValidationErr::BlockTooLarge);
+    }
+    for transaction in &block.transactions {
+        transaction.check().map_err(BlockValidationErr::InvalidTransaction)?;
+    }
+    Ok(())
+}
+
+// 交易及其尚未选中的祖先交易组成的包：成员（祖先在前）、总手续费与总大小
+fn ancestor_package(
+    position: usize,
+    parents: &[Vec<usize>],
+    selected: &[bool],
+    fees: &[u64],
+    sizes: &[u64],
+) -> (Vec<usize>, u64, u64) {
+    let mut members = vec![];
+    let mut visited = HashSet::new();
+    let mut stack = vec![(position, false)];
+    while let Some((position, expanded)) = stack.pop() {
+        if expanded {
+            members.push(position);
+        } else if !selected[position] && visited.insert(position) {
+            stack.push((position, true));
+            stack.extend(parents[position].iter().rev().map(|&parent| (parent, false)));
+        }
+    }
+    let fee = members.iter().map(|&member| fees[member]).fold(0u64, u64::saturating_add);
+    let size = members.iter().map(|&member| sizes[member]).sum();
+    (members, fee, size)
+}
+
+// 比较两个手续费率 `fee / size`
+pub fn compare_fee_rate(fee_a: u64, size_a: u64, fee_b: u64, size_b: u64) -> Ordering {
+    (fee_a as u128 * size_b as u128).cmp(&(fee_b as u128 * size_a as u128))
+}
+
+// 输入的解锁数据必须满足被花费输出的锁定条件，未加锁的输出不接受解锁数据
+fn check_unlock(output: &Output, unlock: &Unlock, ctx: &ScriptContext) -> Result<(), ScriptError> {
+    match &output.lock {
+        Some(lock) => lock.evaluate(unlock, ctx),
+        None if *unlock == Unlock::None => Ok(()),
+        None => Err(ScriptError::UnlockMismatch),
+    }
+}
+
+// 为交易的输出生成 UTXO 条目，数据输出无法花费，不进入 UTXO 集合
+fn utxo_entries(
+    transaction: &Transaction,
+    height: usize,
+    time: u128,
+    is_coinbase: bool,
+) -> impl Iterator<Item = (OutPoint, UtxoEntry)> + '_ {
+    transaction
+        .outpoints()
+        .filter(|(_, output)| output.carried_data().is_none())
+        .map(move |(outpoint, output)| {
+            let entry = UtxoEntry {
+                outpoint: outpoint.clone(),
+                output: output.clone(),
+                height: height as u32,
+                time,
+                is_coinbase,
+            };
+            (outpoint, entry)
+        })
+}
+
+// 资产发行需要满足发行方的锁定条件
+fn check_issuance(transaction: &Transaction, ctx: &ScriptContext) -> Result<(), ScriptError> {
+    match &transaction.issuance {
+        Some(issuance) => issuance.issuer.evaluate(&issuance.unlock, ctx),
+        None => Ok(()),
+    }
+}
+
+// 金额累加溢出时视为无效交易
+fn checked_value(value: Option<u64>) -> Result<u64, BlockValidationErr> {
+    value.ok_or(BlockValidationErr::InvalidTransaction(TransactionError::ValueOverflow))
+}
+
+// 按顺序向区块追加交易，跳过超出单个交易限制的交易，直到区块大小或交易数量达到上限；返回未追加的交易。
+// 父交易需排在子交易之前，父交易未追加时子交易同样跳过
+pub fn fill_block(block: &mut Block, transactions: Vec<Transaction>) -> Vec<Transaction> {
+    let mut size = block.size();
+    let mut remaining = vec![];
+    let mut skipped: HashSet<Vec<u8>> = HashSet::new();
+    for transaction in transactions {
+        // 按独立编码长度累加，略大于在区块中的实际占用，保证不会超限
+        let tx_size = transaction.size();
+        if !transaction.inputs.iter().any(|input| skipped.contains(&input.txid))
+            && tx_size <= MAX_TRANSACTION_SIZE
+            && size + tx_size <= MAX_BLOCK_SIZE
+            && block.transactions.len() < MAX_BLOCK_TRANSACTIONS
+        {
+            size += tx_size;
+            block.transactions.push(transaction);
+        } else {
+            skipped.insert(transaction.txid());
+            remaining.push(transaction);
+        }
+    }
+    remaining
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::Unlock;
+    use crate::test_utils::{pay, regtest_chain, spend, spend_reward};
+    use crate::transaction::Input;
+    use std::sync::Mutex;
+
+    // 模拟时间是全局状态，涉及当前时间的测试串行执行
+    static CLOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_generate_with_mock_time() {
+        let _clock = CLOCK.lock().unwrap();
+        let mut blockchain = Blockchain::new(ChainParams::regtest());
+        let start = ChainParams::regtest().genesis.timestamp as u64 + 1000;
+        set_mock_time(Some(start));
+        let blocks = blockchain.generate(3, "miner", &mut vec![]).unwrap();
+        set_mock_time(None);
+
+        assert_eq!(blockchain.height(), 4);
+        // 模拟时间不变时，时间戳保持晚于过去中位时间
+        let timestamps: Vec<u128> = blocks.iter().map(|b| b.timestamp).collect();
+        assert_eq!(timestamps, vec![start as u128, start as u128 + 1, start as u128 + 1]);
+
+        // 奖励成熟后花费，手续费归下一个区块的 Coinbase
+        let maturity = blockchain.params().coinbase_maturity as usize;
+        blockchain.generate(maturity, "other", &mut vec![]).unwrap();
+        let spend = spend_reward(&blocks[0], "Bob", 45);
+        let blocks = blockchain.generate(1, "miner", &mut vec![spend]).unwrap();
+        assert_eq!(blocks[0].transactions[0].output_value(), 55);
+    }
+
+    #[test]
+    fn test_generate_returns_unmined_transactions() {
+        let _clock = CLOCK.lock().unwrap();
+        let (mut blockchain, funding) = regtest_chain(2);
+        let height = blockchain.height();
+
+        // 同一区块中重复花费导致区块被拒绝，打包进该区块的交易全部放回
+        let spend = spend_reward(&funding[0], "Alice", 45);
+        let double_spend = spend_reward(&funding[0], "Bob", 40);
+        let mut transactions = vec![spend.clone(), double_spend.clone()];
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut transactions),
+            Err(BlockValidationErr::InvalidInput)
+        );
+        assert_eq!(blockchain.height(), height);
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions.contains(&spend) && transactions.contains(&double_spend));
+    }
+
+    fn block_at(blockchain: &Blockchain, timestamp: u128) -> Block {
+        let (mut block, _) = blockchain.block_template("miner", vec![]).unwrap();
+        block.timestamp = timestamp;
+        block.mine().unwrap();
+        block
+    }
+
+    #[test]
+    fn test_timestamp_rules() {
+        let _clock = CLOCK.lock().unwrap();
+        let mut blockchain = Blockchain::new(ChainParams::regtest());
+        let genesis = ChainParams::regtest().genesis;
+        let base = genesis.timestamp;
+        blockchain.update_with_block(genesis).unwrap();
+        for timestamp in [base + 10, base + 20, base + 30, base + 40] {
+            let block = block_at(&blockchain, timestamp);
+            blockchain.update_with_block(block).unwrap();
+        }
+
+        // 中位时间为 base + 20，早于链顶但晚于中位时间的区块有效
+        assert_eq!(blockchain.median_time_past(), Some(base + 20));
+        let block = block_at(&blockchain, base + 20);
+        assert_eq!(
+            blockchain.update_with_block(block),
+            Err(BlockValidationErr::TimestampNotAfterMedianTimePast)
+        );
+        let block = block_at(&blockchain, base + 21);
+        blockchain.update_with_block(block).unwrap();
+
+        // 超前于网络时间过多的区块无效，时间偏移会计入网络时间
+        let future = now() + MAX_FUTURE_DRIFT + 60_000;
+        let block = block_at(&blockchain, future);
+        assert_eq!(
+            blockchain.update_with_block(block.clone()),
+            Err(BlockValidationErr::TimestampTooFarInFuture)
+        );
+        blockchain.set_time_offset(i64::MAX);
+        blockchain.update_with_block(block).unwrap();
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let mut blockchain = Blockchain::new(ChainParams::regtest());
+        blockchain.generate(1, "miner", &mut vec![]).unwrap();
+
+        let large = |receiver_len: usize| {
+            spend([OutPoint { txid: vec![0; 32], index: 0 }], vec![pay(&"x".repeat(receiver_len), 1)])
+        };
+
+        // 模板跳过超限的交易，并在区块装满后停止打包
+        let oversized = large(MAX_TRANSACTION_SIZE);
+        let filler: Vec<Transaction> = (0..11).map(|_| large(MAX_TRANSACTION_SIZE - 100)).collect();
+        let mut candidates = vec![oversized.clone()];
+        candidates.extend(filler);
+        let (block, remaining) = blockchain.block_template("miner", candidates).unwrap();
+        assert!(block.size() <= MAX_BLOCK_SIZE);
+        assert_eq!(block.transactions.len(), 11);
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining[0], oversized);
+
+        let (mut small, _) = blockchain.block_template("miner", vec![]).unwrap();
+        small.transactions.push(oversized);
+        small.mine().unwrap();
+        assert_eq!(
+            blockchain.update_with_block(small),
+            Err(BlockValidationErr::InvalidTransaction(TransactionError::TooLarge))
+        );
+
+        let mut block = block;
+        block.transactions.push(remaining[1].clone());
+        block.mine().unwrap();
+        assert_eq!(blockchain.update_with_block(block), Err(BlockValidationErr::BlockTooLarge));
+    }
+
+    #[test]
+    fn test_package_selection() {
+        let (mut blockchain, blocks) = regtest_chain(3);
+
+        // 低手续费的父交易、高手续费的子交易与中等手续费的独立交易
+        let parent = spend_reward(&blocks[1], "Alice", 49);
+        let (outpoint, _) = parent.outpoints().next().unwrap();
+        let mut child = spend_reward(&blocks[1], "Dave", 39);
+        child.inputs = vec![Input::from(outpoint.clone())];
+        let other = spend_reward(&blocks[2], "Carol", 45);
+
+        let ids = |transactions: &[Transaction]| transactions.iter().map(Transaction::txid).collect::<Vec<_>>();
+        let candidates = vec![other.clone(), parent.clone(), child.clone()];
+        assert_eq!(
+            ids(&blockchain.package_order(candidates.clone())),
+            ids(&[parent.clone(), child.clone(), other.clone()])
+        );
+        assert_eq!(
+            ids(&blockchain.package_order(vec![parent.clone(), other.clone()])),
+            ids(&[other.clone(), parent.clone()])
+        );
+
+        // 子交易必须排在父交易之后；花费未确认输出的相对时间锁尚未到期
+        let (mut reversed, _) = blockchain.block_template("miner", vec![]).unwrap();
+        reversed.transactions.extend([child.clone(), parent.clone()]);
+        reversed.mine().unwrap();
+        assert_eq!(blockchain.update_with_block(reversed), Err(BlockValidationErr::InvalidInput));
+        let mut locked_child = child.clone();
+        locked_child.inputs[0].sequence = Some(RelativeLock::Blocks(0));
+        assert_eq!(
+            blockchain.check_final(&locked_child),
+            Err(BlockValidationErr::SequenceLockNotReached)
+        );
+
+        // 子交易的手续费计入 Coinbase，同一区块中创建并花费的输出不进入 UTXO 集合
+        let mut pending = candidates;
+        let block = blockchain.generate(1, "miner", &mut pending).unwrap().remove(0);
+        assert!(pending.is_empty());
+        assert_eq!(ids(&block.transactions[1..]), ids(&[parent.clone(), child, other.clone()]));
+        assert_eq!(block.transactions[0].output_value(), 50 + 1 + 10 + 5);
+        assert!(blockchain.utxo(&outpoint).is_none());
+        assert_eq!(blockchain.balance("Dave").spendable, 39);
+        // 已确认交易的费率计入手续费估算，区块未满时无需手续费
+        let estimator = blockchain.fee_estimator();
+        assert_eq!(estimator.median_fee_rate(1), Some(fees::fee_rate(5, other.size())));
+        assert_eq!(estimator.estimate(1), Some(0));
+
+        // 父交易未能打包时子交易同样跳过
+        let mut oversized = parent;
+        oversized.outputs[0].receiver = "x".repeat(MAX_TRANSACTION_SIZE);
+        let (outpoint, _) = oversized.outpoints().next().unwrap();
+        let mut dependent = spend_reward(&blocks[2], "Bob", 1);
+        dependent.inputs = vec![Input::from(outpoint)];
+        let (mut template, _) = blockchain.block_template("miner", vec![]).unwrap();
+        let remaining = fill_block(&mut template, vec![oversized, dependent]);
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(template.transactions.len(), 1);
+    }
+
+    #[test]
+    fn test_coinbase_maturity() {
+        let mut params = ChainParams::regtest();
+        params.coinbase_maturity = 3;
+        let mut blockchain = Blockchain::new(params);
+        let blocks = blockchain.generate(1, "Bob", &mut vec![]).unwrap();
+        blockchain.generate(1, "miner", &mut vec![]).unwrap();
+
+        // 创世分配立即可用，新的奖励尚未成熟
+        let balance = blockchain.balance("Bob");
+        assert_eq!(balance, Balance { spendable: 7, immature: 50 });
+
+        let spend = spend_reward(&blocks[0], "carol", 50);
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![spend.clone()]),
+            Err(BlockValidationErr::ImmatureCoinbaseSpend)
+        );
+
+        // 奖励在索引 1 的区块中创建，索引 4 的区块起可以花费
+        blockchain.generate(1, "miner", &mut vec![]).unwrap();
+        assert_eq!(blockchain.balance("Bob"), Balance { spendable: 57, immature: 0 });
+        blockchain.generate(1, "miner", &mut vec![spend]).unwrap();
+        assert_eq!(blockchain.balance("carol").spendable, 50);
+    }
+
+    #[test]
+    fn test_transaction_ids() {
+        // 相同的奖励输出位于不同的 Coinbase 交易中，不会互相覆盖
+        let (mut blockchain, blocks) = regtest_chain(2);
+        assert_ne!(blocks[0].transactions[0].txid(), blocks[1].transactions[0].txid());
+        assert_eq!(blockchain.balance("miner").spendable, 100);
+
+        // Coinbase 交易必须提交本区块的索引
+        let (mut block, _) = blockchain.block_template("miner", vec![]).unwrap();
+        block.transactions[0].coinbase.as_mut().unwrap().height = 1;
+        block.mine().unwrap();
+        assert_eq!(
+            blockchain.update_with_block(block),
+            Err(BlockValidationErr::InvalidCoinbaseTransaction)
+        );
+
+        // 区块声明的哈希必须与区块头一致：挖矿后修改交易或直接伪造哈希都会被拒绝
+        let (mut block, _) = blockchain.block_template("miner", vec![]).unwrap();
+        block.mine().unwrap();
+        let mut forged = block.clone();
+        forged.transactions[0].coinbase.as_mut().unwrap().extra = b"forged".to_vec();
+        assert_eq!(blockchain.update_with_block(forged), Err(BlockValidationErr::MismatchedHash));
+        let mut forged = block.clone();
+        forged.hash = blockchain.tip().unwrap().hash.clone();
+        assert_eq!(blockchain.update_with_block(forged), Err(BlockValidationErr::MismatchedHash));
+
+        // 同一区块中不可包含重复的交易
+        let spend = spend_reward(&blocks[0], "Bob", 50);
+        let (mut block, _) = blockchain.block_template("miner", vec![spend.clone()]).unwrap();
+        block.transactions.push(spend.clone());
+        block.mine().unwrap();
+        assert_eq!(
+            blockchain.update_with_block(block),
+            Err(BlockValidationErr::DuplicateTransaction)
+        );
+
+        // 只有第一个交易可以是 Coinbase 交易
+        let (mut block, _) = blockchain.block_template("miner", vec![]).unwrap();
+        let mut extra = block.transactions[0].clone();
+        extra.coinbase.as_mut().unwrap().extra = b"second".to_vec();
+        block.transactions.push(extra);
+        block.mine().unwrap();
+        assert_eq!(
+            blockchain.update_with_block(block),
+            Err(BlockValidationErr::InvalidCoinbaseTransaction)
+        );
+
+        blockchain.generate(1, "miner", &mut vec![spend]).unwrap();
+        assert_eq!(blockchain.balance("Bob").spendable, 57);
+    }
+
+    #[test]
+    fn test_script_locks() {
+        use crate::script::{sha256, Lock};
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let (mut blockchain, blocks) = regtest_chain(3);
+
+        // 把奖励锁定到公钥、原像与区块高度
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let mut lock = spend_reward(&blocks[0], "locked", 10);
+        lock.outputs = vec![
+            Output::pay_to_public_key_hash(script::public_key_hash(key.verifying_key().as_bytes()), 20),
+            Output {
+                receiver: "htlc".to_owned(),
+                value: 20,
+                asset: None,
+                lock: Some(Lock::HashLock { hash: sha256(b"secret") }),
+            },
+            Output {
+                receiver: "later".to_owned(),
+                value: 10,
+                asset: None,
+                lock: Some(Lock::AfterHeight { height: 10 }),
+            },
+        ];
+        let locked: Vec<OutPoint> = lock.outpoints().map(|(outpoint, _)| outpoint).collect();
+        blockchain.generate(1, "miner", &mut vec![lock]).unwrap();
+        let to_bob = |outpoint: &OutPoint| spend([outpoint.clone()], vec![pay("Bob", 10)]);
+
+        // 签名覆盖交易 ID，错误的签名或缺少解锁数据都会被拒绝
+        let mut signed = to_bob(&locked[0]);
+        let signature = key.sign(&signed.txid()).to_bytes().to_vec();
+        signed.inputs[0].unlock = Unlock::Signature {
+            public_key: key.verifying_key().to_bytes().to_vec(),
+            signature: signature.clone(),
+        };
+        let mut forged = signed.clone();
+        forged.outputs[0].value = 20;
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![forged]),
+            Err(BlockValidationErr::ScriptFailed(ScriptError::InvalidSignature))
+        );
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![to_bob(&locked[0])]),
+            Err(BlockValidationErr::ScriptFailed(ScriptError::UnlockMismatch))
+        );
+
+        let mut claim = to_bob(&locked[1]);
+        claim.inputs[0].unlock = Unlock::Preimage {
+            preimage: b"secret".to_vec(),
+        };
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![to_bob(&locked[2])]),
+            Err(BlockValidationErr::ScriptFailed(ScriptError::HeightLockNotReached))
+        );
+        blockchain.generate(1, "miner", &mut vec![signed, claim]).unwrap();
+        assert_eq!(blockchain.balance("Bob").spendable, 27);
+
+        // 未加锁的输出不接受解锁数据
+        let mut extra = spend_reward(&blocks[1], "Bob", 50);
+        extra.inputs[0].unlock = Unlock::Preimage { preimage: vec![] };
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![extra]),
+            Err(BlockValidationErr::ScriptFailed(ScriptError::UnlockMismatch))
+        );
+
+        while blockchain.height() < 10 {
+            blockchain.generate(1, "miner", &mut vec![]).unwrap();
+        }
+        blockchain.generate(1, "miner", &mut vec![to_bob(&locked[2])]).unwrap();
+        assert_eq!(blockchain.balance("Bob").spendable, 37);
+    }
+
+    #[test]
+    fn test_multisig_spend() {
+        use crate::psbt::Psbt;
+        use crate::script::Lock;
+        use crate::wallet::{MultisigSpend, Wallet};
+        use ed25519_dalek::SigningKey;
+
+        let (mut blockchain, blocks) = regtest_chain(2);
+
+        // 奖励转入 2-of-3 多重签名输出
+        let signers: Vec<Wallet> = (1..=3)
+            .map(|seed| {
+                let mut wallet = Wallet::new();
+                wallet.add_key(SigningKey::from_bytes(&[seed; 32]));
+                wallet
+            })
+            .collect();
+        let public_keys: Vec<_> = signers.iter().flat_map(Wallet::public_keys).collect();
+        let lock = Lock::multisig(2, &public_keys);
+        let mut fund = spend_reward(&blocks[0], "treasury", 50);
+        fund.outputs = vec![Output::locked(lock.clone(), 50)];
+        let (outpoint, _) = fund.outpoints().next().unwrap();
+        blockchain.generate(1, "miner", &mut vec![fund]).unwrap();
+        assert_eq!(blockchain.balance(&lock.address()).spendable, 50);
+
+        let transaction = spend([outpoint], vec![pay("Bob", 50)]);
+        let mut spend = MultisigSpend::new(transaction.clone(), vec![lock]).unwrap();
+        signers[1].sign_multisig(&mut spend);
+
+        // 只有一个签名时无法花费
+        let mut partial = spend.transaction.clone();
+        partial.inputs[0].unlock = Unlock::Multisig {
+            signatures: spend.signatures[0].clone(),
+        };
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![partial]),
+            Err(BlockValidationErr::ScriptFailed(ScriptError::NotEnoughSignatures))
+        );
+
+        signers[2].sign_multisig(&mut spend);
+        let signed = spend.finalize().unwrap();
+
+        // 部分签名交易补充链上 UTXO 后由同样的签名方签名，得到相同的最终交易
+        let mut psbt = Psbt::new(transaction);
+        assert_eq!(psbt.update(&blockchain), 0);
+        assert_eq!(psbt.sign(&signers[1]) + psbt.sign(&signers[2]), 2);
+        psbt.finalize().unwrap();
+        assert_eq!(psbt.extract(), Ok(signed.clone()));
+
+        blockchain.generate(1, "miner", &mut vec![signed]).unwrap();
+        assert_eq!(blockchain.balance("Bob").spendable, 57);
+    }
+
+    #[test]
+    fn test_issued_assets() {
+        use crate::script::Lock;
+        use crate::transaction::Issuance;
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let (mut blockchain, blocks) = regtest_chain(3);
+
+        // 发行方为公钥哈希锁定，发行交易需带有发行方的签名
+        let key = SigningKey::from_bytes(&[9; 32]);
+        let issuer = Lock::PubKeyHash {
+            hash: script::public_key_hash(key.verifying_key().as_bytes()),
+        };
+        let sign = |mut transaction: Transaction, key: &SigningKey| {
+            let signature = key.sign(&transaction.txid()).to_bytes().to_vec();
+            transaction.issuance.as_mut().unwrap().unlock = Unlock::Signature {
+                public_key: key.verifying_key().to_bytes().to_vec(),
+                signature,
+            };
+            transaction
+        };
+        let issuance = Issuance {
+            issuer: issuer.clone(),
+            name: "points".to_owned(),
+            unlock: Unlock::None,
+        };
+        let asset = issuance.asset_id();
+        let token = |receiver: &str, value| Output {
+            receiver: receiver.to_owned(),
+            value,
+            asset: Some(asset.clone()),
+            lock: None,
+        };
+        let mut issue = spend_reward(&blocks[0], "issuer", 50);
+        issue.outputs.push(token("Bob", 100));
+        issue.issuance = Some(issuance.clone());
+
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![issue.clone()]),
+            Err(BlockValidationErr::ScriptFailed(ScriptError::UnlockMismatch))
+        );
+        assert!(matches!(
+            blockchain.generate(1, "miner", &mut vec![sign(issue.clone(), &SigningKey::from_bytes(&[8; 32]))]),
+            Err(BlockValidationErr::ScriptFailed(_))
+        ));
+        let issue = sign(issue, &key);
+        let (token_outpoint, _) = issue.outpoints().nth(1).unwrap();
+        blockchain.generate(1, "miner", &mut vec![issue]).unwrap();
+        let height = blockchain.height() as u32 - 1;
+
+        // 资产输出不计入原生币余额
+        assert_eq!(blockchain.balance("issuer").spendable, 50);
+        assert_eq!(blockchain.balance("Bob").spendable, 7);
+        assert_eq!(blockchain.asset_balances("Bob"), BTreeMap::from([(asset.clone(), 100)]));
+        let info = blockchain.asset(&asset).unwrap();
+        assert_eq!((info.name.as_str(), &info.issuer, info.issued, info.height), ("points", &issuer, 100, height));
+
+        // 转移时资产数量守恒
+        let transfer = |outputs: Vec<Output>| spend([token_outpoint.clone()], outputs);
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![transfer(vec![token("Carol", 61), token("Bob", 40)])]),
+            Err(BlockValidationErr::AssetNotConserved)
+        );
+        assert_eq!(
+            blockchain.generate(1, "miner", &mut vec![transfer(vec![token("Carol", 60)])]),
+            Err(BlockValidationErr::AssetNotConserved)
+        );
+        blockchain
+            .generate(1, "miner", &mut vec![transfer(vec![token("Carol", 60), token("Bob", 40)])])
+            .unwrap();
+        assert_eq!(blockchain.asset_balances("Carol"), BTreeMap::from([(asset.clone(), 60)]));
+        assert_eq!(blockchain.asset_balances("Bob"), BTreeMap::from([(asset.clone(), 40)]));
+
+        // 发行方可以增发，注册信息保留首次发行的高度
+        let mut reissue = spend_reward(&blocks[1], "issuer", 50);
+        reissue.outputs.push(token("Carol", 25));
+        reissue.issuance = Some(issuance);
+        blockchain.generate(1, "miner", &mut vec![sign(reissue, &key)]).unwrap();
+        assert_eq!(blockchain.asset_balances("Carol"), BTreeMap::from([(asset.clone(), 85)]));
+        let info = blockchain.asset(&asset).unwrap();
+        assert_eq!((info.issued, info.height), (125, height));
+
+        // 快照附带发行记录并计入承诺，从快照恢复后仍可查询与增发
+        let snapshot = blockchain.snapshot();
+        let decoded: UtxoSnapshot = encoding::decode(&encoding::encode(&snapshot)).unwrap();
+        let mut restored = Blockchain::from_snapshot(decoded, blockchain.params().clone()).unwrap();
+        assert_eq!(restored.asset(&asset), blockchain.asset(&asset));
+        assert_eq!(restored.utxo_commitment(), blockchain.utxo_commitment());
+        let mut reissue = spend_reward(&blocks[2], "issuer", 50);
+        reissue.outputs.push(token("Carol", 5));
+        reissue.issuance = Some(Issuance {
+            issuer: issuer.clone(),
+            name: "points".to_owned(),
+            unlock: Unlock::None,
+        });
+        restored.generate(1, "miner", &mut vec![sign(reissue, &key)]).unwrap();
+        let info = restored.asset(&asset).unwrap();
+        assert_eq!((info.issued, info.height), (130, height));
+
+        let mut tampered = snapshot;
+        tampered.assets[0].info.issued = 1;
+        assert_eq!(
+            Blockchain::from_snapshot(tampered, blockchain.params().clone()).err(),
+            Some(SnapshotError::CommitmentMismatch)
+        );
+    }
+
+    #[test]
+    fn test_time_locks() {
+        let (mut blockchain, blocks) = regtest_chain(3);
+
+        // 绝对时间锁：只能打包进索引不小于 6 的区块
+        let mut absolute = spend_reward(&blocks[0], "Bob", 50);
+        absolute.lock_time = Some(LockTime::Height(6));
+        assert_eq!(blockchain.check_final(&absolute), Err(BlockValidationErr::LockTimeNotReached));
+        let (mut block, _) = blockchain.block_template("miner", vec![]).unwrap();
+        block.transactions.push(absolute.clone());
+        block.mine().unwrap();
+        assert_eq!(blockchain.update_with_block(block), Err(BlockValidationErr::LockTimeNotReached));
+
+        // 相对时间锁：输出在索引 2 的区块中创建，需要经过 5 个区块
+        let mut relative = spend_reward(&blocks[1], "carol", 50);
+        relative.inputs[0].sequence = Some(RelativeLock::Blocks(5));
+
+        // 模板跳过未到期的交易，到期后依次打包
+        let mut pending = vec![absolute, relative];
+        blockchain.generate(2, "miner", &mut pending).unwrap();
+        assert_eq!(pending.len(), 2);
+        blockchain.generate(1, "miner", &mut pending).unwrap();
+        assert_eq!(blockchain.height(), 7);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(blockchain.balance("Bob").spendable, 57);
+        blockchain.generate(1, "miner", &mut pending).unwrap();
+        assert!(pending.is_empty());
+        assert_eq!(blockchain.balance("carol").spendable, 50);
+
+        // 基于时间的锁与过去中位时间比较
+        let median = blockchain.median_time_past().unwrap() as u64;
+        let mut timed = spend_reward(&blocks[2], "dave", 50);
+        timed.lock_time = Some(LockTime::Timestamp(median + 1));
+        assert_eq!(blockchain.check_final(&timed), Err(BlockValidationErr::LockTimeNotReached));
+        timed.lock_time = Some(LockTime::Timestamp(median));
+        assert_eq!(blockchain.check_final(&timed), Ok(()));
+
+        let created = blocks[2].timestamp as u64;
+        timed.inputs[0].sequence = Some(RelativeLock::Millis(median - created + 1));
+        assert_eq!(blockchain.check_final(&timed), Err(BlockValidationErr::SequenceLockNotReached));
+        timed.inputs[0].sequence = Some(RelativeLock::Millis(median - created));
+        blockchain.generate(1, "miner", &mut vec![timed]).unwrap();
+        assert_eq!(blockchain.balance("dave").spendable, 50);
+    }
+
+    #[test]
+    fn test_data_outputs() {
+        let (mut blockchain, blocks) = regtest_chain(2);
+
+        // 数据输出随交易确认，但不进入 UTXO 集合
+        let mut memo = spend_reward(&blocks[0], "Bob", 49);
+        memo.outputs.push(Output::data_carrier(b"INV-2024-0042".to_vec()));
+        let utxos = blockchain.utxo_count();
+        let block = blockchain.generate(1, "miner", &mut vec![memo.clone()]).unwrap().remove(0);
+        assert_eq!(blockchain.utxo_count(), utxos + 1);
+        let (outpoint, _) = memo.outpoints().nth(1).unwrap();
+        assert!(blockchain.utxo(&outpoint).is_none());
+
+        // 按数据查找所在的交易
+        assert_eq!(
+            blockchain.data_records(b"INV-2024-0042"),
+            &[DataRecord {
+                txid: memo.txid(),
+                index: 1,
+                height: block.index,
+                timestamp: block.timestamp,
+            }]
+        );
+        assert!(blockchain.data_records(b"INV-2024-0043").is_empty());
+
+        // 数据输出不可花费
+        let (mut block, _) = blockchain.block_template("miner", vec![]).unwrap();
+        block.transactions.push(spend([outpoint], vec![pay("carol", 1)]));
+        block.mine().unwrap();
+        assert_eq!(blockchain.update_with_block(block), Err(BlockValidationErr::InvalidInput));
     }
 }
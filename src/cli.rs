@@ -0,0 +1,293 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use crate::logging::LogFormat;
+use blockchainlib::params::Network;
+
+// 定义命令行参数
+#[derive(Debug, Parser)]
+#[command(name = "blockchain", about = "JL-transachain 区块链节点")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// HTTP 服务监听地址
+    #[arg(long, default_value = "0.0.0.0:8080")]
+    pub listen: String,
+
+    /// API Key 配置文件（JSON），未配置时所有写接口均被拒绝
+    #[arg(long)]
+    pub api_keys: Option<PathBuf>,
+
+    /// 允许跨域访问的来源，可重复指定，与配置文件中的列表合并
+    #[arg(long = "cors-origin")]
+    pub cors_origins: Vec<String>,
+
+    /// 每个客户端 IP 每秒允许的请求数
+    #[arg(long, default_value_t = 5.0)]
+    pub ip_rate: f64,
+
+    /// 每个客户端 IP 的突发请求上限
+    #[arg(long, default_value_t = 20)]
+    pub ip_burst: u32,
+
+    /// 每个 API Key 每秒允许的请求数
+    #[arg(long, default_value_t = 10.0)]
+    pub key_rate: f64,
+
+    /// 每个 API Key 的突发请求上限
+    #[arg(long, default_value_t = 50)]
+    pub key_burst: u32,
+
+    /// JSON 请求体的最大字节数
+    #[arg(long, default_value_t = 256 * 1024)]
+    pub max_payload_bytes: usize,
+
+    /// 单次请求最多包含的交易数
+    #[arg(long, default_value_t = 100)]
+    pub max_transactions: usize,
+
+    /// P2P 监听地址，未指定时不启动 P2P 节点
+    #[arg(long)]
+    pub p2p_listen: Option<String>,
+
+    /// 启动时连接的节点地址，可重复指定
+    #[arg(long = "peer")]
+    pub peers: Vec<String>,
+
+    /// 数据目录
+    #[arg(long, default_value = "data")]
+    pub data_dir: PathBuf,
+
+    /// 网络：mainnet、testnet 或 regtest
+    #[arg(long, default_value = "mainnet")]
+    pub network: Network,
+
+    /// 自定义创世文件（JSON），不能与 --network 同时使用
+    #[arg(long, conflicts_with = "network")]
+    pub genesis: Option<PathBuf>,
+
+    /// 模拟时间（毫秒时间戳），仅回归测试网可用
+    #[arg(long)]
+    pub mock_time: Option<u64>,
+
+    /// 数据目录为空时从该 UTXO 快照启动
+    #[arg(long)]
+    pub snapshot: Option<PathBuf>,
+
+    /// 期望的快照承诺（十六进制），与快照不一致时拒绝启动
+    #[arg(long, requires = "snapshot")]
+    pub snapshot_commitment: Option<String>,
+
+    /// 不校验快照承诺直接信任快照（不安全，仅用于测试）
+    #[arg(long, requires = "snapshot", conflicts_with = "snapshot_commitment")]
+    pub allow_unverified_snapshot: bool,
+
+    /// 历史区块文件，启动后在后台重放并与快照承诺比对
+    #[arg(long)]
+    pub verify_blocks: Option<PathBuf>,
+
+    /// 就绪检查允许落后于最高节点的区块数
+    #[arg(long, default_value_t = 2)]
+    pub ready_max_lag: usize,
+
+    /// 就绪检查要求的最少连接节点数
+    #[arg(long, default_value_t = 0)]
+    pub ready_min_peers: usize,
+
+    /// 日志级别或过滤规则（如 `info`、`blockchainlib=debug`），`RUST_LOG` 优先
+    #[arg(long, default_value = "info")]
+    pub log_level: String,
+
+    /// 日志输出格式
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+}
+
+// 定义子命令
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 导出数据目录中的区块
+    Export {
+        /// 起始区块索引（含）
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+
+        /// 结束区块索引（含），默认导出到链顶
+        #[arg(long)]
+        to: Option<u32>,
+
+        /// 导出格式
+        #[arg(long, value_enum, default_value_t = ExportFormat::Binary)]
+        format: ExportFormat,
+
+        /// 输出文件，默认输出到标准输出
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// 从文件导入区块，逐个重新验证；中断后重新执行即可从断点继续
+    Import {
+        /// 导入文件，格式自动识别
+        file: PathBuf,
+    },
+
+    /// 在回归测试网中立即挖出若干区块
+    Generate {
+        /// 区块数量
+        count: usize,
+
+        /// 接收出块奖励的地址
+        #[arg(default_value = "miner")]
+        address: String,
+    },
+
+    /// 管理本地钱包文件
+    Wallet {
+        #[command(subcommand)]
+        action: WalletCommand,
+    },
+
+    /// 创建、签名与合并部分签名交易，用于离线签名
+    Psbt {
+        #[command(subcommand)]
+        action: PsbtCommand,
+    },
+
+    /// 离线验证存证证明，输出证明指向的区块
+    VerifyProof {
+        /// `GET /notarize/proof/{hash}` 返回的证明（`proof` 字段）
+        proof: PathBuf,
+
+        /// 原始文档，提供时检查其 SHA-256 哈希与证明一致
+        #[arg(long)]
+        document: Option<PathBuf>,
+    },
+
+    /// 计算资产 ID，发行交易中该资产的输出需填写此 ID
+    AssetId {
+        /// 发行方锁定条件（JSON）文件，如 `wallet show` 或 `wallet multisig` 输出中的 `lock`
+        issuer: PathBuf,
+
+        /// 资产名称
+        #[arg(long)]
+        name: String,
+    },
+
+    /// 生成 UTXO 快照
+    Snapshot {
+        /// 快照包含的区块数，默认包含全部区块
+        #[arg(long)]
+        height: Option<u32>,
+
+        /// 快照输出文件
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+}
+
+// 定义钱包子命令
+#[derive(Debug, Subcommand)]
+pub enum WalletCommand {
+    /// 创建钱包文件并随机生成私钥
+    New {
+        /// 钱包文件
+        file: PathBuf,
+
+        /// 生成的私钥数量
+        #[arg(long, default_value_t = 1)]
+        keys: usize,
+    },
+
+    /// 列出钱包中的公钥与对应的 P2PKH 地址
+    Show {
+        /// 钱包文件
+        file: PathBuf,
+    },
+
+    /// 生成 M-of-N 多重签名锁定条件与地址
+    Multisig {
+        /// 需要的签名数量 M
+        #[arg(long)]
+        threshold: u32,
+
+        /// 参与签名的公钥（十六进制），按顺序确定签名序号
+        #[arg(required = true)]
+        public_keys: Vec<String>,
+    },
+}
+
+// 定义部分签名交易子命令，结果写入 `--output` 指定的文件或标准输出
+#[derive(Debug, Subcommand)]
+pub enum PsbtCommand {
+    /// 由未签名交易（JSON）创建部分签名交易
+    Create {
+        /// 交易文件
+        transaction: PathBuf,
+
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// 从数据目录中的 UTXO 集合补充被花费输出的信息
+    Update {
+        /// 部分签名交易文件
+        file: PathBuf,
+
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// 用钱包中的私钥签名，无需访问数据目录
+    Sign {
+        /// 部分签名交易文件
+        file: PathBuf,
+
+        /// 钱包文件
+        #[arg(long)]
+        wallet: PathBuf,
+
+        /// 哈希锁原像，格式为 `输入序号:十六进制原像`，可重复指定
+        #[arg(long = "preimage")]
+        preimages: Vec<String>,
+
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// 合并多个签名方的部分签名交易
+    Combine {
+        /// 部分签名交易文件
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// 根据已收集的签名生成每个输入的解锁数据
+    Finalize {
+        /// 部分签名交易文件
+        file: PathBuf,
+
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// 提取可以提交的交易
+    Extract {
+        /// 部分签名交易文件
+        file: PathBuf,
+
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+// 定义导出格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// 规范二进制编码
+    Binary,
+    /// 每行一个区块的 JSON
+    Json,
+}
@@ -0,0 +1,398 @@
+use crate::cli::{Command, ExportFormat, PsbtCommand, WalletCommand};
+use blockchainlib::notary::{self, NotarizationProof};
+use blockchainlib::psbt::Psbt;
+use blockchainlib::script::{sha256, Lock, Unlock};
+use blockchainlib::transaction::Issuance;
+use blockchainlib::snapshot::SNAPSHOT_FILE_NAME;
+use blockchainlib::store::{self, BlockReader, BLOCK_FILE_MAGIC};
+use blockchainlib::wallet::Wallet;
+use blockchainlib::{Block, BlockStore, Blockchain, ChainParams, Hashable, Transaction, UtxoSnapshot};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::cell::Cell;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// 导入进度的输出间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+// 执行子命令
+pub fn run(command: &Command, data_dir: &Path, params: &ChainParams) -> io::Result<()> {
+    match command {
+        Command::Export {
+            from,
+            to,
+            format,
+            output,
+        } => {
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout().lock()),
+            };
+            export(data_dir, *from, *to, *format, BufWriter::new(writer))
+        }
+        Command::Import { file } => import(data_dir, params, file),
+        Command::Generate { count, address } => generate(data_dir, params, *count, address),
+        Command::Wallet { action } => wallet(action),
+        Command::Psbt { action } => psbt(action, data_dir, params),
+        Command::Snapshot { height, output } => create_snapshot(data_dir, params, *height, output),
+        Command::VerifyProof { proof, document } => verify_proof(proof, document.as_deref()),
+        Command::AssetId { issuer, name } => asset_id(issuer, name),
+    }
+}
+
+// 执行钱包子命令，结果以 JSON 输出到标准输出
+fn wallet(action: &WalletCommand) -> io::Result<()> {
+    let output = match action {
+        WalletCommand::New { file, keys } => {
+            if file.exists() {
+                return Err(io::Error::new(ErrorKind::AlreadyExists, format!("{} already exists", file.display())));
+            }
+            let mut wallet = Wallet::new();
+            for _ in 0..*keys {
+                wallet.generate_key();
+            }
+            wallet.save(file)?;
+            info!(path = %file.display(), keys, "钱包已创建");
+            wallet_keys(&wallet)
+        }
+        WalletCommand::Show { file } => wallet_keys(&Wallet::load(file)?),
+        WalletCommand::Multisig { threshold, public_keys } => {
+            let public_keys = public_keys
+                .iter()
+                .map(|key| {
+                    hex::decode(key)
+                        .ok()
+                        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                        .and_then(|bytes| ed25519_dalek::VerifyingKey::from_bytes(&bytes).ok())
+                        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, format!("invalid public key {}", key)))
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            let lock = Lock::multisig(*threshold, &public_keys);
+            if !lock.is_well_formed() {
+                return Err(io::Error::new(ErrorKind::InvalidInput, "threshold must be between 1 and the number of keys (at most 16)"));
+            }
+            serde_json::json!({ "address": lock.address(), "lock": lock })
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+// 钱包公钥与对应 P2PKH 地址的 JSON
+fn wallet_keys(wallet: &Wallet) -> serde_json::Value {
+    let keys: Vec<_> = wallet
+        .public_keys()
+        .iter()
+        .map(|key| {
+            serde_json::json!({
+                "public_key": hex::encode(key.as_bytes()),
+                "address": Lock::pay_to_public_key(key).address(),
+                "lock": Lock::pay_to_public_key(key),
+            })
+        })
+        .collect();
+    serde_json::json!({ "keys": keys })
+}
+
+// 执行部分签名交易子命令
+fn psbt(action: &PsbtCommand, data_dir: &Path, params: &ChainParams) -> io::Result<()> {
+    let invalid = |e| io::Error::new(ErrorKind::InvalidData, format!("{:?}", e));
+    let (output, value) = match action {
+        PsbtCommand::Create { transaction, output } => {
+            let transaction: Transaction = serde_json::from_slice(&std::fs::read(transaction)?)?;
+            (output, serde_json::to_value(Psbt::new(transaction))?)
+        }
+        PsbtCommand::Update { file, output } => {
+            let mut psbt = read_psbt(file)?;
+            let (_, blockchain) = load_chain(data_dir, params)?;
+            let missing = psbt.update(&blockchain);
+            if missing > 0 {
+                warn!(missing, "部分输入不在 UTXO 集合中");
+            }
+            (output, serde_json::to_value(psbt)?)
+        }
+        PsbtCommand::Sign {
+            file,
+            wallet,
+            preimages,
+            output,
+        } => {
+            let mut psbt = read_psbt(file)?;
+            for preimage in preimages {
+                let (input, bytes) = preimage
+                    .split_once(':')
+                    .and_then(|(input, bytes)| Some((input.parse().ok()?, hex::decode(bytes).ok()?)))
+                    .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, format!("invalid preimage {}", preimage)))?;
+                psbt.add_preimage(input, bytes);
+            }
+            let added = psbt.sign(&Wallet::load(wallet)?);
+            info!(added, "已添加签名");
+            (output, serde_json::to_value(psbt)?)
+        }
+        PsbtCommand::Combine { files, output } => {
+            let mut psbt = read_psbt(&files[0])?;
+            for file in &files[1..] {
+                psbt.combine(&read_psbt(file)?).map_err(invalid)?;
+            }
+            (output, serde_json::to_value(psbt)?)
+        }
+        PsbtCommand::Finalize { file, output } => {
+            let mut psbt = read_psbt(file)?;
+            psbt.finalize().map_err(invalid)?;
+            (output, serde_json::to_value(psbt)?)
+        }
+        PsbtCommand::Extract { file, output } => {
+            let transaction = read_psbt(file)?.extract().map_err(invalid)?;
+            (output, serde_json::to_value(transaction)?)
+        }
+    };
+    let json = serde_json::to_string_pretty(&value)?;
+    match output {
+        Some(path) => std::fs::write(path, json + "\n"),
+        None => {
+            println!("{}", json);
+            Ok(())
+        }
+    }
+}
+
+// 读取部分签名交易文件并检查格式
+fn read_psbt(path: &Path) -> io::Result<Psbt> {
+    let psbt: Psbt = serde_json::from_slice(&std::fs::read(path)?)?;
+    psbt.validate()
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{}: {:?}", path.display(), e)))?;
+    Ok(psbt)
+}
+
+// 离线验证存证证明，不读取数据目录；结果以 JSON 输出到标准输出
+fn verify_proof(path: &Path, document: Option<&Path>) -> io::Result<()> {
+    let proof: NotarizationProof = serde_json::from_slice(&std::fs::read(path)?)?;
+    if let Some(document) = document
+        && sha256(&std::fs::read(document)?) != proof.document
+    {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{} does not match the notarized hash", document.display()),
+        ));
+    }
+    let attestation = notary::verify(&proof)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{}: {:?}", path.display(), e)))?;
+    let output = serde_json::json!({ "document": hex::encode(&proof.document), "attestation": attestation });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+// 计算资产 ID，不检查名称与锁定条件是否满足发行规则
+fn asset_id(issuer: &Path, name: &str) -> io::Result<()> {
+    let issuance = Issuance {
+        issuer: serde_json::from_slice(&std::fs::read(issuer)?)?,
+        name: name.to_owned(),
+        unlock: Unlock::None,
+    };
+    let output = serde_json::json!({
+        "asset": hex::encode(issuance.asset_id()),
+        "issuer_address": issuance.issuer.address(),
+        "name": issuance.name,
+    });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+// 加载数据目录：存在快照时先从快照恢复，再重放快照之后的区块；首次启动时写入创世区块
+pub fn load_chain(data_dir: &Path, params: &ChainParams) -> io::Result<(BlockStore, Blockchain)> {
+    let mut store = BlockStore::open(data_dir)?;
+    let snapshot_path = data_dir.join(SNAPSHOT_FILE_NAME);
+    let base = if snapshot_path.exists() {
+        let snapshot = UtxoSnapshot::load(&snapshot_path)?;
+        info!(height = snapshot.height, commitment = %hex::encode(&snapshot.commitment), "从 UTXO 快照恢复");
+        Blockchain::from_snapshot(snapshot, params.clone())
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("invalid snapshot: {:?}", e)))?
+    } else {
+        Blockchain::new(params.clone())
+    };
+    let mut blockchain = store.replay_onto(base)?;
+    if blockchain.height() == 0 {
+        blockchain
+            .update_with_block(params.genesis.clone())
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("invalid genesis block: {:?}", e)))?;
+        store.append(&params.genesis)?;
+        info!(network = %params.network, hash = %hex::encode(&params.genesis.hash), "已写入创世区块");
+    }
+    Ok((store, blockchain))
+}
+
+// 打开区块文件，根据文件头自动识别二进制或 JSON 格式
+pub fn read_blocks<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn Iterator<Item = io::Result<Block>> + 'a>> {
+    if reader.fill_buf()?.starts_with(BLOCK_FILE_MAGIC) {
+        Ok(Box::new(BlockReader::new(reader)?))
+    } else {
+        Ok(Box::new(
+            reader
+                .lines()
+                .filter(|line| !line.as_ref().is_ok_and(|l| l.trim().is_empty()))
+                .map(|line| serde_json::from_str::<Block>(&line?).map_err(io::Error::from)),
+        ))
+    }
+}
+
+// 在回归测试网中挖出区块并写入存储
+fn generate(data_dir: &Path, params: &ChainParams, count: usize, address: &str) -> io::Result<()> {
+    if !params.is_regtest() {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "generate is only available on regtest"));
+    }
+    let (mut store, mut blockchain) = load_chain(data_dir, params)?;
+    let blocks = blockchain
+        .generate(count, address, &mut vec![])
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("failed to generate blocks: {:?}", e)))?;
+    for block in &blocks {
+        store.append(block)?;
+        println!("{}", hex::encode(&block.hash));
+    }
+    info!(count, height = blockchain.height(), "区块已生成");
+    Ok(())
+}
+
+// 重放前 `height` 个区块并生成 UTXO 快照
+fn create_snapshot(data_dir: &Path, params: &ChainParams, height: Option<u32>, output: &Path) -> io::Result<()> {
+    let store = BlockStore::open(data_dir)?;
+    let mut blockchain = Blockchain::new(params.clone());
+    for block in store.blocks()? {
+        let block = block?;
+        if height.is_some_and(|height| block.index >= height) {
+            break;
+        }
+        let index = block.index;
+        blockchain
+            .update_with_block(block)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("block {} is invalid: {:?}", index, e)))?;
+    }
+    if height.is_some_and(|height| blockchain.height() != height as usize) {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "chain is shorter than the requested height"));
+    }
+
+    let snapshot = blockchain.snapshot();
+    snapshot.save(output)?;
+    info!(
+        height = snapshot.height,
+        utxos = snapshot.utxos.len(),
+        commitment = %hex::encode(&snapshot.commitment),
+        "UTXO 快照已生成"
+    );
+    Ok(())
+}
+
+// 按区块索引范围导出
+fn export(data_dir: &Path, from: u32, to: Option<u32>, format: ExportFormat, mut writer: impl Write) -> io::Result<()> {
+    let store = BlockStore::open(data_dir)?;
+    if format == ExportFormat::Binary {
+        store::write_header(&mut writer)?;
+    }
+
+    let mut exported = 0;
+    for block in store.blocks()? {
+        let block = block?;
+        if block.index < from {
+            continue;
+        }
+        if to.is_some_and(|to| block.index > to) {
+            break;
+        }
+
+        match format {
+            ExportFormat::Binary => store::write_block(&mut writer, &block)?,
+            ExportFormat::Json => {
+                serde_json::to_writer(&mut writer, &block)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        exported += 1;
+    }
+    writer.flush()?;
+
+    info!(exported, from, to, "区块导出完成");
+    Ok(())
+}
+
+// 统计已读取字节数，用于输出导入进度
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+// 导入区块：已存在的区块校验哈希后跳过，新区块经 `update_with_block` 验证后写入存储
+fn import(data_dir: &Path, params: &ChainParams, path: &Path) -> io::Result<()> {
+    let (mut store, mut blockchain) = load_chain(data_dir, params)?;
+    let start_height = blockchain.height();
+    info!(height = start_height, file = %path.display(), "开始导入区块");
+
+    let total = std::fs::metadata(path)?.len();
+    let read = Rc::new(Cell::new(0));
+    let mut reader = BufReader::new(CountingReader {
+        inner: File::open(path)?,
+        count: read.clone(),
+    });
+
+    let blocks = read_blocks(&mut reader)?;
+    let mut imported = 0;
+    let mut skipped = 0;
+    let mut last_report = Instant::now();
+    for block in blocks {
+        let block = block?;
+        import_block(&mut blockchain, &mut store, block, &mut imported, &mut skipped)?;
+
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            info!(
+                imported,
+                skipped,
+                height = blockchain.height(),
+                progress = format!("{:.1}%", read.get() as f64 * 100.0 / total.max(1) as f64),
+                "导入进度"
+            );
+            last_report = Instant::now();
+        }
+    }
+
+    info!(imported, skipped, height = blockchain.height(), "区块导入完成");
+    Ok(())
+}
+
+fn import_block(
+    blockchain: &mut Blockchain,
+    store: &mut BlockStore,
+    block: Block,
+    imported: &mut usize,
+    skipped: &mut usize,
+) -> io::Result<()> {
+    let index = block.index as usize;
+
+    // 已导入的区块：重新计算的哈希一致则跳过，实现断点续传；快照之前的区块直接跳过
+    if index < blockchain.height() {
+        if blockchain.block(index).is_some_and(|existing| existing.hash != block.hash()) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("block {} conflicts with the local chain", index),
+            ));
+        }
+        *skipped += 1;
+        return Ok(());
+    }
+
+    blockchain.update_with_block(block.clone()).map_err(|e| {
+        warn!(index, error = ?e, "区块验证失败");
+        io::Error::new(ErrorKind::InvalidData, format!("block {} is invalid: {:?}", index, e))
+    })?;
+    store.append(&block)?;
+    *imported += 1;
+    Ok(())
+}
@@ -0,0 +1,339 @@
+use std::fmt;
+
+// 当前编码版本，写在每个编码结果的首字节；任何类型的编码布局变化都必须递增，
+// 使旧版本写入的数据被整体拒绝，而不是按新布局错位解析。
+// 版本 2：交易输入引用输出位置，输出与输入带锁定/解锁脚本、时间锁，支持数据输出、资产与资产发行
+// 版本 3：UTXO 快照附带资产发行记录
+pub const ENCODING_VERSION: u8 = 3;
+
+// 定义解码错误
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    UnsupportedVersion(u8),
+    InvalidUtf8,
+    TrailingBytes,
+    InvalidTag(u8),
+    NestingTooDeep,
+    // 区块编码中的哈希与区块头的哈希不一致
+    MismatchedHash,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for std::io::Error {
+    fn from(e: DecodeError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+// 定义编码器：定长整数使用小端序，变长字段使用 u32 长度前缀
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put_u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    pub fn put_u32(&mut self, v: u32) {
+        self.buf.extend(v.to_le_bytes());
+    }
+
+    pub fn put_u64(&mut self, v: u64) {
+        self.buf.extend(v.to_le_bytes());
+    }
+
+    pub fn put_u128(&mut self, v: u128) {
+        self.buf.extend(v.to_le_bytes());
+    }
+
+    // 写入带长度前缀的字节串
+    pub fn put_bytes(&mut self, v: &[u8]) {
+        self.put_len(v.len());
+        self.buf.extend(v);
+    }
+
+    // 写入带长度前缀的 UTF-8 字符串
+    pub fn put_str(&mut self, v: &str) {
+        self.put_bytes(v.as_bytes());
+    }
+
+    // 写入带长度前缀的序列
+    pub fn put_seq<T: Encode>(&mut self, items: &[T]) {
+        self.put_len(items.len());
+        for item in items {
+            item.encode(self);
+        }
+    }
+
+    fn put_len(&mut self, len: usize) {
+        let len = u32::try_from(len).expect("encoded field exceeds u32::MAX bytes");
+        self.put_u32(len);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+// 定义解码器
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Decoder { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self.pos.checked_add(n).ok_or(DecodeError::UnexpectedEnd)?;
+        let slice = self.data.get(self.pos..end).ok_or(DecodeError::UnexpectedEnd)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn get_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take_array()?))
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take_array()?))
+    }
+
+    pub fn get_u128(&mut self) -> Result<u128, DecodeError> {
+        Ok(u128::from_le_bytes(self.take_array()?))
+    }
+
+    pub fn get_bytes(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let len = self.get_u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    pub fn get_str(&mut self) -> Result<String, DecodeError> {
+        String::from_utf8(self.get_bytes()?).map_err(|_| DecodeError::InvalidUtf8)
+    }
+
+    pub fn get_seq<T: Decode>(&mut self) -> Result<Vec<T>, DecodeError> {
+        let len = self.get_u32()? as usize;
+        // 每个元素至少占用一个字节，避免恶意长度导致预分配过大
+        let mut items = Vec::with_capacity(len.min(self.remaining()));
+        for _ in 0..len {
+            items.push(T::decode(self)?);
+        }
+        Ok(items)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+// 可编码类型
+pub trait Encode {
+    fn encode(&self, enc: &mut Encoder);
+}
+
+// 可解码类型
+pub trait Decode: Sized {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError>;
+}
+
+// 字节串（如哈希）作为序列元素时同样带长度前缀
+impl Encode for Vec<u8> {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_bytes(self);
+    }
+}
+
+impl Decode for Vec<u8> {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        dec.get_bytes()
+    }
+}
+
+// 编码为带版本号的字节数组
+pub fn encode<T: Encode + ?Sized>(value: &T) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    enc.put_u8(ENCODING_VERSION);
+    value.encode(&mut enc);
+    enc.into_bytes()
+}
+
+// 从带版本号的字节数组解码，要求恰好消耗全部字节
+pub fn decode<T: Decode>(bytes: &[u8]) -> Result<T, DecodeError> {
+    let mut dec = Decoder::new(bytes);
+    let version = dec.get_u8()?;
+    if version != ENCODING_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    let value = T::decode(&mut dec)?;
+    if dec.remaining() != 0 {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::Unlock;
+    use crate::transaction::{Input, Output};
+    use crate::{Block, Hashable, Transaction};
+
+    fn output(receiver: &str, value: u64) -> Output {
+        Output {
+            receiver: receiver.to_owned(),
+            value,
+            asset: None,
+            lock: None,
+        }
+    }
+
+    #[test]
+    fn test_integer_layout() {
+        let mut enc = Encoder::new();
+        enc.put_u32(0x12345678);
+        enc.put_bytes(b"ab");
+        assert_eq!(enc.into_bytes(), vec![0x78, 0x56, 0x34, 0x12, 2, 0, 0, 0, b'a', b'b']);
+    }
+
+    // 旧版本写入的区块与快照（回归测试网的创世区块及其快照）按版本号拒绝
+    #[test]
+    fn test_previous_version_fixture() {
+        let bytes = include_bytes!("../tests/fixtures/block-v1.bin");
+        assert_eq!(bytes[0], 1);
+        assert_eq!(decode::<Block>(bytes), Err(DecodeError::UnsupportedVersion(1)));
+
+        let bytes = include_bytes!("../tests/fixtures/snapshot-v2.bin");
+        assert_eq!(bytes[0], ENCODING_VERSION - 1);
+        assert_eq!(
+            decode::<crate::UtxoSnapshot>(bytes),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn test_output_round_trip() {
+        let out = output("Alice", 50);
+        let decoded: Output = decode(&encode(&out)).unwrap();
+        assert_eq!(decoded, out);
+    }
+
+    #[test]
+    fn test_transaction_round_trip() {
+        let tx = Transaction {
+            inputs: vec![Input {
+                txid: vec![3; 32],
+                index: 1,
+                unlock: Unlock::None,
+                sequence: None,
+            }],
+            outputs: vec![output("Bob", 30), output("Alice", 20)],
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        };
+        let decoded: Transaction = decode(&encode(&tx)).unwrap();
+        assert_eq!(decoded, tx);
+
+        let mut tx = Transaction::coinbase(7, vec![output("Alice", 50)]);
+        tx.coinbase.as_mut().unwrap().extra = b"extra".to_vec();
+        let decoded: Transaction = decode(&encode(&tx)).unwrap();
+        assert_eq!(decoded, tx);
+        assert_eq!(decoded.hash(), tx.hash());
+    }
+
+    #[test]
+    fn test_block_round_trip() {
+        let mut block = Block::new(
+            1,
+            1234,
+            vec![7; 32],
+            vec![Transaction::coinbase(1, vec![output("Chris", 536)])],
+            0x00ffffffffffffffffffffffffffffff,
+        );
+        block.mine().unwrap();
+
+        let decoded: Block = decode(&encode(&block)).unwrap();
+        assert_eq!(decoded, block);
+        assert_eq!(decoded.hash(), block.hash);
+
+        // 编码中的哈希不是区块头的哈希时拒绝解码
+        let mut forged = block.clone();
+        forged.nonce += 1;
+        assert_eq!(decode::<Block>(&encode(&forged)), Err(DecodeError::MismatchedHash));
+    }
+
+    #[test]
+    fn test_field_boundaries_are_unambiguous() {
+        // 旧编码仅拼接字节，输入与输出之间没有分界，两笔交易会产生相同的原像
+        let input = Input {
+            txid: encode(&output("Alice", 50)),
+            index: 0,
+            unlock: Unlock::None,
+            sequence: None,
+        };
+        let spend = Transaction {
+            inputs: vec![input],
+            outputs: vec![],
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        };
+        let coinbase = Transaction {
+            inputs: vec![],
+            outputs: vec![output("Alice", 50)],
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        };
+        assert_ne!(spend.hash(), coinbase.hash());
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let bytes = encode(&output("Alice", 50));
+
+        assert_eq!(decode::<Output>(&bytes[..bytes.len() - 1]), Err(DecodeError::UnexpectedEnd));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = 0xff;
+        assert_eq!(decode::<Output>(&wrong_version), Err(DecodeError::UnsupportedVersion(0xff)));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode::<Output>(&trailing), Err(DecodeError::TrailingBytes));
+
+        // 长度前缀远超实际数据时不应预分配
+        assert_eq!(
+            decode::<Transaction>(&[ENCODING_VERSION, 0xff, 0xff, 0xff, 0xff]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use crate::blockchain::{MAX_BLOCK_SIZE, MAX_BLOCK_TRANSACTIONS};
+use std::collections::VecDeque;
+
+// 记录费率的最近区块数
+pub const FEE_HISTORY_BLOCKS: usize = 100;
+// 可估算的最大确认目标（区块数）
+pub const MAX_CONFIRMATION_TARGET: usize = 25;
+// 按估算费率提交的交易在过去应有该比例能在目标区块数内被打包
+const SUCCESS_PERCENT: usize = 95;
+// 区块大小或交易数达到上限的该比例时视为已满
+const FULL_BLOCK_PERCENT: usize = 95;
+
+// 手续费率：每 1000 字节规范编码的手续费，向下取整
+pub fn fee_rate(fee: u64, size: usize) -> u64 {
+    (fee as u128 * 1000 / size.max(1) as u128).min(u64::MAX as u128) as u64
+}
+
+// 按手续费率计算交易需要支付的手续费，向上取整
+pub fn fee_for(fee_rate: u64, size: usize) -> u64 {
+    (fee_rate as u128 * size as u128).div_ceil(1000).min(u64::MAX as u128) as u64
+}
+
+// 定义一个区块中已确认交易的费率统计
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockFeeStats {
+    // 非 Coinbase 交易的手续费率，从低到高排序
+    pub fee_rates: Vec<u64>,
+    // 区块是否已满：未满时任何费率的交易都可以被打包
+    pub full: bool,
+}
+
+impl BlockFeeStats {
+    pub fn new(mut fee_rates: Vec<u64>, size: usize, transactions: usize) -> Self {
+        fee_rates.sort_unstable();
+        let full = size * 100 >= MAX_BLOCK_SIZE * FULL_BLOCK_PERCENT
+            || transactions * 100 >= MAX_BLOCK_TRANSACTIONS * FULL_BLOCK_PERCENT;
+        BlockFeeStats { fee_rates, full }
+    }
+
+    // 被打包所需的最低费率：已满区块中的最低费率，未满区块为 0
+    pub fn clearing_rate(&self) -> u64 {
+        match self.fee_rates.first() {
+            Some(&lowest) if self.full => lowest,
+            _ => 0,
+        }
+    }
+}
+
+// 定义手续费估算器：保存最近区块的费率统计
+#[derive(Clone, Debug, Default)]
+pub struct FeeEstimator {
+    blocks: VecDeque<BlockFeeStats>,
+}
+
+impl FeeEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 记录新区块的费率统计，只保留最近 `FEE_HISTORY_BLOCKS` 个区块
+    pub fn record(&mut self, stats: BlockFeeStats) {
+        if self.blocks.len() == FEE_HISTORY_BLOCKS {
+            self.blocks.pop_front();
+        }
+        self.blocks.push_back(stats);
+    }
+
+    // 已记录的区块数
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    // 估算在 `target` 个区块内被打包所需的费率：对最近区块中每段连续 `target` 个区块，取其中最低的
+    // 打包所需费率（交易在该段任一区块中达到该费率即可被打包），返回能覆盖 95% 区段的费率；
+    // 目标超出范围或记录的区块不足时返回 None
+    pub fn estimate(&self, target: usize) -> Option<u64> {
+        if target == 0 || target > MAX_CONFIRMATION_TARGET || self.blocks.len() < target {
+            return None;
+        }
+        let clearing: Vec<u64> = self.blocks.iter().map(BlockFeeStats::clearing_rate).collect();
+        let mut windows: Vec<u64> = clearing
+            .windows(target)
+            .map(|window| window.iter().copied().min().unwrap_or(0))
+            .collect();
+        windows.sort_unstable();
+        let index = (windows.len() * SUCCESS_PERCENT).div_ceil(100) - 1;
+        Some(windows[index])
+    }
+
+    // 最近 `blocks` 个区块中已确认交易费率的中位数，没有交易时返回 None
+    pub fn median_fee_rate(&self, blocks: usize) -> Option<u64> {
+        let mut fee_rates: Vec<u64> = self
+            .blocks
+            .iter()
+            .rev()
+            .take(blocks)
+            .flat_map(|stats| stats.fee_rates.iter().copied())
+            .collect();
+        fee_rates.sort_unstable();
+        fee_rates.get(fee_rates.len() / 2).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full(fee_rates: Vec<u64>) -> BlockFeeStats {
+        BlockFeeStats::new(fee_rates, MAX_BLOCK_SIZE, 100)
+    }
+
+    #[test]
+    fn test_fee_rate() {
+        assert_eq!(fee_rate(3, 150), 20);
+        assert_eq!(fee_rate(1, 3000), 0);
+        assert_eq!(fee_for(20, 150), 3);
+        assert_eq!(fee_for(20, 151), 4);
+        assert_eq!(fee_for(0, 150), 0);
+        assert!(!BlockFeeStats::new(vec![5], 1000, 2).full);
+        assert_eq!(BlockFeeStats::new(vec![5], 1000, 2).clearing_rate(), 0);
+        assert_eq!(full(vec![30, 5, 10]).clearing_rate(), 5);
+    }
+
+    #[test]
+    fn test_estimate() {
+        let mut estimator = FeeEstimator::new();
+        assert_eq!(estimator.estimate(1), None);
+
+        // 已满区块的最低费率交替为 100 与 10：一个区块内确认需要 100，两个区块内确认只需 10
+        for i in 0..40 {
+            estimator.record(full(if i % 2 == 0 { vec![100, 200] } else { vec![10, 200] }));
+        }
+        assert_eq!(estimator.estimate(1), Some(100));
+        assert_eq!(estimator.estimate(2), Some(10));
+        assert_eq!(estimator.estimate(0), None);
+        assert_eq!(estimator.estimate(MAX_CONFIRMATION_TARGET + 1), None);
+        assert_eq!(estimator.median_fee_rate(2), Some(200));
+
+        // 区块未满时不需要手续费，只保留最近的区块
+        for _ in 0..FEE_HISTORY_BLOCKS {
+            estimator.record(BlockFeeStats::new(vec![50], 1000, 2));
+        }
+        assert_eq!(estimator.len(), FEE_HISTORY_BLOCKS);
+        assert_eq!(estimator.estimate(1), Some(0));
+        assert_eq!(estimator.median_fee_rate(10), Some(50));
+    }
+}
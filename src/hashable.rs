@@ -24,11 +24,11 @@ mod tests {
         // 实现 Hashable 特性
         impl Hashable for Test {
             fn bytes(&self) -> Vec<u8> {
-                let mut bytes = vec![];
-                bytes.extend(&u32_bytes(&self.a));
-                bytes.extend(&u32_bytes(&self.b));
-                bytes.extend(&u32_bytes(&self.c));
-                bytes
+                let mut enc = crate::encoding::Encoder::new();
+                enc.put_u32(self.a);
+                enc.put_u32(self.b);
+                enc.put_u32(self.c);
+                enc.into_bytes()
             }
         }
 
@@ -1,64 +1,38 @@
 type Hash = Vec<u8>;
 type Address = String;
 
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-// 获取当前时间戳
-pub fn now() -> u128 {
-    let duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+// 模拟时间（毫秒），0 表示使用系统时间
+static MOCK_TIME: AtomicU64 = AtomicU64::new(0);
 
-    // 返回时间戳
-    duration.as_secs() as u128 * 1000 + duration.subsec_millis() as u128
+// 设置模拟时间，None 恢复使用系统时间；用于回归测试网构造确定的场景
+pub fn set_mock_time(timestamp: Option<u64>) {
+    MOCK_TIME.store(timestamp.unwrap_or(0), Ordering::SeqCst);
 }
 
-// 获取 u32 类型的字节数组
-pub fn u32_bytes(u: &u32) -> [u8; 4] {
-    [
-        (u >> 8 * 0x0) as u8,
-        (u >> 8 * 0x1) as u8,
-        (u >> 8 * 0x2) as u8,
-        (u >> 8 * 0x3) as u8,
-    ]
+// 获取当前的模拟时间
+pub fn mock_time() -> Option<u64> {
+    match MOCK_TIME.load(Ordering::SeqCst) {
+        0 => None,
+        timestamp => Some(timestamp),
+    }
 }
 
-// 获取 u64 类型的字节数组
-pub fn u64_bytes(u: &u64) -> [u8; 8] {
-    [
-        (u >> 8 * 0x0) as u8,
-        (u >> 8 * 0x1) as u8,
-        (u >> 8 * 0x2) as u8,
-        (u >> 8 * 0x3) as u8,
-        (u >> 8 * 0x4) as u8,
-        (u >> 8 * 0x5) as u8,
-        (u >> 8 * 0x6) as u8,
-        (u >> 8 * 0x7) as u8,
-    ]
-}
+// 获取当前时间戳，设置了模拟时间时返回模拟时间
+pub fn now() -> u128 {
+    if let Some(timestamp) = mock_time() {
+        return timestamp as u128;
+    }
+    let duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
 
-// 获取 u128 类型的字节数组
-pub fn u128_bytes(u: &u128) -> [u8; 16] {
-    [
-        (u >> 8 * 0x0) as u8,
-        (u >> 8 * 0x1) as u8,
-        (u >> 8 * 0x2) as u8,
-        (u >> 8 * 0x3) as u8,
-        (u >> 8 * 0x4) as u8,
-        (u >> 8 * 0x5) as u8,
-        (u >> 8 * 0x6) as u8,
-        (u >> 8 * 0x7) as u8,
-        (u >> 8 * 0x8) as u8,
-        (u >> 8 * 0x9) as u8,
-        (u >> 8 * 0xa) as u8,
-        (u >> 8 * 0xb) as u8,
-        (u >> 8 * 0xc) as u8,
-        (u >> 8 * 0xd) as u8,
-        (u >> 8 * 0xe) as u8,
-        (u >> 8 * 0xf) as u8,
-    ]
+    // 返回时间戳
+    duration.as_secs() as u128 * 1000 + duration.subsec_millis() as u128
 }
 
 // 获取困难度字节数组
-pub fn difficulty_bytes_as_u128(v: &Vec<u8>) -> u128 {
+pub fn difficulty_bytes_as_u128(v: &[u8]) -> u128 {
     assert!(
         v.len() >= 16,
         "The input vector must have at least 16 bytes"
@@ -75,30 +49,6 @@ pub fn difficulty_bytes_as_u128(v: &Vec<u8>) -> u128 {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_u32_bytes() {
-        assert_eq!(u32_bytes(&0x12345678), [0x78, 0x56, 0x34, 0x12]);
-    }
-
-    #[test]
-    fn test_u64_bytes() {
-        assert_eq!(
-            u64_bytes(&0x0123456789abcdef),
-            [0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01]
-        );
-    }
-
-    #[test]
-    fn test_u128_bytes() {
-        assert_eq!(
-            u128_bytes(&0x0123456789abcdef0123456789abcdef),
-            [
-                0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01, 0xef, 0xcd, 0xab, 0x89, 0x67, 0x45,
-                0x23, 0x01
-            ]
-        );
-    }
-
     #[test]
     fn test_difficulty_bytes_as_u128() {
         // 注意是大端序
@@ -112,13 +62,31 @@ mod tests {
     }
 }
 
+pub mod encoding;
+pub mod serde_utils;
 mod block;
-pub use crate::block::Block;
+pub use crate::block::{Block, BlockHeader};
 mod hashable;
 pub use crate::hashable::Hashable;
-mod blockchain;
+pub mod blockchain;
 pub use crate::blockchain::Blockchain;
+pub mod script;
 pub mod transaction;
-mod p2p;
+pub mod wallet;
+pub mod psbt;
+pub mod fees;
+pub mod merkle;
+pub mod notary;
+mod mempool;
+pub use crate::mempool::Mempool;
+pub mod store;
+pub use crate::store::BlockStore;
+pub mod utxo;
+pub mod snapshot;
+pub mod params;
+pub use crate::params::ChainParams;
+pub use crate::snapshot::UtxoSnapshot;
+#[cfg(test)]
+mod test_utils;
 
 pub use crate::transaction::Transaction;
@@ -0,0 +1,24 @@
+use clap::ValueEnum;
+use tracing_subscriber::EnvFilter;
+
+// 定义日志输出格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+// 初始化日志：优先使用 `RUST_LOG` 环境变量，未设置时使用命令行指定的级别
+// 日志写入标准错误，避免与导出到标准输出的数据混在一起
+pub fn init(level: &str, format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(true)
+        .with_writer(std::io::stderr);
+
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).init(),
+    }
+}
@@ -1,140 +1,148 @@
+mod cli;
+mod commands;
+mod logging;
 mod p2p;
 mod server;
+mod snapshot_verify;
 
 use std::sync::{Arc, Mutex};
 use actix_web::{main};
 use blockchainlib::*;
+use clap::Parser;
+use cli::Cli;
+use server::auth::{AuthConfig, Authenticator};
+use server::health::HealthConfig;
+use server::rate_limit::{RateLimiter, RequestLimits};
 use server::server::MyServer;
+use p2p::p2p_node::P2PNode;
+use tracing::{info, warn};
 
 #[main]
 async fn main() -> std::io::Result<()> {
-    // 定义困难度
-    let difficulty = 0x000fffffffffffffffffffffffffffff;
-
-    // 创建创世区块
-    let mut genesis_block = Block::new(
-        0,                      // 索引
-        now(),                        // 时间戳
-        vec![0; 32],   // 前一个区块的哈希
-        vec![Transaction {
-            // 交易
-            inputs: vec![],
-            outputs: vec![
-                transaction::Output {
-                    receiver: "Alice".to_owned(),
-                    value: 50,
-                },
-                transaction::Output {
-                    receiver: "Bob".to_owned(),
-                    value: 7,
-                },
-            ],
-        }],
-        difficulty,
-    );
-
-    // 挖掘创世区块
-    let _ = genesis_block.mine();
-
-    println!("挖掘创世区块 {:?}\nInfo: {:#?}", &genesis_block, &genesis_block.transactions);
-
-    // 记录上一个区块的哈希
-    let mut last_hash = genesis_block.hash.clone();
-
-    // 创建区块链
-    let mut blockchain = Blockchain::new();
-
-    // 更新区块链
-    blockchain
-        .update_with_block(genesis_block)
-        .expect("添加创世区块失败");
-
-    // 创建新区块
-    let mut block = Block::new(
-        1,
-        now(),
-        last_hash,
-        vec![
-            Transaction {
-                inputs: vec![],
-                outputs: vec![transaction::Output {
-                    receiver: "Chris".to_owned(),
-                    value: 536,
-                }],
-            },
-            Transaction {
-                inputs: vec![blockchain.blocks[0].transactions[0].outputs[0].clone()],
-                outputs: vec![
-                    transaction::Output {
-                        receiver: "Alice".to_owned(),
-                        value: 37,
-                    },
-                    transaction::Output {
-                        receiver: "Bob".to_owned(),
-                        value: 12,
-                    },
-                ],
-            },
-        ],
-        difficulty,
-    );
-
-    // 挖掘新区块
-    let _ = block.mine();
-
-    println!("挖掘区块 {:?}\nInfo: {:#?}\n", &block, &block.transactions);
-
-    last_hash = block.hash.clone();
-
-    // 更新区块链
-    blockchain
-        .update_with_block(block)
-        .expect("添加区块失败");
-
-    // println!(">> blocks: {:?}", &blockchain.blocks);
-
-    // =====================================================================
-
-    // 创建新区块
-    block = Block::new(
-        2,
-        now(),
-        last_hash,
-        vec![
-            Transaction {
-                inputs: vec![],
-                outputs: vec![
-                    transaction::Output {
-                        receiver: "Alice".to_owned(),
-                        value: 3,
-                    },
-                    transaction::Output {
-                        receiver: "Bob".to_owned(),
-                        value: 12,
-                    },
-                ],
-            },
-        ],
-        difficulty,
-    );
-
-    // 挖掘新区块
-    let _ = block.mine();
-
-    println!("挖掘区块 {:?}\nInfo: {:#?}\n", &block, &block.transactions);
-
-    // last_hash = block.hash.clone();
-
-    // 更新区块链
-    blockchain
-        .update_with_block(block)
-        .expect("添加区块失败");
+    let cli = Cli::parse();
+    logging::init(&cli.log_level, cli.log_format);
+    std::fs::create_dir_all(&cli.data_dir)?;
+
+    // 加载链参数
+    let params = match &cli.genesis {
+        Some(path) => ChainParams::from_genesis_file(path)?,
+        None => ChainParams::for_network(cli.network),
+    };
+    info!(network = %params.network, genesis = %hex::encode(&params.genesis.hash), "链参数已加载");
+    if let Some(timestamp) = cli.mock_time {
+        if !params.is_regtest() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--mock-time is only available on regtest",
+            ));
+        }
+        set_mock_time(Some(timestamp));
+    }
+
+    // 执行子命令
+    if let Some(command) = &cli.command {
+        return commands::run(command, &cli.data_dir, &params);
+    }
+
+    // 加载鉴权配置
+    let mut auth = match &cli.api_keys {
+        Some(path) => Authenticator::from_file(path)?,
+        None => {
+            warn!("未配置 API Key 文件，所有写接口将被拒绝");
+            Authenticator::new(AuthConfig::default())
+        }
+    };
+    auth.cors_origins.extend(cli.cors_origins.iter().cloned());
+
+    // 请求限流与大小限制
+    let limits = RequestLimits {
+        per_ip: RateLimiter::new(cli.ip_burst, cli.ip_rate),
+        per_key: RateLimiter::new(cli.key_burst, cli.key_rate),
+        max_payload_bytes: cli.max_payload_bytes,
+        max_transactions: cli.max_transactions,
+    };
+
+    // 数据目录为空时从指定的 UTXO 快照启动
+    let snapshot_path = cli.data_dir.join(snapshot::SNAPSHOT_FILE_NAME);
+    if let Some(source) = &cli.snapshot
+        && !snapshot_path.exists()
+        && BlockStore::open(&cli.data_dir)?.is_empty()?
+    {
+        let snapshot = UtxoSnapshot::load(source)?;
+        // 快照内容只能自证一致，必须与可信来源的承诺比对
+        match &cli.snapshot_commitment {
+            Some(expected) if hex::encode(&snapshot.commitment) != expected.to_lowercase() => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "snapshot commitment does not match --snapshot-commitment",
+                ));
+            }
+            Some(_) => {}
+            None if cli.allow_unverified_snapshot => warn!("未校验快照承诺，快照内容未经信任确认"),
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "--snapshot requires --snapshot-commitment (or --allow-unverified-snapshot)",
+                ));
+            }
+        }
+        snapshot.save(&snapshot_path)?;
+    }
+
+    // 从数据目录加载区块链，首次启动时写入创世区块
+    let (store, blockchain) = commands::load_chain(&cli.data_dir, &params)?;
+    info!(height = blockchain.height(), path = %store.path().display(), "区块链已加载");
+
+    // 后台验证快照之前的历史区块
+    let snapshot_status = match &cli.verify_blocks {
+        Some(blocks_file) if snapshot_path.exists() => {
+            Some(snapshot_verify::spawn(UtxoSnapshot::load(&snapshot_path)?, params.clone(), blocks_file.clone()))
+        }
+        Some(_) => {
+            warn!("未从快照启动，忽略 --verify-blocks");
+            None
+        }
+        None => None,
+    };
 
     // 将区块链包装在Arc和Mutex中以共享状态
     let shared_blockchain = Arc::new(Mutex::new(blockchain));
+    let shared_mempool = Arc::new(Mutex::new(Mempool::new()));
+    let shared_store = Arc::new(Mutex::new(store));
+    let notary = notary::Notary::open(&cli.data_dir)?;
+    info!(pending = notary.pending_len(), "存证服务已加载");
 
     // 创建服务器实例并运行
-    let server = MyServer::new("0.0.0.0:8080", shared_blockchain);
-    println!("Server is running on http://127.0.0.1:8080");
+    let health = HealthConfig {
+        data_dir: cli.data_dir.clone(),
+        max_lag: cli.ready_max_lag,
+        min_peers: cli.ready_min_peers,
+    };
+    let mut server = MyServer::new(
+        &cli.listen,
+        shared_blockchain.clone(),
+        shared_mempool,
+        auth,
+        limits,
+        health,
+    )
+    .with_store(shared_store)
+    .with_notary(Arc::new(Mutex::new(notary)));
+    if let Some(status) = snapshot_status {
+        server = server.with_snapshot_status(status);
+    }
+
+    // 启动 P2P 节点并连接指定的节点
+    if let Some(p2p_listen) = &cli.p2p_listen {
+        let node = Arc::new(P2PNode::new(p2p_listen, shared_blockchain)?);
+        node.start();
+        for peer in &cli.peers {
+            node.connect(peer);
+        }
+        info!(address = %p2p_listen, "P2P node is listening");
+        server = server.with_p2p(node);
+    }
+
+    info!(address = %cli.listen, "Server is running");
     server.run().await
-}
\ No newline at end of file
+}
@@ -0,0 +1,414 @@
+use super::*;
+use crate::blockchain::{compare_fee_rate, BlockValidationErr};
+use crate::script::ScriptError;
+use crate::transaction::{OutPoint, Output, TransactionError};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+// 替换交易在被驱逐交易的手续费之外至少需要额外支付的手续费
+pub const INCREMENTAL_RELAY_FEE: u64 = 1;
+// 一次替换最多驱逐的交易数量，包括冲突交易的后代
+pub const MAX_REPLACEMENT_EVICTIONS: usize = 100;
+// 交易在交易池中的祖先交易数量上限，限制交易包的规模
+pub const MAX_UNCONFIRMED_ANCESTORS: usize = 25;
+
+// 定义交易池错误
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MempoolError {
+    AlreadyKnown,
+    InvalidTransaction(TransactionError),
+    // Coinbase 交易只能由矿工放在区块的第一个位置
+    CoinbaseTransaction,
+    LockTimeNotReached,
+    SequenceLockNotReached,
+    MissingInput(OutPoint),
+    ImmatureCoinbaseSpend,
+    InsufficientInputValue,
+    // 资产的输出与输入数量不一致，且不是本交易发行的资产
+    AssetNotConserved,
+    ScriptFailed(ScriptError),
+    TooManyAncestors,
+    // 替换交易花费了将被驱逐的交易的输出
+    SpendsConflictingTransaction,
+    TooManyReplacements(usize),
+    ReplacementFeeRateTooLow,
+    ReplacementFeeTooLow { required: u64, offered: u64 },
+}
+
+// 交易池中的交易及其手续费与大小
+#[derive(Clone, Debug)]
+struct MempoolEntry {
+    transaction: Transaction,
+    fee: u64,
+    size: u64,
+}
+
+// 定义交易池结构体，保存已提交但尚未打包的交易
+#[derive(Clone, Debug, Default)]
+pub struct Mempool {
+    // 按加入顺序保存的交易 ID，父交易总在子交易之前
+    order: Vec<Vec<u8>>,
+    entries: HashMap<Vec<u8>, MempoolEntry>,
+    // 被交易池中的交易花费的输出，以及花费它的交易
+    spent: HashMap<OutPoint, Vec<u8>>,
+}
+
+// 实现交易池结构体
+impl Mempool {
+    // 创建新的交易池
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 添加交易：交易须通过与上下文无关的检查且可以打包进下一个区块，输入必须来自 UTXO 集合或交易池中的交易，
+    // 解锁数据必须有效。与交易池中的交易冲突时按替换规则处理，成功时返回被驱逐的交易
+    pub fn add(&mut self, transaction: Transaction, blockchain: &Blockchain) -> Result<Vec<Transaction>, MempoolError> {
+        let txid = transaction.txid();
+        if self.entries.contains_key(&txid) {
+            return Err(MempoolError::AlreadyKnown);
+        }
+        transaction.check().map_err(MempoolError::InvalidTransaction)?;
+        if transaction.is_coinbase() {
+            return Err(MempoolError::CoinbaseTransaction);
+        }
+        match blockchain.check_final(&transaction) {
+            Ok(()) => {}
+            Err(BlockValidationErr::SequenceLockNotReached) => return Err(MempoolError::SequenceLockNotReached),
+            Err(_) => return Err(MempoolError::LockTimeNotReached),
+        }
+
+        // 查找被花费的输出
+        let mut spent_outputs: Vec<Output> = vec![];
+        let mut parents: HashSet<Vec<u8>> = HashSet::new();
+        for input in &transaction.inputs {
+            let outpoint = input.outpoint();
+            let output = match blockchain.utxo(&outpoint) {
+                Some(entry) => {
+                    if !entry.is_mature(blockchain.height(), blockchain.params().coinbase_maturity) {
+                        return Err(MempoolError::ImmatureCoinbaseSpend);
+                    }
+                    entry.output.clone()
+                }
+                None => {
+                    let output = self
+                        .entries
+                        .get(&input.txid)
+                        .and_then(|parent| parent.transaction.outputs.get(input.index as usize))
+                        .filter(|output| output.carried_data().is_none())
+                        .ok_or(MempoolError::MissingInput(outpoint))?;
+                    parents.insert(input.txid.clone());
+                    output.clone()
+                }
+            };
+            spent_outputs.push(output);
+        }
+        if !transaction.assets_balanced(&spent_outputs) {
+            return Err(MempoolError::AssetNotConserved);
+        }
+        let fee = spent_outputs
+            .iter()
+            .try_fold(0u64, |sum, output| sum.checked_add(output.native_value()))
+            .and_then(|value| value.checked_sub(transaction.output_value()))
+            .ok_or(MempoolError::InsufficientInputValue)?;
+        blockchain
+            .check_unlocks(&transaction, &spent_outputs)
+            .map_err(MempoolError::ScriptFailed)?;
+        let ancestors = self.ancestors(parents);
+        if ancestors.len() > MAX_UNCONFIRMED_ANCESTORS {
+            return Err(MempoolError::TooManyAncestors);
+        }
+
+        // 与交易池中的交易冲突时检查替换规则
+        let size = transaction.size() as u64;
+        let conflicts: HashSet<Vec<u8>> = transaction
+            .inputs
+            .iter()
+            .filter_map(|input| self.spent.get(&input.outpoint()).cloned())
+            .collect();
+        let evicted = self.descendants(conflicts.clone());
+        if !evicted.is_empty() {
+            self.check_replacement(fee, size, &conflicts, &evicted, &ancestors)?;
+        }
+
+        let replaced = self.remove(&evicted);
+        for input in &transaction.inputs {
+            self.spent.insert(input.outpoint(), txid.clone());
+        }
+        self.order.push(txid.clone());
+        self.entries.insert(txid, MempoolEntry { transaction, fee, size });
+        Ok(replaced)
+    }
+
+    // 替换规则：替换交易的手续费率高于每个直接冲突的交易，手续费不低于全部被驱逐交易的手续费之和加上
+    // `INCREMENTAL_RELAY_FEE`，驱逐的交易不超过 `MAX_REPLACEMENT_EVICTIONS` 个，且不能依赖被驱逐的交易
+    fn check_replacement(
+        &self,
+        fee: u64,
+        size: u64,
+        conflicts: &HashSet<Vec<u8>>,
+        evicted: &HashSet<Vec<u8>>,
+        ancestors: &HashSet<Vec<u8>>,
+    ) -> Result<(), MempoolError> {
+        if evicted.len() > MAX_REPLACEMENT_EVICTIONS {
+            return Err(MempoolError::TooManyReplacements(evicted.len()));
+        }
+        if !ancestors.is_disjoint(evicted) {
+            return Err(MempoolError::SpendsConflictingTransaction);
+        }
+        if conflicts.iter().any(|txid| {
+            let entry = &self.entries[txid];
+            compare_fee_rate(fee, size, entry.fee, entry.size) != Ordering::Greater
+        }) {
+            return Err(MempoolError::ReplacementFeeRateTooLow);
+        }
+        let required = evicted
+            .iter()
+            .map(|txid| self.entries[txid].fee)
+            .fold(INCREMENTAL_RELAY_FEE, u64::saturating_add);
+        if fee < required {
+            return Err(MempoolError::ReplacementFeeTooLow { required, offered: fee });
+        }
+        Ok(())
+    }
+
+    // 交易池中的祖先交易
+    fn ancestors(&self, mut txids: HashSet<Vec<u8>>) -> HashSet<Vec<u8>> {
+        for txid in self.order.iter().rev() {
+            if txids.contains(txid) {
+                let inputs = &self.entries[txid].transaction.inputs;
+                txids.extend(
+                    inputs
+                        .iter()
+                        .filter(|input| self.entries.contains_key(&input.txid))
+                        .map(|input| input.txid.clone()),
+                );
+            }
+        }
+        txids
+    }
+
+    // 交易及其在交易池中的全部后代交易
+    fn descendants(&self, mut txids: HashSet<Vec<u8>>) -> HashSet<Vec<u8>> {
+        for txid in &self.order {
+            if self.entries[txid].transaction.inputs.iter().any(|input| txids.contains(&input.txid)) {
+                txids.insert(txid.clone());
+            }
+        }
+        txids
+    }
+
+    // 移除交易，按加入顺序返回
+    fn remove(&mut self, txids: &HashSet<Vec<u8>>) -> Vec<Transaction> {
+        if txids.is_empty() {
+            return vec![];
+        }
+        self.spent.retain(|_, spender| !txids.contains(spender));
+        let mut removed = vec![];
+        self.order.retain(|txid| {
+            if !txids.contains(txid) {
+                return true;
+            }
+            if let Some(entry) = self.entries.remove(txid) {
+                removed.push(entry.transaction);
+            }
+            false
+        });
+        removed
+    }
+
+    // 放回未打包的交易并重新检查，输入已被花费或不再有效的交易被丢弃；返回丢弃的数量
+    pub fn restore(&mut self, transactions: Vec<Transaction>, blockchain: &Blockchain) -> usize {
+        transactions
+            .into_iter()
+            .filter(|transaction| self.add(transaction.clone(), blockchain).is_err())
+            .count()
+    }
+
+    // 取出全部交易用于打包，父交易在子交易之前
+    pub fn take_all(&mut self) -> Vec<Transaction> {
+        self.spent.clear();
+        let mut entries = std::mem::take(&mut self.entries);
+        std::mem::take(&mut self.order)
+            .into_iter()
+            .filter_map(|txid| entries.remove(&txid))
+            .map(|entry| entry.transaction)
+            .collect()
+    }
+
+    // 获取交易的手续费
+    pub fn fee(&self, txid: &[u8]) -> Option<u64> {
+        self.entries.get(txid).map(|entry| entry.fee)
+    }
+
+    // 获取交易数量
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    // 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    // 按加入顺序获取全部交易
+    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.order.iter().map(|txid| &self.entries[txid].transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::Unlock;
+    use crate::transaction::Input;
+
+    // 生成区块，返回前 `count` 个区块的 Coinbase 输出
+    fn funded_chain(count: usize) -> (Blockchain, Vec<OutPoint>) {
+        let mut params = ChainParams::regtest();
+        params.coinbase_maturity = 2;
+        let mut blockchain = Blockchain::new(params);
+        let blocks = blockchain.generate(count + 1, "miner", &mut vec![]).unwrap();
+        let outpoints = blocks[..count]
+            .iter()
+            .map(|block| block.transactions[0].outpoints().next().unwrap().0)
+            .collect();
+        (blockchain, outpoints)
+    }
+
+    fn spend(outpoints: &[OutPoint], receiver: &str, value: u64) -> Transaction {
+        Transaction {
+            inputs: outpoints.iter().cloned().map(Input::from).collect(),
+            outputs: vec![Output {
+                receiver: receiver.to_owned(),
+                value,
+                asset: None,
+                lock: None,
+            }],
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        }
+    }
+
+    fn output_of(transaction: &Transaction) -> OutPoint {
+        transaction.outpoints().next().unwrap().0
+    }
+
+    #[test]
+    fn test_mempool_take_all() {
+        let (blockchain, outpoints) = funded_chain(1);
+        let mut mempool = Mempool::new();
+        let parent = spend(&outpoints, "Alice", 45);
+        let child = spend(&[output_of(&parent)], "Bob", 40);
+        assert_eq!(mempool.add(parent.clone(), &blockchain), Ok(vec![]));
+        assert_eq!(mempool.add(child.clone(), &blockchain), Ok(vec![]));
+        assert_eq!(mempool.add(child.clone(), &blockchain), Err(MempoolError::AlreadyKnown));
+        assert_eq!(mempool.fee(&child.txid()), Some(5));
+
+        assert_eq!(mempool.len(), 2);
+        assert_eq!(mempool.take_all(), vec![parent, child]);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_add_checks_inputs() {
+        let (blockchain, outpoints) = funded_chain(1);
+        let mut mempool = Mempool::new();
+
+        let missing = OutPoint {
+            txid: vec![0; 32],
+            index: 0,
+        };
+        assert_eq!(
+            mempool.add(spend(std::slice::from_ref(&missing), "Alice", 1), &blockchain),
+            Err(MempoolError::MissingInput(missing))
+        );
+        assert_eq!(
+            mempool.add(spend(&outpoints, "Alice", 51), &blockchain),
+            Err(MempoolError::InsufficientInputValue)
+        );
+        let mut signed = spend(&outpoints, "Alice", 50);
+        signed.inputs[0].unlock = Unlock::Preimage { preimage: vec![1] };
+        assert_eq!(
+            mempool.add(signed, &blockchain),
+            Err(MempoolError::ScriptFailed(ScriptError::UnlockMismatch))
+        );
+
+        // 链顶区块的 Coinbase 尚未成熟
+        let tip = blockchain.tip().unwrap().transactions[0].clone();
+        assert_eq!(
+            mempool.add(spend(&[output_of(&tip)], "Alice", 50), &blockchain),
+            Err(MempoolError::ImmatureCoinbaseSpend)
+        );
+
+        // 无法打包进下一个区块的交易不进入交易池，避免每次挖矿都因同一交易失败
+        assert_eq!(
+            mempool.add(spend(&outpoints, "Alice", 0), &blockchain),
+            Err(MempoolError::InvalidTransaction(TransactionError::ZeroValueOutput))
+        );
+        let mut locked = spend(&outpoints, "Alice", 50);
+        locked.lock_time = Some(crate::transaction::LockTime::Height(100));
+        assert_eq!(mempool.add(locked, &blockchain), Err(MempoolError::LockTimeNotReached));
+        assert_eq!(
+            mempool.add(Transaction::coinbase(blockchain.height() as u32, vec![]), &blockchain),
+            Err(MempoolError::InvalidTransaction(TransactionError::NoOutputs))
+        );
+        let coinbase = Transaction::coinbase(blockchain.height() as u32, spend(&[], "Alice", 50).outputs);
+        assert_eq!(mempool.add(coinbase, &blockchain), Err(MempoolError::CoinbaseTransaction));
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_replace_by_fee() {
+        let (blockchain, outpoints) = funded_chain(2);
+        let mut mempool = Mempool::new();
+        let original = spend(&outpoints[..1], "Alice", 48);
+        let child = spend(&[output_of(&original)], "Bob", 47);
+        mempool.add(original.clone(), &blockchain).unwrap();
+        mempool.add(child.clone(), &blockchain).unwrap();
+
+        // 替换交易需要支付原交易及其子交易的手续费之和再加上增量手续费
+        assert_eq!(
+            mempool.add(spend(&outpoints[..1], "Carol", 47), &blockchain),
+            Err(MempoolError::ReplacementFeeTooLow { required: 4, offered: 3 })
+        );
+        // 手续费率必须高于冲突交易：增加一个输入后交易变大，手续费率反而降低
+        assert_eq!(
+            mempool.add(spend(&outpoints, "Carol", 98), &blockchain),
+            Err(MempoolError::ReplacementFeeRateTooLow)
+        );
+        // 不能花费将被驱逐的交易的输出
+        let dependent = spend(&[output_of(&original), outpoints[0].clone()], "Carol", 40);
+        assert_eq!(
+            mempool.add(dependent, &blockchain),
+            Err(MempoolError::SpendsConflictingTransaction)
+        );
+
+        let replacement = spend(&outpoints[..1], "Carol", 40);
+        assert_eq!(mempool.add(replacement.clone(), &blockchain), Ok(vec![original, child]));
+        assert_eq!(mempool.transactions().collect::<Vec<_>>(), vec![&replacement]);
+        // 被驱逐交易花费的输出可以再次使用
+        assert_eq!(mempool.add(spend(&outpoints[1..], "Dave", 50), &blockchain), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_replacement_and_ancestor_limits() {
+        let (blockchain, outpoints) = funded_chain(1);
+        let mut mempool = Mempool::new();
+        let mut chain = vec![spend(&outpoints, "Alice", 50)];
+        while chain.len() <= MAX_UNCONFIRMED_ANCESTORS {
+            let next = spend(&[output_of(chain.last().unwrap())], "Alice", 50);
+            chain.push(next);
+        }
+        for transaction in &chain {
+            mempool.add(transaction.clone(), &blockchain).unwrap();
+        }
+        let too_deep = spend(&[output_of(chain.last().unwrap())], "Alice", 50);
+        assert_eq!(mempool.add(too_deep, &blockchain), Err(MempoolError::TooManyAncestors));
+
+        // 放回交易时重新检查：与已有交易冲突且手续费不足的交易被丢弃
+        let dropped = mempool.restore(vec![chain[0].clone(), spend(&outpoints, "Bob", 50)], &blockchain);
+        assert_eq!(dropped, 2);
+        assert_eq!(mempool.len(), chain.len());
+    }
+}
@@ -0,0 +1,130 @@
+use super::*;
+use crate::script::sha256;
+use serde::{Deserialize, Serialize};
+
+// 叶子与内部节点使用不同的前缀，避免内部节点被当作叶子伪造证明
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+// 叶子节点哈希
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut bytes = Vec::with_capacity(data.len() + 1);
+    bytes.push(LEAF_PREFIX);
+    bytes.extend_from_slice(data);
+    sha256(&bytes)
+}
+
+// 内部节点哈希
+fn node_hash(left: &[u8], right: &[u8]) -> Hash {
+    let mut bytes = Vec::with_capacity(left.len() + right.len() + 1);
+    bytes.push(NODE_PREFIX);
+    bytes.extend_from_slice(left);
+    bytes.extend_from_slice(right);
+    sha256(&bytes)
+}
+
+// 由下一层节点计算上一层：两两合并，奇数个时最后一个节点直接上移（不复制，避免不同叶子序列得到相同的根）
+fn next_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => single.clone(),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+// 计算叶子数据的默克尔根，没有叶子时为空数据的哈希
+pub fn root<T: AsRef<[u8]>>(leaves: &[T]) -> Hash {
+    let mut level: Vec<Hash> = leaves.iter().map(|leaf| leaf_hash(leaf.as_ref())).collect();
+    if level.is_empty() {
+        return sha256(&[]);
+    }
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level.remove(0)
+}
+
+// 证明路径上兄弟节点所在的一侧
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+// 证明路径的一步：与兄弟节点合并得到上一层节点
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleStep {
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub hash: Hash,
+    pub side: Side,
+}
+
+// 定义默克尔证明：从叶子到根的兄弟节点，按从下到上的顺序排列
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub steps: Vec<MerkleStep>,
+}
+
+impl MerkleProof {
+    // 为第 `index` 个叶子生成证明，序号超出范围时返回 None
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T], index: usize) -> Option<Self> {
+        if index >= leaves.len() {
+            return None;
+        }
+        let mut level: Vec<Hash> = leaves.iter().map(|leaf| leaf_hash(leaf.as_ref())).collect();
+        let mut position = index;
+        let mut steps = vec![];
+        while level.len() > 1 {
+            let sibling = position ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                let side = if sibling < position { Side::Left } else { Side::Right };
+                steps.push(MerkleStep { hash: hash.clone(), side });
+            }
+            level = next_level(&level);
+            position /= 2;
+        }
+        Some(MerkleProof { steps })
+    }
+
+    // 由叶子数据沿证明路径计算默克尔根
+    pub fn root(&self, leaf: &[u8]) -> Hash {
+        self.steps.iter().fold(leaf_hash(leaf), |hash, step| match step.side {
+            Side::Left => node_hash(&step.hash, &hash),
+            Side::Right => node_hash(&hash, &step.hash),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proofs() {
+        for count in 1..=9usize {
+            let leaves: Vec<Vec<u8>> = (0..count).map(|i| vec![i as u8; 4]).collect();
+            let expected = root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = MerkleProof::new(&leaves, index).unwrap();
+                assert_eq!(proof.root(leaf), expected);
+                assert_ne!(proof.root(b"other"), expected);
+            }
+            assert_eq!(MerkleProof::new(&leaves, count), None);
+        }
+
+        // 单个叶子的根是叶子哈希；奇数个节点不复制，追加重复的最后一个叶子会改变根
+        let leaves = [b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+        assert_eq!(root(&leaves[..1]), leaf_hash(b"a"));
+        let duplicated = [b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"c".to_vec()];
+        assert_ne!(root(&leaves), root(&duplicated));
+
+        // 内部节点不能作为叶子通过验证
+        let internal = [leaf_hash(b"a"), leaf_hash(b"b")].concat();
+        let forged = MerkleProof { steps: vec![MerkleStep { hash: leaf_hash(b"c"), side: Side::Right }] };
+        assert_ne!(forged.root(&internal), root(&leaves));
+    }
+}
@@ -0,0 +1,314 @@
+use super::*;
+use crate::block::{self, BlockHeader};
+use crate::encoding;
+use crate::merkle::{self, MerkleProof};
+use crate::transaction::Output;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+// 已打包批次的记录文件（每行一个 JSON 批次）与待打包文档哈希文件
+pub const NOTARY_FILE_NAME: &str = "notary.jsonl";
+pub const NOTARY_PENDING_FILE_NAME: &str = "notary-pending.json";
+// 文档哈希为 SHA-256 摘要
+pub const DOCUMENT_HASH_SIZE: usize = 32;
+// 等待打包的文档哈希上限
+pub const MAX_PENDING_DOCUMENTS: usize = 100_000;
+
+// 定义存证错误
+#[derive(Debug, PartialEq, Eq)]
+pub enum NotaryError {
+    InvalidDocumentHash,
+    TooManyPendingDocuments,
+    CommitmentNotFound,
+    MerkleRootMismatch,
+    InvalidProofOfWork,
+}
+
+// 定义一个区块中的存证批次：文档哈希的默克尔根写入 Coinbase 交易的数据输出
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotaryBatch {
+    pub header: BlockHeader,
+    pub coinbase: Transaction,
+    // Coinbase 交易到区块头默克尔根的证明
+    pub transaction_path: MerkleProof,
+    #[serde(with = "serde_utils::hex_bytes_seq")]
+    pub documents: Vec<Hash>,
+}
+
+// 定义存证证明：文档哈希 → 批次默克尔根 → Coinbase 数据输出 → 区块头默克尔根 → 区块哈希
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotarizationProof {
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub document: Hash,
+    pub document_path: MerkleProof,
+    pub coinbase: Transaction,
+    pub transaction_path: MerkleProof,
+    pub header: BlockHeader,
+}
+
+// 定义验证结果：文档哈希不晚于该区块的时间戳存在
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Attestation {
+    pub height: u32,
+    pub timestamp: u128,
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub block_hash: Hash,
+}
+
+// 承诺一批文档哈希的数据输出
+pub fn commitment_output(documents: &[Hash]) -> Output {
+    Output::data_carrier(merkle::root(documents))
+}
+
+// 离线验证存证证明，只依赖证明本身；返回的区块哈希需与可信的链比对，确认区块在主链上
+pub fn verify(proof: &NotarizationProof) -> Result<Attestation, NotaryError> {
+    let batch_root = proof.document_path.root(&proof.document);
+    let committed = proof.coinbase.is_coinbase()
+        && proof
+            .coinbase
+            .outputs
+            .iter()
+            .any(|output| output.carried_data() == Some(batch_root.as_slice()));
+    if !committed {
+        return Err(NotaryError::CommitmentNotFound);
+    }
+    if proof.transaction_path.root(&encoding::encode(&proof.coinbase)) != proof.header.merkle_root {
+        return Err(NotaryError::MerkleRootMismatch);
+    }
+    let block_hash = proof.header.hash();
+    if !block::check_difficulty(&block_hash, proof.header.difficulty) {
+        return Err(NotaryError::InvalidProofOfWork);
+    }
+    Ok(Attestation {
+        height: proof.header.index,
+        timestamp: proof.header.timestamp,
+        block_hash,
+    })
+}
+
+// 定义存证服务：收集文档哈希，由节点挖出的区块逐批打包
+#[derive(Debug, Default)]
+pub struct Notary {
+    pending: Vec<Hash>,
+    pending_set: HashSet<Hash>,
+    batches: Vec<NotaryBatch>,
+    // 文档哈希所在的批次与批次内的序号
+    documents: HashMap<Hash, (usize, usize)>,
+    // 数据目录，未设置时不持久化
+    data_dir: Option<PathBuf>,
+}
+
+impl Notary {
+    // 创建不持久化的存证服务
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 从数据目录加载已打包的批次与待打包的文档哈希
+    pub fn open(data_dir: &Path) -> io::Result<Self> {
+        let mut notary = Notary {
+            data_dir: Some(data_dir.to_path_buf()),
+            ..Self::default()
+        };
+        match std::fs::File::open(data_dir.join(NOTARY_FILE_NAME)) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        notary.insert_batch(serde_json::from_str(&line)?);
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        match std::fs::read(data_dir.join(NOTARY_PENDING_FILE_NAME)) {
+            Ok(bytes) => {
+                let pending: PendingFile = serde_json::from_slice(&bytes)?;
+                notary.restore_batch(pending.documents);
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        Ok(notary)
+    }
+
+    // 提交文档哈希，全部有效时才接收；已提交过的哈希被忽略，返回新接收的数量
+    pub fn submit(&mut self, documents: Vec<Hash>) -> Result<usize, NotaryError> {
+        if documents.iter().any(|document| document.len() != DOCUMENT_HASH_SIZE) {
+            return Err(NotaryError::InvalidDocumentHash);
+        }
+        let mut seen = HashSet::new();
+        let new: Vec<Hash> = documents
+            .into_iter()
+            .filter(|document| !self.is_known(document) && seen.insert(document.clone()))
+            .collect();
+        if self.pending.len() + new.len() > MAX_PENDING_DOCUMENTS {
+            return Err(NotaryError::TooManyPendingDocuments);
+        }
+        let accepted = new.len();
+        self.restore_batch(new);
+        Ok(accepted)
+    }
+
+    // 等待打包的文档哈希数量
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_pending(&self, document: &[u8]) -> bool {
+        self.pending_set.contains(document)
+    }
+
+    fn is_known(&self, document: &[u8]) -> bool {
+        self.is_pending(document) || self.documents.contains_key(document)
+    }
+
+    // 取出全部待打包的文档哈希，没有时返回 None
+    pub fn take_batch(&mut self) -> Option<Vec<Hash>> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        self.pending_set.clear();
+        Some(std::mem::take(&mut self.pending))
+    }
+
+    // 区块未被接受时放回取出的文档哈希
+    pub fn restore_batch(&mut self, documents: Vec<Hash>) {
+        for document in documents {
+            if !self.is_known(&document) {
+                self.pending_set.insert(document.clone());
+                self.pending.push(document);
+            }
+        }
+    }
+
+    // 记录区块中打包的批次，区块的 Coinbase 交易必须包含该批次的承诺
+    pub fn commit(&mut self, block: &Block, documents: Vec<Hash>) -> io::Result<()> {
+        let coinbase = block
+            .transactions
+            .first()
+            .filter(|coinbase| coinbase.outputs.contains(&commitment_output(&documents)))
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "block does not commit to the batch"))?;
+        let batch = NotaryBatch {
+            header: block.header(),
+            coinbase: coinbase.clone(),
+            transaction_path: block.merkle_proof(0).expect("block has a coinbase"),
+            documents,
+        };
+        if let Some(data_dir) = &self.data_dir {
+            let mut line = serde_json::to_vec(&batch)?;
+            line.push(b'\n');
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(data_dir.join(NOTARY_FILE_NAME))?
+                .write_all(&line)?;
+        }
+        self.insert_batch(batch);
+        self.save_pending()
+    }
+
+    fn insert_batch(&mut self, batch: NotaryBatch) {
+        let position = self.batches.len();
+        for (index, document) in batch.documents.iter().enumerate() {
+            self.documents.entry(document.clone()).or_insert((position, index));
+        }
+        self.batches.push(batch);
+    }
+
+    // 保存待打包的文档哈希，节点重启后继续打包
+    pub fn save_pending(&self) -> io::Result<()> {
+        let Some(data_dir) = &self.data_dir else {
+            return Ok(());
+        };
+        let path = data_dir.join(NOTARY_PENDING_FILE_NAME);
+        let tmp = path.with_extension("tmp");
+        let file = PendingFile {
+            documents: self.pending.clone(),
+        };
+        std::fs::write(&tmp, serde_json::to_vec(&file)?)?;
+        std::fs::rename(tmp, path)
+    }
+
+    // 生成文档哈希的存证证明，尚未打包时返回 None
+    pub fn proof(&self, document: &[u8]) -> Option<NotarizationProof> {
+        let &(position, index) = self.documents.get(document)?;
+        let batch = &self.batches[position];
+        Some(NotarizationProof {
+            document: document.to_vec(),
+            document_path: MerkleProof::new(&batch.documents, index)?,
+            coinbase: batch.coinbase.clone(),
+            transaction_path: batch.transaction_path.clone(),
+            header: batch.header.clone(),
+        })
+    }
+}
+
+// 待打包文档哈希文件的格式
+#[derive(Serialize, Deserialize)]
+struct PendingFile {
+    #[serde(with = "serde_utils::hex_bytes_seq")]
+    documents: Vec<Hash>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::sha256;
+
+    #[test]
+    fn test_notarize_and_verify() {
+        // 提高难度，使篡改后的区块头无法满足工作量证明
+        let mut params = ChainParams::regtest();
+        params.difficulty = 0x00ffffffffffffffffffffffffffffff;
+        let mut blockchain = Blockchain::new(params);
+        blockchain.generate(1, "miner", &mut vec![]).unwrap();
+
+        let mut notary = Notary::new();
+        let documents: Vec<Hash> = (0..5u8).map(|i| sha256(&[i])).collect();
+        assert_eq!(notary.submit(vec![b"short".to_vec()]), Err(NotaryError::InvalidDocumentHash));
+        assert_eq!(notary.submit([documents.clone(), documents[..1].to_vec()].concat()), Ok(5));
+        assert_eq!(notary.submit(documents[..2].to_vec()), Ok(0));
+        assert!(notary.proof(&documents[0]).is_none());
+
+        // 批次的承诺写入 Coinbase 交易，同一区块中还有其他交易
+        let batch = notary.take_batch().unwrap();
+        let (mut block, _) = blockchain.block_template("miner", vec![]).unwrap();
+        block.transactions[0].outputs.push(commitment_output(&batch));
+        block.mine().unwrap();
+        blockchain.update_with_block(block.clone()).unwrap();
+        notary.commit(&block, batch).unwrap();
+        assert_eq!(notary.pending_len(), 0);
+        assert_eq!(notary.submit(documents[..1].to_vec()), Ok(0));
+
+        let proof = notary.proof(&documents[3]).unwrap();
+        let attestation = verify(&proof).unwrap();
+        assert_eq!(attestation.block_hash, block.hash);
+        assert_eq!(attestation.height, block.index);
+        assert_eq!(attestation.timestamp, block.timestamp);
+
+        // 证明经 JSON 往返后仍然有效
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(verify(&serde_json::from_str(&json).unwrap()), Ok(attestation));
+
+        // 篡改证明的任一环节都无法通过验证
+        let mut forged = proof.clone();
+        forged.document = sha256(b"forged");
+        assert_eq!(verify(&forged), Err(NotaryError::CommitmentNotFound));
+        let mut forged = proof.clone();
+        forged.coinbase.outputs[0].value += 1;
+        assert_eq!(verify(&forged), Err(NotaryError::MerkleRootMismatch));
+        let mut forged = proof.clone();
+        forged.header.nonce = (0..)
+            .find(|&nonce| {
+                forged.header.nonce = nonce;
+                !block::check_difficulty(&forged.header.hash(), forged.header.difficulty)
+            })
+            .unwrap();
+        assert_eq!(verify(&forged), Err(NotaryError::InvalidProofOfWork));
+    }
+}
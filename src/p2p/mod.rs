@@ -1 +1 @@
-pub mod p2p_node; // 导出 p2p_node 模块
\ No newline at end of file
+pub mod p2p_node;
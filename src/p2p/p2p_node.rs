@@ -1,76 +1,116 @@
-use std::collections::HashSet;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
+use blockchainlib::{now, Blockchain};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::thread;
+use tracing::{debug, info, info_span, warn};
+
+// 向对端同步区块高度的间隔
+const HEIGHT_INTERVAL: Duration = Duration::from_secs(5);
+// 计算网络时间偏移至少需要的对端时钟样本数
+const MIN_TIME_SAMPLES: usize = 5;
+// 参与计算网络时间偏移与对端高度的样本上限
+const MAX_PEER_SAMPLES: usize = 16;
+// 单条消息（含换行）的最大字节数
+const MAX_MESSAGE_SIZE: u64 = 1024;
+// 主动连接方每隔 `HEIGHT_INTERVAL` 发送一次高度，超过该时长未收到消息的入站连接被关闭
+const INBOUND_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+// 定义已连接节点的状态
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PeerState {
+    pub(crate) height: Option<usize>,
+    // 对端时钟与本地时钟之差（毫秒）
+    pub(crate) time_offset: Option<i64>,
+    // 对端 IP，同一主机的多个连接只计一个时钟样本
+    pub(crate) ip: Option<IpAddr>,
+    // 是否由本节点主动连接
+    pub(crate) outbound: bool,
+}
+
+impl PeerState {
+    fn new(stream: &TcpStream, outbound: bool) -> Self {
+        PeerState {
+            ip: stream.peer_addr().ok().map(|addr| addr.ip()),
+            outbound,
+            ..PeerState::default()
+        }
+    }
+}
+
+type Peers = Arc<Mutex<HashMap<String, PeerState>>>;
 
-#[allow(dead_code)]
 pub(crate) struct P2PNode {
     listener: TcpListener,
-    peers: Arc<Mutex<HashSet<String>>>,
+    peers: Peers,
+    blockchain: Arc<Mutex<Blockchain>>,
 }
 
-#[allow(dead_code)]
 impl P2PNode {
-    pub(crate) fn new(addr: &str) -> std::io::Result<Self> {
+    pub(crate) fn new(addr: &str, blockchain: Arc<Mutex<Blockchain>>) -> std::io::Result<Self> {
         let listener = TcpListener::bind(addr)?;
         listener.set_nonblocking(true)?;
         Ok(P2PNode {
             listener,
-            peers: Arc::new(Mutex::new(HashSet::new())),
+            peers: Arc::new(Mutex::new(HashMap::new())),
+            blockchain,
         })
     }
 
     pub(crate) fn start(&self) {
         let listener = self.listener.try_clone().unwrap();
         let peers = self.peers.clone();
+        let blockchain = self.blockchain.clone();
 
         thread::spawn(move || {
             for stream in listener.incoming() {
                 match stream {
                     Ok(stream) => {
                         let peers = peers.clone();
+                        let blockchain = blockchain.clone();
                         thread::spawn(move || {
-                            handle_incoming(stream, peers);
+                            handle_incoming(stream, peers, blockchain);
                         });
                     }
-                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
                         thread::sleep(Duration::from_millis(100));
                     }
-                    Err(e) => eprintln!("Connection failed: {}", e),
+                    Err(e) => warn!(error = %e, "Connection failed"),
                 }
             }
         });
     }
 
+    // 当前已连接的节点数量
+    pub(crate) fn peer_count(&self) -> usize {
+        self.peers.lock().unwrap().len()
+    }
+
+    // 已连接节点上报的区块高度，每个 IP 一个样本
+    pub(crate) fn peer_heights(&self) -> Vec<usize> {
+        peer_samples(self.peers.lock().unwrap().values(), |peer| peer.height)
+    }
+
     pub(crate) fn connect(&self, addr: &str) {
         let addr = addr.to_string();
+        let peers = self.peers.clone();
+        let blockchain = self.blockchain.clone();
 
         thread::spawn(move || {
+            let _span = info_span!("peer_session", peer = %addr, direction = "outbound").entered();
             loop {
                 match TcpStream::connect(&addr) {
-                    Ok(mut stream) => {
-                        println!("Connected to {}", addr);
-
-                        let local_addr = stream.local_addr().unwrap().to_string();
-                        stream
-                            .write_all(format!("PEER {}", local_addr).as_bytes())
-                            .unwrap();
-
-                        let mut buffer = [0; 1024];
-                        loop {
-                            match stream.read(&mut buffer) {
-                                Ok(0) => break,
-                                Ok(n) => {
-                                    let msg = String::from_utf8_lossy(&buffer[..n]);
-                                    println!("Received: {}", msg);
-                                }
-                                Err(_) => break,
-                            }
+                    Ok(stream) => {
+                        info!("Connected");
+                        peers.lock().unwrap().insert(addr.clone(), PeerState::new(&stream, true));
+                        if let Err(e) = run_outbound(stream, &addr, &peers, &blockchain) {
+                            debug!(error = %e, "Session ended");
                         }
+                        peers.lock().unwrap().remove(&addr);
                     }
-                    Err(e) => eprintln!("Connection to {} failed: {}", addr, e),
+                    Err(e) => warn!(error = %e, "Connection failed"),
                 }
                 thread::sleep(Duration::from_secs(5));
             }
@@ -78,33 +118,185 @@ impl P2PNode {
     }
 }
 
-fn handle_incoming(mut stream: TcpStream, peers: Arc<Mutex<HashSet<String>>>) {
+// 获取本地区块高度
+fn local_height(blockchain: &Mutex<Blockchain>) -> usize {
+    blockchain.lock().unwrap().height()
+}
+
+// 获取本地网络魔数的十六进制表示
+fn network_magic(blockchain: &Mutex<Blockchain>) -> String {
+    hex::encode(blockchain.lock().unwrap().params().magic)
+}
+
+// 校验握手消息 `PEER <addr> <magic>`，只接受同一网络的节点
+fn check_handshake(msg: &str, magic: &str) -> bool {
+    let mut parts = msg.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("PEER"), Some(peer_addr), Some(peer_magic)) if peer_magic == magic => {
+            debug!(listen_addr = %peer_addr, "Peer announced");
+            true
+        }
+        _ => false,
+    }
+}
+
+// 选取对端上报的样本：每个 IP 只取一个样本，主动连接的对端优先，最多 `MAX_PEER_SAMPLES` 个，
+// 避免同一主机通过多个入站连接控制中位数
+fn peer_samples<'a, T: Copy>(
+    peers: impl Iterator<Item = &'a PeerState>,
+    sample: impl Fn(&PeerState) -> Option<T>,
+) -> Vec<T> {
+    let mut by_ip: BTreeMap<IpAddr, (bool, T)> = BTreeMap::new();
+    for peer in peers {
+        let (Some(ip), Some(value)) = (peer.ip, sample(peer)) else {
+            continue;
+        };
+        let entry = by_ip.entry(ip).or_insert((peer.outbound, value));
+        if peer.outbound && !entry.0 {
+            *entry = (true, value);
+        }
+    }
+    let mut samples: Vec<(bool, T)> = by_ip.into_values().collect();
+    samples.sort_by_key(|(outbound, _)| !outbound);
+    samples.into_iter().take(MAX_PEER_SAMPLES).map(|(_, value)| value).collect()
+}
+
+// 记录对端时钟偏移，并以选取的样本的中位数更新网络时间
+fn record_peer_time(peer_time: u128, addr: &str, peers: &Peers, blockchain: &Mutex<Blockchain>) {
+    let offset = (peer_time as i128 - now() as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+    let mut offsets: Vec<i64> = {
+        let mut peers = peers.lock().unwrap();
+        if let Some(peer) = peers.get_mut(addr) {
+            peer.time_offset = Some(offset);
+        }
+        peer_samples(peers.values(), |peer| peer.time_offset)
+    };
+    if offsets.len() < MIN_TIME_SAMPLES {
+        return;
+    }
+    offsets.sort_unstable();
+    let median = offsets[offsets.len() / 2];
+    debug!(median_offset_ms = median, samples = offsets.len(), "Network time adjusted");
+    blockchain.lock().unwrap().set_time_offset(median);
+}
+
+// 处理一条消息，返回需要回复的内容
+fn handle_message(msg: &str, addr: &str, peers: &Peers, blockchain: &Mutex<Blockchain>) -> Option<String> {
+    if let Some(height) = msg.strip_prefix("HEIGHT ") {
+        let height = height.trim().parse::<usize>().ok()?;
+        if let Some(peer) = peers.lock().unwrap().get_mut(addr) {
+            peer.height = Some(height);
+        }
+        Some(format!("HEIGHT {}", local_height(blockchain)))
+    } else if let Some(time) = msg.strip_prefix("TIME ") {
+        let time = time.trim().parse::<u128>().ok()?;
+        record_peer_time(time, addr, peers, blockchain);
+        Some(format!("TIME {}", now()))
+    } else {
+        debug!(message = %msg, "Received");
+        None
+    }
+}
+
+// 主动连接的会话：定期发送本地高度并记录对端高度
+fn run_outbound(stream: TcpStream, addr: &str, peers: &Peers, blockchain: &Mutex<Blockchain>) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    stream.set_read_timeout(Some(HEIGHT_INTERVAL))?;
+    let mut reader = BufReader::new(stream);
+
+    let local_addr = writer.local_addr()?.to_string();
+    writeln!(writer, "PEER {} {}", local_addr, network_magic(blockchain))?;
+    writeln!(writer, "TIME {}", now())?;
+
+    let mut line = String::new();
+    loop {
+        writeln!(writer, "HEIGHT {}", local_height(blockchain))?;
+
+        match read_message(&mut reader, &mut line) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {
+                if line.starts_with("REJECT") {
+                    return Err(std::io::Error::other(format!("peer rejected handshake: {}", line.trim())));
+                }
+                // 对端的高度回复不再继续应答，避免消息往返不止
+                if let Some(height) = line.trim().strip_prefix("HEIGHT ") {
+                    if let (Ok(height), Some(peer)) = (height.parse::<usize>(), peers.lock().unwrap().get_mut(addr)) {
+                        peer.height = Some(height);
+                    }
+                } else {
+                    handle_message(line.trim(), addr, peers, blockchain);
+                }
+                thread::sleep(HEIGHT_INTERVAL);
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn handle_incoming(stream: TcpStream, peers: Peers, blockchain: Arc<Mutex<Blockchain>>) {
     let addr = stream.peer_addr().unwrap().to_string();
-    println!("New connection from: {}", addr);
+    let _span = info_span!("peer_session", peer = %addr, direction = "inbound").entered();
+    info!("New connection");
+
+    let mut writer = match stream.set_read_timeout(Some(INBOUND_READ_TIMEOUT)).and_then(|_| stream.try_clone()) {
+        Ok(writer) => writer,
+        Err(e) => {
+            warn!(error = %e, "Error cloning stream");
+            return;
+        }
+    };
+    let state = PeerState::new(&stream, false);
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    // 第一条消息必须是携带相同网络魔数的握手
+    let magic = network_magic(&blockchain);
+    match read_message(&mut reader, &mut line) {
+        Ok(read) if read > 0 && check_handshake(line.trim(), &magic) => {
+            if writeln!(writer, "PONG").is_err() {
+                return;
+            }
+        }
+        _ => {
+            warn!("Rejected peer from a different network");
+            let _ = writeln!(writer, "REJECT network magic mismatch");
+            return;
+        }
+    }
+    peers.lock().unwrap().insert(addr.clone(), state);
 
-    let mut buffer = [0; 1024];
     loop {
-        match stream.read(&mut buffer) {
+        match read_message(&mut reader, &mut line) {
             Ok(0) => break,
-            Ok(n) => {
-                let msg = String::from_utf8_lossy(&buffer[..n]);
-                if msg.starts_with("PEER ") {
-                    let peer_addr = msg.trim_start_matches("PEER ").to_string();
-                    peers.lock().unwrap().insert(peer_addr);
+            Ok(_) => {
+                if let Some(reply) = handle_message(line.trim(), &addr, &peers, &blockchain)
+                    && writeln!(writer, "{}", reply).is_err()
+                {
+                    break;
                 }
-                stream.write_all(b"PONG").unwrap();
             }
             Err(e) => {
-                eprintln!("Error reading from {}: {}", addr, e);
+                warn!(error = %e, "Error reading from peer");
                 break;
             }
         }
     }
 
-    println!("Connection closed: {}", addr);
+    info!("Connection closed");
     peers.lock().unwrap().remove(&addr);
 }
 
+// 读取一条以换行结尾的消息，超过 `MAX_MESSAGE_SIZE` 仍未遇到换行时返回错误；返回 0 表示连接已关闭
+fn read_message(reader: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    line.clear();
+    let read = reader.take(MAX_MESSAGE_SIZE).read_line(line)?;
+    if read as u64 == MAX_MESSAGE_SIZE && !line.ends_with('\n') {
+        return Err(io::Error::new(ErrorKind::InvalidData, "message too long"));
+    }
+    Ok(read)
+}
+
 /*fn main() -> std::io::Result<()> {
     let args: Vec<String> = env::args().collect();
 
@@ -128,3 +320,51 @@ fn handle_incoming(mut stream: TcpStream, peers: Arc<Mutex<HashSet<String>>>) {
         thread::sleep(Duration::from_secs(1));
     }
 }*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_message_is_bounded() {
+        let mut reader = BufReader::new(Cursor::new(b"HEIGHT 3\nTIME 1".to_vec()));
+        let mut line = String::new();
+        assert_eq!(read_message(&mut reader, &mut line).unwrap(), 9);
+        assert_eq!(line, "HEIGHT 3\n");
+        assert_eq!(read_message(&mut reader, &mut line).unwrap(), 6);
+        assert_eq!(read_message(&mut reader, &mut line).unwrap(), 0);
+
+        // 没有换行的超长消息不会被完整读入内存
+        let long = vec![b'x'; MAX_MESSAGE_SIZE as usize * 4];
+        let mut reader = BufReader::new(Cursor::new(long));
+        let err = read_message(&mut reader, &mut line).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(line.len() as u64, MAX_MESSAGE_SIZE);
+    }
+
+    #[test]
+    fn test_peer_samples() {
+        let peer = |ip: [u8; 4], outbound, offset| PeerState {
+            time_offset: Some(offset),
+            ip: Some(IpAddr::from(ip)),
+            outbound,
+            ..PeerState::default()
+        };
+
+        // 同一主机的多个入站连接只计一个样本，主动连接的样本优先
+        let mut peers: Vec<PeerState> = (0..5).map(|_| peer([10, 0, 0, 1], false, 4_000_000)).collect();
+        peers.push(peer([10, 0, 0, 1], true, 0));
+        peers.push(peer([10, 0, 0, 2], false, 10));
+        let time_samples = |peers: &[PeerState]| peer_samples(peers.iter(), |peer| peer.time_offset);
+        assert_eq!(time_samples(&peers), vec![0, 10]);
+
+        // 样本数量有上限，超出时保留主动连接的对端
+        let mut peers: Vec<PeerState> = (0..MAX_PEER_SAMPLES as u8).map(|i| peer([10, 1, 0, i], false, 1)).collect();
+        peers.push(peer([10, 2, 0, 0], true, 2));
+        let samples = time_samples(&peers);
+        assert_eq!(samples.len(), MAX_PEER_SAMPLES);
+        assert_eq!(samples[0], 2);
+        assert!(time_samples(&[PeerState::default()]).is_empty());
+    }
+}
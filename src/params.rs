@@ -0,0 +1,256 @@
+use super::*;
+use crate::transaction::Output;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+// 内置网络
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Regtest,
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mainnet" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            "regtest" => Ok(Network::Regtest),
+            _ => Err(format!("unknown network '{}', expected mainnet, testnet or regtest", s)),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Regtest => "regtest",
+        };
+        f.write_str(name)
+    }
+}
+
+// 定义链参数：同一网络的所有节点必须使用相同的参数
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainParams {
+    // 网络名称
+    pub network: String,
+    // 网络魔数，P2P 握手时校验
+    pub magic: [u8; 4],
+    // 创世区块
+    pub genesis: Block,
+    // 初始难度
+    pub difficulty: u128,
+    // 每个区块的出块奖励
+    pub subsidy: u64,
+    // 目标出块间隔
+    pub block_time: Duration,
+    // Coinbase 输出需要经过的区块数才能花费
+    pub coinbase_maturity: u32,
+    // 是否为内置的回归测试网，自定义创世文件中的网络名称不影响该标记
+    pub regtest: bool,
+}
+
+// 自定义创世文件格式
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GenesisConfig {
+    pub network: String,
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub magic: Vec<u8>,
+    #[serde(with = "serde_utils::u128_string")]
+    pub timestamp: u128,
+    #[serde(with = "serde_utils::u128_string")]
+    pub difficulty: u128,
+    pub subsidy: u64,
+    pub block_time_secs: u64,
+    #[serde(default = "default_coinbase_maturity")]
+    pub coinbase_maturity: u32,
+    // 创世区块中 Coinbase 交易的输出
+    pub outputs: Vec<Output>,
+}
+
+// 默认的 Coinbase 成熟度
+pub const COINBASE_MATURITY: u32 = 100;
+
+fn default_coinbase_maturity() -> u32 {
+    COINBASE_MATURITY
+}
+
+const MAINNET_DIFFICULTY: u128 = 0x000fffffffffffffffffffffffffffff;
+const TESTNET_DIFFICULTY: u128 = 0x00ffffffffffffffffffffffffffffff;
+const REGTEST_DIFFICULTY: u128 = u128::MAX;
+
+// 内置网络的创世区块在首次使用时挖出，之后复用，避免每次获取参数都重新挖矿
+static MAINNET_GENESIS: LazyLock<Block> =
+    LazyLock::new(|| genesis_block(1_700_000_000_000, MAINNET_DIFFICULTY, default_allocation()));
+static TESTNET_GENESIS: LazyLock<Block> =
+    LazyLock::new(|| genesis_block(1_700_000_000_001, TESTNET_DIFFICULTY, default_allocation()));
+static REGTEST_GENESIS: LazyLock<Block> =
+    LazyLock::new(|| genesis_block(1_700_000_000_002, REGTEST_DIFFICULTY, default_allocation()));
+
+impl ChainParams {
+    // 主网：固定的创世时间戳与分配，所有节点得到相同的创世哈希
+    pub fn mainnet() -> Self {
+        ChainParams {
+            network: "mainnet".to_owned(),
+            magic: *b"JLMN",
+            genesis: MAINNET_GENESIS.clone(),
+            difficulty: MAINNET_DIFFICULTY,
+            subsidy: 50,
+            block_time: Duration::from_secs(600),
+            coinbase_maturity: COINBASE_MATURITY,
+            regtest: false,
+        }
+    }
+
+    // 测试网：难度较低，出块更快
+    pub fn testnet() -> Self {
+        ChainParams {
+            network: "testnet".to_owned(),
+            magic: *b"JLTN",
+            genesis: TESTNET_GENESIS.clone(),
+            difficulty: TESTNET_DIFFICULTY,
+            subsidy: 50,
+            block_time: Duration::from_secs(60),
+            coinbase_maturity: COINBASE_MATURITY,
+            regtest: false,
+        }
+    }
+
+    // 回归测试网：难度最低，任意哈希均满足要求
+    pub fn regtest() -> Self {
+        ChainParams {
+            network: "regtest".to_owned(),
+            magic: *b"JLRT",
+            genesis: REGTEST_GENESIS.clone(),
+            difficulty: REGTEST_DIFFICULTY,
+            subsidy: 50,
+            block_time: Duration::from_secs(1),
+            coinbase_maturity: COINBASE_MATURITY,
+            regtest: true,
+        }
+    }
+
+    // 获取内置网络的参数
+    pub fn for_network(network: Network) -> Self {
+        match network {
+            Network::Mainnet => Self::mainnet(),
+            Network::Testnet => Self::testnet(),
+            Network::Regtest => Self::regtest(),
+        }
+    }
+
+    // 是否为回归测试网，只有回归测试网允许即时生成区块和模拟时间
+    pub fn is_regtest(&self) -> bool {
+        self.regtest
+    }
+
+    // 从自定义创世文件加载参数
+    pub fn from_genesis_file(path: &Path) -> io::Result<Self> {
+        let config: GenesisConfig = serde_json::from_slice(&std::fs::read(path)?)?;
+        Self::from_config(config)
+    }
+
+    pub fn from_config(config: GenesisConfig) -> io::Result<Self> {
+        let magic = <[u8; 4]>::try_from(config.magic.as_slice())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "network magic must be 4 bytes"))?;
+        if config.outputs.is_empty() {
+            return Err(io::Error::new(ErrorKind::InvalidData, "genesis must have at least one output"));
+        }
+        Ok(ChainParams {
+            network: config.network,
+            magic,
+            genesis: genesis_block(config.timestamp, config.difficulty, config.outputs),
+            difficulty: config.difficulty,
+            subsidy: config.subsidy,
+            block_time: Duration::from_secs(config.block_time_secs),
+            coinbase_maturity: config.coinbase_maturity,
+            regtest: false,
+        })
+    }
+}
+
+// 创世区块的全部字段都由参数确定，挖矿结果因此也是确定的
+fn genesis_block(timestamp: u128, difficulty: u128, outputs: Vec<Output>) -> Block {
+    let mut genesis = Block::new(
+        0,
+        timestamp,
+        vec![0; 32],
+        vec![Transaction::coinbase(0, outputs)],
+        difficulty,
+    );
+    genesis.mine().expect("genesis difficulty is unreachable");
+    genesis
+}
+
+// 内置网络的创世分配
+fn default_allocation() -> Vec<Output> {
+    vec![output("Alice", 50), output("Bob", 7)]
+}
+
+fn output(receiver: &str, value: u64) -> Output {
+    Output {
+        receiver: receiver.to_owned(),
+        value,
+        asset: None,
+        lock: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_are_deterministic_and_distinct() {
+        assert_eq!(ChainParams::mainnet().genesis.hash, ChainParams::mainnet().genesis.hash);
+        // 缓存的创世区块与按参数重新挖出的结果一致
+        assert_eq!(
+            ChainParams::testnet().genesis,
+            genesis_block(1_700_000_000_001, TESTNET_DIFFICULTY, default_allocation())
+        );
+        let mainnet = ChainParams::mainnet();
+        assert!(block::check_difficulty(&mainnet.genesis.hash(), mainnet.difficulty));
+        assert_ne!(ChainParams::mainnet().genesis.hash, ChainParams::testnet().genesis.hash);
+        assert_ne!(ChainParams::testnet().magic, ChainParams::regtest().magic);
+        assert_eq!("regtest".parse(), Ok(Network::Regtest));
+        assert!(ChainParams::regtest().is_regtest());
+        assert!(!ChainParams::testnet().is_regtest());
+    }
+
+    #[test]
+    fn test_genesis_config() {
+        let config: GenesisConfig = serde_json::from_str(
+            r#"{
+                "network": "regtest",
+                "magic": "4a4c5056",
+                "timestamp": "1700000000000",
+                "difficulty": "340282366920938463463374607431768211455",
+                "subsidy": 25,
+                "block_time_secs": 30,
+                "outputs": [{ "receiver": "Alice", "value": 100 }]
+            }"#,
+        )
+        .unwrap();
+        let params = ChainParams::from_config(config).unwrap();
+        assert_eq!(params.magic, *b"JLPV");
+        assert_eq!(params.subsidy, 25);
+        assert_eq!(params.coinbase_maturity, COINBASE_MATURITY);
+        assert_eq!(params.genesis.transactions[0].output_value(), 100);
+        // 创世文件声明的网络名称不能解锁回归测试网的功能
+        assert!(!params.is_regtest());
+
+        let mut blockchain = Blockchain::new(params.clone());
+        blockchain.update_with_block(params.genesis).unwrap();
+    }
+}
@@ -0,0 +1,483 @@
+use super::*;
+use crate::script::{self, KeySignature, Lock, Unlock};
+use crate::transaction::Output;
+use crate::wallet::Wallet;
+use serde::{Deserialize, Serialize};
+
+// 部分签名交易格式的版本
+pub const PSBT_VERSION: u8 = 1;
+
+// 定义部分签名交易错误
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PsbtError {
+    UnsupportedVersion(u8),
+    InputCountMismatch,
+    TransactionMismatch,
+    UtxoMismatch(usize),
+    MissingUtxo(usize),
+    InvalidSignature(usize),
+    // 已收集的签名与原像不足以满足锁定条件
+    Incomplete(usize),
+    NotFinalized(usize),
+}
+
+// 定义一个公钥对交易 ID 的签名
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub public_key: Vec<u8>,
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub signature: Vec<u8>,
+}
+
+// 定义每个输入的签名信息
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PsbtInput {
+    // 被花费的输出，包含金额与锁定条件，离线签名方据此确认交易内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utxo: Option<Output>,
+    // 已收集的签名
+    #[serde(default)]
+    pub signatures: Vec<PartialSignature>,
+    // 已收集的哈希锁原像
+    #[serde(default, with = "serde_utils::hex_bytes_seq")]
+    pub preimages: Vec<Vec<u8>>,
+    // 最终的解锁数据，生成后不再接受新的签名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlock: Option<Unlock>,
+}
+
+// 定义部分签名交易：未签名的交易、每个输入的 UTXO 信息与已收集的签名
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Psbt {
+    pub version: u8,
+    pub transaction: Transaction,
+    pub inputs: Vec<PsbtInput>,
+    // 资产发行方的签名信息，锁定条件为发行方锁定条件；错误与原像按序号 `inputs.len()` 指代
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuance: Option<PsbtInput>,
+}
+
+impl Psbt {
+    // 由交易创建，清除已有的解锁数据
+    pub fn new(mut transaction: Transaction) -> Self {
+        for input in &mut transaction.inputs {
+            input.unlock = Unlock::None;
+        }
+        let issuance = transaction.issuance.as_mut().map(|issuance| {
+            issuance.unlock = Unlock::None;
+            PsbtInput::default()
+        });
+        let inputs = vec![PsbtInput::default(); transaction.inputs.len()];
+        Psbt {
+            version: PSBT_VERSION,
+            transaction,
+            inputs,
+            issuance,
+        }
+    }
+
+    // 检查版本、输入数量与发行方签名信息
+    pub fn validate(&self) -> Result<(), PsbtError> {
+        if self.version != PSBT_VERSION {
+            return Err(PsbtError::UnsupportedVersion(self.version));
+        }
+        if self.inputs.len() != self.transaction.inputs.len()
+            || self.issuance.is_some() != self.transaction.issuance.is_some()
+        {
+            return Err(PsbtError::InputCountMismatch);
+        }
+        Ok(())
+    }
+
+    // 需要签名的锁定条件与对应的签名信息：每个输入（UTXO 未知时为 None），以及发行方
+    fn slots_mut(&mut self) -> impl Iterator<Item = (Option<Lock>, &mut PsbtInput)> {
+        let inputs = self
+            .inputs
+            .iter_mut()
+            .map(|info| (info.utxo.as_ref().and_then(|utxo| utxo.lock.clone()), info));
+        let issuance = self
+            .transaction
+            .issuance
+            .as_ref()
+            .map(|issuance| issuance.issuer.clone())
+            .zip(self.issuance.as_mut())
+            .map(|(issuer, info)| (Some(issuer), info));
+        inputs.chain(issuance)
+    }
+
+    // 从链上 UTXO 集合补充每个输入的 UTXO 信息，返回仍然缺失的输入数量
+    pub fn update(&mut self, blockchain: &Blockchain) -> usize {
+        for (input, info) in self.transaction.inputs.iter().zip(&mut self.inputs) {
+            if info.utxo.is_none() {
+                info.utxo = blockchain.utxo(&input.outpoint()).map(|entry| entry.output.clone());
+            }
+        }
+        self.inputs.iter().filter(|info| info.utxo.is_none()).count()
+    }
+
+    // 用钱包中与锁定条件相关的私钥签名，返回新增的签名数量
+    pub fn sign(&mut self, wallet: &Wallet) -> usize {
+        let txid = self.transaction.txid();
+        let mut added = 0;
+        for (lock, info) in self.slots_mut() {
+            let Some(lock) = &lock else {
+                continue;
+            };
+            if info.unlock.is_some() {
+                continue;
+            }
+            for public_key in wallet.public_keys() {
+                let public_key = public_key.to_bytes().to_vec();
+                if !lock_mentions_key(lock, &public_key)
+                    || info.signatures.iter().any(|sig| sig.public_key == public_key)
+                {
+                    continue;
+                }
+                if let Some(signature) = wallet.sign(&public_key, &txid) {
+                    info.signatures.push(PartialSignature { public_key, signature });
+                    added += 1;
+                }
+            }
+        }
+        added
+    }
+
+    // 添加哈希锁原像，序号 `inputs.len()` 为发行方
+    pub fn add_preimage(&mut self, input: usize, preimage: Vec<u8>) {
+        if let Some((_, info)) = self.slots_mut().nth(input)
+            && !info.preimages.contains(&preimage)
+        {
+            info.preimages.push(preimage);
+        }
+    }
+
+    // 合并其他签名方的结果，只接受对同一交易的有效签名
+    pub fn combine(&mut self, other: &Psbt) -> Result<(), PsbtError> {
+        other.validate()?;
+        if other.transaction.txid() != self.transaction.txid() {
+            return Err(PsbtError::TransactionMismatch);
+        }
+        let txid = self.transaction.txid();
+        let pairs = self
+            .inputs
+            .iter_mut()
+            .chain(self.issuance.as_mut())
+            .zip(other.inputs.iter().chain(other.issuance.as_ref()));
+        for (index, (ours, theirs)) in pairs.enumerate() {
+            match (&ours.utxo, &theirs.utxo) {
+                (Some(a), Some(b)) if a != b => return Err(PsbtError::UtxoMismatch(index)),
+                (None, Some(utxo)) => ours.utxo = Some(utxo.clone()),
+                _ => {}
+            }
+            for sig in &theirs.signatures {
+                if script::verify_signature(&sig.public_key, &sig.signature, &txid).is_err() {
+                    return Err(PsbtError::InvalidSignature(index));
+                }
+                if !ours.signatures.iter().any(|existing| existing.public_key == sig.public_key) {
+                    ours.signatures.push(sig.clone());
+                }
+            }
+            for preimage in &theirs.preimages {
+                if !ours.preimages.contains(preimage) {
+                    ours.preimages.push(preimage.clone());
+                }
+            }
+            if ours.unlock.is_none() {
+                ours.unlock = theirs.unlock.clone();
+            }
+        }
+        Ok(())
+    }
+
+    // 根据锁定条件与已收集的签名、原像生成每个输入的解锁数据
+    pub fn finalize(&mut self) -> Result<(), PsbtError> {
+        self.validate()?;
+        for (index, info) in self.inputs.iter_mut().enumerate() {
+            if info.unlock.is_some() {
+                continue;
+            }
+            let utxo = info.utxo.as_ref().ok_or(PsbtError::MissingUtxo(index))?;
+            let unlock = match &utxo.lock {
+                Some(lock) => build_unlock(lock, info).ok_or(PsbtError::Incomplete(index))?,
+                None => Unlock::None,
+            };
+            info.unlock = Some(unlock);
+        }
+        if let (Some(issuance), Some(info)) = (&self.transaction.issuance, &mut self.issuance)
+            && info.unlock.is_none()
+        {
+            let unlock = build_unlock(&issuance.issuer, info).ok_or(PsbtError::Incomplete(self.inputs.len()))?;
+            info.unlock = Some(unlock);
+        }
+        Ok(())
+    }
+
+    // 是否所有输入与发行方都已生成解锁数据
+    pub fn is_finalized(&self) -> bool {
+        self.inputs.iter().chain(&self.issuance).all(|info| info.unlock.is_some())
+    }
+
+    // 提取可以提交的交易
+    pub fn extract(&self) -> Result<Transaction, PsbtError> {
+        self.validate()?;
+        let mut transaction = self.transaction.clone();
+        for (index, (input, info)) in transaction.inputs.iter_mut().zip(&self.inputs).enumerate() {
+            input.unlock = info.unlock.clone().ok_or(PsbtError::NotFinalized(index))?;
+        }
+        if let (Some(issuance), Some(info)) = (&mut transaction.issuance, &self.issuance) {
+            issuance.unlock = info.unlock.clone().ok_or(PsbtError::NotFinalized(self.inputs.len()))?;
+        }
+        Ok(transaction)
+    }
+}
+
+// 锁定条件中是否包含该公钥（P2PKH 按公钥哈希比较）
+fn lock_mentions_key(lock: &Lock, public_key: &[u8]) -> bool {
+    match lock {
+        Lock::PubKeyHash { hash } => script::public_key_hash(public_key) == *hash,
+        Lock::Multisig { public_keys, .. } => public_keys.iter().any(|key| key == public_key),
+        Lock::All { locks } | Lock::Any { locks } => locks.iter().any(|lock| lock_mentions_key(lock, public_key)),
+        Lock::HashLock { .. } | Lock::AfterHeight { .. } | Lock::AfterTime { .. } | Lock::Data { .. } => false,
+    }
+}
+
+// 构造满足锁定条件的解锁数据；`Any` 选择第一个可以满足的分支，时间锁是否到期由区块验证判断
+fn build_unlock(lock: &Lock, info: &PsbtInput) -> Option<Unlock> {
+    let signature_for = |public_key: &[u8]| {
+        info.signatures
+            .iter()
+            .find(|sig| sig.public_key == public_key)
+            .map(|sig| sig.signature.clone())
+    };
+    match lock {
+        Lock::PubKeyHash { hash } => info
+            .signatures
+            .iter()
+            .find(|sig| script::public_key_hash(&sig.public_key) == *hash)
+            .map(|sig| Unlock::Signature {
+                public_key: sig.public_key.clone(),
+                signature: sig.signature.clone(),
+            }),
+        Lock::HashLock { hash } => info
+            .preimages
+            .iter()
+            .find(|preimage| script::sha256(preimage) == *hash)
+            .map(|preimage| Unlock::Preimage {
+                preimage: preimage.clone(),
+            }),
+        Lock::AfterHeight { .. } | Lock::AfterTime { .. } => Some(Unlock::None),
+        Lock::Data { .. } => None,
+        Lock::Multisig { threshold, public_keys } => {
+            let signatures: Vec<KeySignature> = public_keys
+                .iter()
+                .enumerate()
+                .filter_map(|(key, public_key)| {
+                    signature_for(public_key).map(|signature| KeySignature {
+                        key: key as u32,
+                        signature,
+                    })
+                })
+                .take(*threshold as usize)
+                .collect();
+            (signatures.len() == *threshold as usize).then_some(Unlock::Multisig { signatures })
+        }
+        Lock::All { locks } => locks
+            .iter()
+            .map(|lock| build_unlock(lock, info))
+            .collect::<Option<Vec<_>>>()
+            .map(|unlocks| Unlock::All { unlocks }),
+        Lock::Any { locks } => locks.iter().enumerate().find_map(|(branch, lock)| {
+            build_unlock(lock, info).map(|unlock| Unlock::Any {
+                branch: branch as u32,
+                unlock: Box::new(unlock),
+            })
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::{sha256, ScriptContext};
+    use crate::transaction::{Input, Issuance};
+    use ed25519_dalek::{SigningKey, VerifyingKey};
+
+    fn wallet(seed: u8) -> Wallet {
+        let mut wallet = Wallet::new();
+        wallet.add_key(SigningKey::from_bytes(&[seed; 32]));
+        wallet
+    }
+
+    fn psbt(lock: Option<Lock>) -> Psbt {
+        let transaction = Transaction {
+            inputs: vec![Input {
+                txid: vec![1; 32],
+                index: 0,
+                unlock: Unlock::Preimage { preimage: vec![1] },
+                sequence: None,
+            }],
+            outputs: vec![Output {
+                receiver: "Bob".to_owned(),
+                value: 10,
+                asset: None,
+                lock: None,
+            }],
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        };
+        let mut psbt = Psbt::new(transaction);
+        psbt.inputs[0].utxo = Some(Output {
+            receiver: "funds".to_owned(),
+            value: 10,
+            asset: None,
+            lock,
+        });
+        psbt
+    }
+
+    // 用最终交易执行锁定条件
+    fn evaluate(lock: &Lock, transaction: &Transaction) -> Result<(), script::ScriptError> {
+        let txid = transaction.txid();
+        let ctx = ScriptContext {
+            sighash: &txid,
+            height: 0,
+            time: 0,
+        };
+        lock.evaluate(&transaction.inputs[0].unlock, &ctx)
+    }
+
+    #[test]
+    fn test_multisig_round_trip() {
+        let wallets = [wallet(1), wallet(2), wallet(3)];
+        let public_keys: Vec<VerifyingKey> = wallets.iter().flat_map(Wallet::public_keys).collect();
+        let lock = Lock::multisig(2, &public_keys);
+
+        // 创建后分发给两个签名方，经 JSON 传输后合并
+        let unsigned = psbt(Some(lock.clone()));
+        assert_eq!(unsigned.transaction.inputs[0].unlock, Unlock::None);
+        let mut first = unsigned.clone();
+        let mut second: Psbt = serde_json::from_str(&serde_json::to_string(&unsigned).unwrap()).unwrap();
+        assert_eq!(first.sign(&wallets[2]), 1);
+        assert_eq!(second.sign(&wallets[0]), 1);
+        assert_eq!(first.finalize(), Err(PsbtError::Incomplete(0)));
+        assert_eq!(first.extract(), Err(PsbtError::NotFinalized(0)));
+
+        first.combine(&second).unwrap();
+        first.combine(&second).unwrap();
+        assert_eq!(first.inputs[0].signatures.len(), 2);
+        first.finalize().unwrap();
+        assert!(first.is_finalized());
+        let transaction = first.extract().unwrap();
+        assert_eq!(transaction.txid(), unsigned.transaction.txid());
+        assert_eq!(evaluate(&lock, &transaction), Ok(()));
+    }
+
+    #[test]
+    fn test_pay_to_public_key_and_htlc() {
+        let (alice, bob) = (wallet(1), wallet(2));
+        let p2pkh = Lock::pay_to_public_key(&alice.public_keys()[0]);
+        let mut signed = psbt(Some(p2pkh.clone()));
+        assert_eq!(signed.clone().sign(&bob), 0);
+        assert_eq!(signed.sign(&alice), 1);
+        signed.finalize().unwrap();
+        assert_eq!(evaluate(&p2pkh, &signed.extract().unwrap()), Ok(()));
+
+        // 哈希时间锁合约：提供原像时选择领取分支，否则只能选择退款分支
+        let htlc = Lock::Any {
+            locks: vec![
+                Lock::All {
+                    locks: vec![
+                        Lock::HashLock { hash: sha256(b"secret") },
+                        Lock::pay_to_public_key(&bob.public_keys()[0]),
+                    ],
+                },
+                Lock::All {
+                    locks: vec![
+                        Lock::AfterHeight { height: 100 },
+                        Lock::pay_to_public_key(&alice.public_keys()[0]),
+                    ],
+                },
+            ],
+        };
+        let mut claim = psbt(Some(htlc.clone()));
+        claim.sign(&bob);
+        assert_eq!(claim.clone().finalize(), Err(PsbtError::Incomplete(0)));
+        claim.add_preimage(0, b"secret".to_vec());
+        claim.finalize().unwrap();
+        assert_eq!(evaluate(&htlc, &claim.extract().unwrap()), Ok(()));
+
+        let mut refund = psbt(Some(htlc));
+        refund.sign(&alice);
+        refund.finalize().unwrap();
+        let Unlock::Any { branch, .. } = refund.inputs[0].unlock.clone().unwrap() else {
+            panic!("expected a branch unlock");
+        };
+        assert_eq!(branch, 1);
+
+        // 未加锁的输出无需签名
+        let mut open = psbt(None);
+        open.finalize().unwrap();
+        assert_eq!(open.extract().unwrap().inputs[0].unlock, Unlock::None);
+    }
+
+    #[test]
+    fn test_issuance_signature() {
+        let alice = wallet(1);
+        let issuer = Lock::pay_to_public_key(&alice.public_keys()[0]);
+        let mut unsigned = psbt(None);
+        unsigned.transaction.issuance = Some(Issuance {
+            issuer: issuer.clone(),
+            name: "points".to_owned(),
+            unlock: Unlock::Preimage { preimage: vec![1] },
+        });
+        assert_eq!(unsigned.validate(), Err(PsbtError::InputCountMismatch));
+
+        // 发行方的签名位于所有输入之后
+        let mut signed = Psbt::new(unsigned.transaction.clone());
+        signed.inputs[0].utxo = unsigned.inputs[0].utxo.clone();
+        assert_eq!(signed.transaction.issuance.as_ref().unwrap().unlock, Unlock::None);
+        assert_eq!(signed.clone().finalize(), Err(PsbtError::Incomplete(1)));
+        assert_eq!(signed.sign(&alice), 1);
+        signed.finalize().unwrap();
+        let transaction = signed.extract().unwrap();
+        let txid = transaction.txid();
+        let ctx = ScriptContext {
+            sighash: &txid,
+            height: 0,
+            time: 0,
+        };
+        let issuance = transaction.issuance.unwrap();
+        assert_eq!(issuer.evaluate(&issuance.unlock, &ctx), Ok(()));
+    }
+
+    #[test]
+    fn test_combine_and_finalize_errors() {
+        let alice = wallet(1);
+        let lock = Lock::pay_to_public_key(&alice.public_keys()[0]);
+        let mut ours = psbt(Some(lock.clone()));
+
+        let mut other = psbt(Some(lock.clone()));
+        other.transaction.outputs[0].value = 5;
+        assert_eq!(ours.combine(&other), Err(PsbtError::TransactionMismatch));
+
+        let mut forged = psbt(Some(lock.clone()));
+        forged.inputs[0].signatures.push(PartialSignature {
+            public_key: alice.public_keys()[0].to_bytes().to_vec(),
+            signature: vec![0; 64],
+        });
+        assert_eq!(ours.combine(&forged), Err(PsbtError::InvalidSignature(0)));
+
+        let mut different = psbt(None);
+        assert_eq!(ours.combine(&different), Err(PsbtError::UtxoMismatch(0)));
+        different.version = 2;
+        assert_eq!(ours.combine(&different), Err(PsbtError::UnsupportedVersion(2)));
+
+        let mut missing = psbt(Some(lock));
+        missing.inputs[0].utxo = None;
+        assert_eq!(missing.finalize(), Err(PsbtError::MissingUtxo(0)));
+        missing.inputs.clear();
+        assert_eq!(missing.finalize(), Err(PsbtError::InputCountMismatch));
+    }
+}
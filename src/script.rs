@@ -0,0 +1,697 @@
+use super::*;
+use crate::encoding::{self, Decode, DecodeError, Decoder, Encode, Encoder};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+// 锁定条件与解锁数据允许的最大嵌套层数
+pub const MAX_SCRIPT_DEPTH: usize = 8;
+// 多重签名允许的最大公钥数量
+pub const MAX_MULTISIG_KEYS: usize = 16;
+// 数据输出携带的最大字节数
+pub const MAX_DATA_SIZE: usize = 80;
+
+// 定义脚本执行错误
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptError {
+    UnlockMismatch,
+    InvalidPublicKey,
+    PublicKeyHashMismatch,
+    InvalidSignature,
+    PreimageMismatch,
+    HeightLockNotReached,
+    TimeLockNotReached,
+    BranchOutOfRange,
+    NotEnoughSignatures,
+    InvalidKeyOrder,
+    // 数据输出无法花费
+    Unspendable,
+}
+
+// 定义输出的锁定条件
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Lock {
+    // 需要公钥哈希为 `hash` 的 ed25519 公钥对交易 ID 的签名
+    PubKeyHash {
+        #[serde(with = "serde_utils::hex_bytes")]
+        hash: Hash,
+    },
+    // 需要 SHA-256 哈希为 `hash` 的原像
+    HashLock {
+        #[serde(with = "serde_utils::hex_bytes")]
+        hash: Hash,
+    },
+    // 只能在索引不小于 `height` 的区块中花费
+    AfterHeight { height: u32 },
+    // 只能在过去中位时间不早于 `timestamp`（毫秒）的链上花费
+    AfterTime {
+        #[serde(with = "serde_utils::u128_string")]
+        timestamp: u128,
+    },
+    // 需要 `public_keys` 中至少 `threshold` 个不同公钥对交易 ID 的签名
+    Multisig {
+        threshold: u32,
+        #[serde(with = "serde_utils::hex_bytes_seq")]
+        public_keys: Vec<Vec<u8>>,
+    },
+    // 全部条件均须满足
+    All { locks: Vec<Lock> },
+    // 满足任一条件即可
+    Any { locks: Vec<Lock> },
+    // 数据输出：携带至多 `MAX_DATA_SIZE` 字节的任意数据，任何解锁数据都无法花费，只能作为输出的顶层条件
+    Data {
+        #[serde(with = "serde_utils::hex_bytes")]
+        data: Vec<u8>,
+    },
+}
+
+// 定义输入的解锁数据，结构与被花费输出的锁定条件一一对应
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Unlock {
+    // 无需解锁数据，用于时间锁与未加锁的输出
+    #[default]
+    None,
+    Signature {
+        #[serde(with = "serde_utils::hex_bytes")]
+        public_key: Vec<u8>,
+        #[serde(with = "serde_utils::hex_bytes")]
+        signature: Vec<u8>,
+    },
+    Preimage {
+        #[serde(with = "serde_utils::hex_bytes")]
+        preimage: Vec<u8>,
+    },
+    // 按公钥序号严格递增排列的签名
+    Multisig { signatures: Vec<KeySignature> },
+    // 按顺序解锁 `Lock::All` 中的每个条件
+    All { unlocks: Vec<Unlock> },
+    // 解锁 `Lock::Any` 中序号为 `branch` 的条件
+    Any { branch: u32, unlock: Box<Unlock> },
+}
+
+// 定义多重签名中的一个签名，`key` 为公钥在锁定条件中的序号
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySignature {
+    pub key: u32,
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub signature: Vec<u8>,
+}
+
+// 执行脚本所需的链上下文
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptContext<'a> {
+    // 签名的消息，即花费交易的交易 ID
+    pub sighash: &'a [u8],
+    // 花费交易所在区块的索引
+    pub height: u32,
+    // 花费交易所在区块之前的过去中位时间
+    pub time: u128,
+}
+
+// SHA-256 哈希
+pub fn sha256(data: &[u8]) -> Hash {
+    crypto_hash::digest(crypto_hash::Algorithm::SHA256, data)
+}
+
+// 公钥哈希，P2PKH 锁定条件与地址都使用它
+pub fn public_key_hash(public_key: &[u8]) -> Hash {
+    sha256(public_key)
+}
+
+impl Lock {
+    // 锁定到 ed25519 公钥
+    pub fn pay_to_public_key(public_key: &VerifyingKey) -> Self {
+        Lock::PubKeyHash {
+            hash: public_key_hash(public_key.as_bytes()),
+        }
+    }
+
+    // 嵌套层数，叶子条件为 1
+    pub fn depth(&self) -> usize {
+        match self {
+            Lock::All { locks } | Lock::Any { locks } => 1 + locks.iter().map(Lock::depth).max().unwrap_or(0),
+            _ => 1,
+        }
+    }
+
+    // 锁定条件对应的地址：P2PKH 为公钥哈希，其他条件为规范编码的哈希，均为十六进制
+    pub fn address(&self) -> Address {
+        match self {
+            Lock::PubKeyHash { hash } => hex::encode(hash),
+            _ => hex::encode(sha256(&encoding::encode(self))),
+        }
+    }
+
+    // 锁定到多个 ed25519 公钥中的任意 `threshold` 个
+    pub fn multisig(threshold: u32, public_keys: &[VerifyingKey]) -> Self {
+        Lock::Multisig {
+            threshold,
+            public_keys: public_keys.iter().map(|key| key.to_bytes().to_vec()).collect(),
+        }
+    }
+
+    // 结构检查：组合条件不能为空，多重签名的门限与公钥数量有效，数据不超过上限且不在组合条件中，嵌套不超过上限
+    pub fn is_well_formed(&self) -> bool {
+        fn well_formed(lock: &Lock, depth: usize) -> bool {
+            match lock {
+                _ if depth > MAX_SCRIPT_DEPTH => false,
+                Lock::Data { data } => depth == 1 && data.len() <= MAX_DATA_SIZE,
+                Lock::Multisig { threshold, public_keys } => {
+                    *threshold >= 1 && *threshold as usize <= public_keys.len() && public_keys.len() <= MAX_MULTISIG_KEYS
+                }
+                Lock::All { locks } | Lock::Any { locks } => {
+                    !locks.is_empty() && locks.iter().all(|lock| well_formed(lock, depth + 1))
+                }
+                _ => true,
+            }
+        }
+        well_formed(self, 1)
+    }
+
+    // 是否每一条满足路径都需要对交易 ID 的签名：`All` 中至少一个子条件需要签名，`Any` 中每个分支都需要签名
+    pub fn requires_signature(&self) -> bool {
+        match self {
+            Lock::PubKeyHash { .. } | Lock::Multisig { .. } => true,
+            Lock::All { locks } => locks.iter().any(Lock::requires_signature),
+            Lock::Any { locks } => !locks.is_empty() && locks.iter().all(Lock::requires_signature),
+            Lock::HashLock { .. } | Lock::AfterHeight { .. } | Lock::AfterTime { .. } | Lock::Data { .. } => false,
+        }
+    }
+
+    // 用解锁数据执行锁定条件
+    pub fn evaluate(&self, unlock: &Unlock, ctx: &ScriptContext) -> Result<(), ScriptError> {
+        match (self, unlock) {
+            (Lock::Data { .. }, _) => Err(ScriptError::Unspendable),
+            (Lock::PubKeyHash { hash }, Unlock::Signature { public_key, signature }) => {
+                if public_key_hash(public_key) != *hash {
+                    return Err(ScriptError::PublicKeyHashMismatch);
+                }
+                verify_signature(public_key, signature, ctx.sighash)
+            }
+            (Lock::HashLock { hash }, Unlock::Preimage { preimage }) => {
+                if sha256(preimage) == *hash {
+                    Ok(())
+                } else {
+                    Err(ScriptError::PreimageMismatch)
+                }
+            }
+            (Lock::AfterHeight { height }, Unlock::None) => {
+                if ctx.height >= *height {
+                    Ok(())
+                } else {
+                    Err(ScriptError::HeightLockNotReached)
+                }
+            }
+            (Lock::AfterTime { timestamp }, Unlock::None) => {
+                if ctx.time >= *timestamp {
+                    Ok(())
+                } else {
+                    Err(ScriptError::TimeLockNotReached)
+                }
+            }
+            (Lock::Multisig { threshold, public_keys }, Unlock::Multisig { signatures }) => {
+                if signatures.len() < *threshold as usize {
+                    return Err(ScriptError::NotEnoughSignatures);
+                }
+                // 序号严格递增保证每个公钥至多计入一次
+                if signatures.windows(2).any(|pair| pair[0].key >= pair[1].key) {
+                    return Err(ScriptError::InvalidKeyOrder);
+                }
+                signatures.iter().try_for_each(|sig| {
+                    let public_key = public_keys.get(sig.key as usize).ok_or(ScriptError::InvalidKeyOrder)?;
+                    verify_signature(public_key, &sig.signature, ctx.sighash)
+                })
+            }
+            (Lock::All { locks }, Unlock::All { unlocks }) => {
+                if locks.len() != unlocks.len() {
+                    return Err(ScriptError::UnlockMismatch);
+                }
+                locks
+                    .iter()
+                    .zip(unlocks)
+                    .try_for_each(|(lock, unlock)| lock.evaluate(unlock, ctx))
+            }
+            (Lock::Any { locks }, Unlock::Any { branch, unlock }) => locks
+                .get(*branch as usize)
+                .ok_or(ScriptError::BranchOutOfRange)?
+                .evaluate(unlock, ctx),
+            _ => Err(ScriptError::UnlockMismatch),
+        }
+    }
+}
+
+impl Unlock {
+    // 嵌套层数，叶子数据为 1
+    pub fn depth(&self) -> usize {
+        match self {
+            Unlock::All { unlocks } => 1 + unlocks.iter().map(Unlock::depth).max().unwrap_or(0),
+            Unlock::Any { unlock, .. } => 1 + unlock.depth(),
+            _ => 1,
+        }
+    }
+}
+
+// 校验 ed25519 签名
+pub fn verify_signature(public_key: &[u8], signature: &[u8], message: &[u8]) -> Result<(), ScriptError> {
+    let public_key = <[u8; 32]>::try_from(public_key)
+        .ok()
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+        .ok_or(ScriptError::InvalidPublicKey)?;
+    let signature = Signature::from_slice(signature).map_err(|_| ScriptError::InvalidSignature)?;
+    public_key
+        .verify(message, &signature)
+        .map_err(|_| ScriptError::InvalidSignature)
+}
+
+// 实现规范编码：首字节为类型标签，组合条件递归编码
+impl Encode for Lock {
+    fn encode(&self, enc: &mut Encoder) {
+        match self {
+            Lock::PubKeyHash { hash } => {
+                enc.put_u8(0);
+                enc.put_bytes(hash);
+            }
+            Lock::HashLock { hash } => {
+                enc.put_u8(1);
+                enc.put_bytes(hash);
+            }
+            Lock::AfterHeight { height } => {
+                enc.put_u8(2);
+                enc.put_u32(*height);
+            }
+            Lock::AfterTime { timestamp } => {
+                enc.put_u8(3);
+                enc.put_u128(*timestamp);
+            }
+            Lock::All { locks } => {
+                enc.put_u8(4);
+                enc.put_seq(locks);
+            }
+            Lock::Any { locks } => {
+                enc.put_u8(5);
+                enc.put_seq(locks);
+            }
+            Lock::Multisig { threshold, public_keys } => {
+                enc.put_u8(6);
+                enc.put_u32(*threshold);
+                enc.put_seq(public_keys);
+            }
+            Lock::Data { data } => {
+                enc.put_u8(7);
+                enc.put_bytes(data);
+            }
+        }
+    }
+}
+
+impl Lock {
+    fn decode_nested(dec: &mut Decoder, depth: usize) -> Result<Self, DecodeError> {
+        if depth > MAX_SCRIPT_DEPTH {
+            return Err(DecodeError::NestingTooDeep);
+        }
+        let decode_locks = |dec: &mut Decoder| -> Result<Vec<Lock>, DecodeError> {
+            let len = dec.get_u32()? as usize;
+            let mut locks = Vec::with_capacity(len.min(dec.remaining()));
+            for _ in 0..len {
+                locks.push(Lock::decode_nested(dec, depth + 1)?);
+            }
+            Ok(locks)
+        };
+        Ok(match dec.get_u8()? {
+            0 => Lock::PubKeyHash { hash: dec.get_bytes()? },
+            1 => Lock::HashLock { hash: dec.get_bytes()? },
+            2 => Lock::AfterHeight { height: dec.get_u32()? },
+            3 => Lock::AfterTime { timestamp: dec.get_u128()? },
+            4 => Lock::All { locks: decode_locks(dec)? },
+            5 => Lock::Any { locks: decode_locks(dec)? },
+            6 => Lock::Multisig {
+                threshold: dec.get_u32()?,
+                public_keys: dec.get_seq()?,
+            },
+            7 => Lock::Data { data: dec.get_bytes()? },
+            tag => return Err(DecodeError::InvalidTag(tag)),
+        })
+    }
+}
+
+// 解码时限制嵌套层数，避免恶意数据耗尽栈空间
+impl Decode for Lock {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Lock::decode_nested(dec, 1)
+    }
+}
+
+impl Encode for Unlock {
+    fn encode(&self, enc: &mut Encoder) {
+        match self {
+            Unlock::None => enc.put_u8(0),
+            Unlock::Signature { public_key, signature } => {
+                enc.put_u8(1);
+                enc.put_bytes(public_key);
+                enc.put_bytes(signature);
+            }
+            Unlock::Preimage { preimage } => {
+                enc.put_u8(2);
+                enc.put_bytes(preimage);
+            }
+            Unlock::All { unlocks } => {
+                enc.put_u8(3);
+                enc.put_seq(unlocks);
+            }
+            Unlock::Any { branch, unlock } => {
+                enc.put_u8(4);
+                enc.put_u32(*branch);
+                unlock.encode(enc);
+            }
+            Unlock::Multisig { signatures } => {
+                enc.put_u8(5);
+                enc.put_seq(signatures);
+            }
+        }
+    }
+}
+
+impl Unlock {
+    fn decode_nested(dec: &mut Decoder, depth: usize) -> Result<Self, DecodeError> {
+        if depth > MAX_SCRIPT_DEPTH {
+            return Err(DecodeError::NestingTooDeep);
+        }
+        Ok(match dec.get_u8()? {
+            0 => Unlock::None,
+            1 => Unlock::Signature {
+                public_key: dec.get_bytes()?,
+                signature: dec.get_bytes()?,
+            },
+            2 => Unlock::Preimage { preimage: dec.get_bytes()? },
+            3 => {
+                let len = dec.get_u32()? as usize;
+                let mut unlocks = Vec::with_capacity(len.min(dec.remaining()));
+                for _ in 0..len {
+                    unlocks.push(Unlock::decode_nested(dec, depth + 1)?);
+                }
+                Unlock::All { unlocks }
+            }
+            4 => Unlock::Any {
+                branch: dec.get_u32()?,
+                unlock: Box::new(Unlock::decode_nested(dec, depth + 1)?),
+            },
+            5 => Unlock::Multisig { signatures: dec.get_seq()? },
+            tag => return Err(DecodeError::InvalidTag(tag)),
+        })
+    }
+}
+
+impl Decode for Unlock {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Unlock::decode_nested(dec, 1)
+    }
+}
+
+impl Encode for KeySignature {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_u32(self.key);
+        enc.put_bytes(&self.signature);
+    }
+}
+
+impl Decode for KeySignature {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(KeySignature {
+            key: dec.get_u32()?,
+            signature: dec.get_bytes()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const SIGHASH: &[u8] = b"txid";
+
+    fn ctx(height: u32, time: u128) -> ScriptContext<'static> {
+        ScriptContext {
+            sighash: SIGHASH,
+            height,
+            time,
+        }
+    }
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn sign(key: &SigningKey, message: &[u8]) -> Unlock {
+        Unlock::Signature {
+            public_key: key.verifying_key().to_bytes().to_vec(),
+            signature: key.sign(message).to_bytes().to_vec(),
+        }
+    }
+
+    fn preimage(data: &[u8]) -> Unlock {
+        Unlock::Preimage {
+            preimage: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_pay_to_public_key_hash() {
+        let alice = key(1);
+        let lock = Lock::pay_to_public_key(&alice.verifying_key());
+        assert_eq!(lock.evaluate(&sign(&alice, SIGHASH), &ctx(0, 0)), Ok(()));
+
+        // 其他人的公钥、对其他消息的签名、伪造的签名与公钥
+        assert_eq!(
+            lock.evaluate(&sign(&key(2), SIGHASH), &ctx(0, 0)),
+            Err(ScriptError::PublicKeyHashMismatch)
+        );
+        assert_eq!(
+            lock.evaluate(&sign(&alice, b"other"), &ctx(0, 0)),
+            Err(ScriptError::InvalidSignature)
+        );
+        let forged = Unlock::Signature {
+            public_key: alice.verifying_key().to_bytes().to_vec(),
+            signature: vec![0; 10],
+        };
+        assert_eq!(lock.evaluate(&forged, &ctx(0, 0)), Err(ScriptError::InvalidSignature));
+        let short_key = Lock::PubKeyHash {
+            hash: public_key_hash(&[1, 2, 3]),
+        };
+        let unlock = Unlock::Signature {
+            public_key: vec![1, 2, 3],
+            signature: vec![0; 64],
+        };
+        assert_eq!(short_key.evaluate(&unlock, &ctx(0, 0)), Err(ScriptError::InvalidPublicKey));
+        assert_eq!(lock.evaluate(&Unlock::None, &ctx(0, 0)), Err(ScriptError::UnlockMismatch));
+    }
+
+    fn multisig(signers: &[(u32, &SigningKey)]) -> Unlock {
+        Unlock::Multisig {
+            signatures: signers
+                .iter()
+                .map(|(index, key)| KeySignature {
+                    key: *index,
+                    signature: key.sign(SIGHASH).to_bytes().to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_multisig() {
+        let keys = [key(1), key(2), key(3)];
+        let public_keys: Vec<VerifyingKey> = keys.iter().map(SigningKey::verifying_key).collect();
+        let lock = Lock::multisig(2, &public_keys);
+        assert!(lock.is_well_formed());
+
+        assert_eq!(lock.evaluate(&multisig(&[(0, &keys[0]), (2, &keys[2])]), &ctx(0, 0)), Ok(()));
+        assert_eq!(
+            lock.evaluate(&multisig(&[(0, &keys[0]), (1, &keys[1]), (2, &keys[2])]), &ctx(0, 0)),
+            Ok(())
+        );
+        assert_eq!(
+            lock.evaluate(&multisig(&[(1, &keys[1])]), &ctx(0, 0)),
+            Err(ScriptError::NotEnoughSignatures)
+        );
+        // 同一公钥不能计入两次，序号必须递增且在范围内
+        assert_eq!(
+            lock.evaluate(&multisig(&[(1, &keys[1]), (1, &keys[1])]), &ctx(0, 0)),
+            Err(ScriptError::InvalidKeyOrder)
+        );
+        assert_eq!(
+            lock.evaluate(&multisig(&[(2, &keys[2]), (0, &keys[0])]), &ctx(0, 0)),
+            Err(ScriptError::InvalidKeyOrder)
+        );
+        assert_eq!(
+            lock.evaluate(&multisig(&[(0, &keys[0]), (3, &keys[2])]), &ctx(0, 0)),
+            Err(ScriptError::InvalidKeyOrder)
+        );
+        // 签名与序号对应的公钥不符
+        assert_eq!(
+            lock.evaluate(&multisig(&[(0, &keys[1]), (1, &keys[2])]), &ctx(0, 0)),
+            Err(ScriptError::InvalidSignature)
+        );
+        assert_eq!(lock.evaluate(&sign(&keys[0], SIGHASH), &ctx(0, 0)), Err(ScriptError::UnlockMismatch));
+
+        assert!(!Lock::multisig(0, &public_keys).is_well_formed());
+        assert!(!Lock::multisig(4, &public_keys).is_well_formed());
+        let many: Vec<VerifyingKey> = (0..=MAX_MULTISIG_KEYS as u8).map(|i| key(i).verifying_key()).collect();
+        assert!(!Lock::multisig(1, &many).is_well_formed());
+    }
+
+    #[test]
+    fn test_hash_lock() {
+        let lock = Lock::HashLock { hash: sha256(b"secret") };
+        assert_eq!(lock.evaluate(&preimage(b"secret"), &ctx(0, 0)), Ok(()));
+        assert_eq!(
+            lock.evaluate(&preimage(b"guess"), &ctx(0, 0)),
+            Err(ScriptError::PreimageMismatch)
+        );
+        assert_eq!(
+            lock.evaluate(&sign(&key(1), SIGHASH), &ctx(0, 0)),
+            Err(ScriptError::UnlockMismatch)
+        );
+    }
+
+    #[test]
+    fn test_time_locks() {
+        let height = Lock::AfterHeight { height: 10 };
+        assert_eq!(height.evaluate(&Unlock::None, &ctx(10, 0)), Ok(()));
+        assert_eq!(
+            height.evaluate(&Unlock::None, &ctx(9, 0)),
+            Err(ScriptError::HeightLockNotReached)
+        );
+
+        let time = Lock::AfterTime { timestamp: 1_000 };
+        assert_eq!(time.evaluate(&Unlock::None, &ctx(0, 1_000)), Ok(()));
+        assert_eq!(
+            time.evaluate(&Unlock::None, &ctx(0, 999)),
+            Err(ScriptError::TimeLockNotReached)
+        );
+        assert_eq!(
+            time.evaluate(&preimage(b""), &ctx(0, 1_000)),
+            Err(ScriptError::UnlockMismatch)
+        );
+    }
+
+    #[test]
+    fn test_data_lock() {
+        let lock = Lock::Data { data: b"INV-2024-0042".to_vec() };
+        assert_eq!(lock.evaluate(&Unlock::None, &ctx(0, 0)), Err(ScriptError::Unspendable));
+        assert_eq!(lock.evaluate(&sign(&key(1), SIGHASH), &ctx(0, 0)), Err(ScriptError::Unspendable));
+        assert!(lock.is_well_formed());
+        assert!(Lock::Data { data: vec![0; MAX_DATA_SIZE] }.is_well_formed());
+        assert!(!Lock::Data { data: vec![0; MAX_DATA_SIZE + 1] }.is_well_formed());
+        // 数据不能嵌入组合条件，否则组合条件的其他分支可以花费该输出
+        let nested = Lock::Any {
+            locks: vec![lock.clone(), Lock::AfterHeight { height: 0 }],
+        };
+        assert!(!nested.is_well_formed());
+
+        assert_eq!(encoding::decode::<Lock>(&encoding::encode(&lock)), Ok(lock.clone()));
+        let json = serde_json::to_string(&lock).unwrap();
+        assert_eq!(json, r#"{"type":"data","data":"494e562d323032342d30303432"}"#);
+        assert_eq!(serde_json::from_str::<Lock>(&json).unwrap(), lock);
+    }
+
+    #[test]
+    fn test_composite_locks() {
+        // 哈希时间锁合约：收款方凭原像与签名领取，或付款方在超时后取回
+        let (alice, bob) = (key(1), key(2));
+        let htlc = Lock::Any {
+            locks: vec![
+                Lock::All {
+                    locks: vec![
+                        Lock::HashLock { hash: sha256(b"secret") },
+                        Lock::pay_to_public_key(&bob.verifying_key()),
+                    ],
+                },
+                Lock::All {
+                    locks: vec![
+                        Lock::AfterHeight { height: 100 },
+                        Lock::pay_to_public_key(&alice.verifying_key()),
+                    ],
+                },
+            ],
+        };
+
+        let claim = Unlock::Any {
+            branch: 0,
+            unlock: Box::new(Unlock::All {
+                unlocks: vec![preimage(b"secret"), sign(&bob, SIGHASH)],
+            }),
+        };
+        assert_eq!(htlc.evaluate(&claim, &ctx(0, 0)), Ok(()));
+
+        let refund = Unlock::Any {
+            branch: 1,
+            unlock: Box::new(Unlock::All {
+                unlocks: vec![Unlock::None, sign(&alice, SIGHASH)],
+            }),
+        };
+        assert_eq!(htlc.evaluate(&refund, &ctx(100, 0)), Ok(()));
+        assert_eq!(
+            htlc.evaluate(&refund, &ctx(99, 0)),
+            Err(ScriptError::HeightLockNotReached)
+        );
+
+        let missing = Unlock::Any {
+            branch: 0,
+            unlock: Box::new(Unlock::All {
+                unlocks: vec![preimage(b"secret")],
+            }),
+        };
+        assert_eq!(htlc.evaluate(&missing, &ctx(0, 0)), Err(ScriptError::UnlockMismatch));
+        let out_of_range = Unlock::Any {
+            branch: 2,
+            unlock: Box::new(Unlock::None),
+        };
+        assert_eq!(htlc.evaluate(&out_of_range, &ctx(0, 0)), Err(ScriptError::BranchOutOfRange));
+        assert_eq!(htlc.depth(), 3);
+        assert!(htlc.is_well_formed());
+    }
+
+    #[test]
+    fn test_well_formed_and_nesting_limit() {
+        assert!(!Lock::All { locks: vec![] }.is_well_formed());
+        assert!(!Lock::Any { locks: vec![] }.is_well_formed());
+
+        let nested = |depth: usize| {
+            (1..depth).fold(Lock::AfterHeight { height: 0 }, |lock, _| Lock::All { locks: vec![lock] })
+        };
+        assert!(nested(MAX_SCRIPT_DEPTH).is_well_formed());
+        assert!(!nested(MAX_SCRIPT_DEPTH + 1).is_well_formed());
+
+        let bytes = encoding::encode(&nested(MAX_SCRIPT_DEPTH));
+        assert_eq!(encoding::decode::<Lock>(&bytes), Ok(nested(MAX_SCRIPT_DEPTH)));
+        let bytes = encoding::encode(&nested(MAX_SCRIPT_DEPTH + 1));
+        assert_eq!(encoding::decode::<Lock>(&bytes), Err(DecodeError::NestingTooDeep));
+        assert_eq!(encoding::decode::<Lock>(&[encoding::ENCODING_VERSION, 9]), Err(DecodeError::InvalidTag(9)));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let lock = Lock::Any {
+            locks: vec![
+                Lock::HashLock { hash: sha256(b"secret") },
+                Lock::AfterTime { timestamp: u128::MAX },
+                Lock::multisig(1, &[key(1).verifying_key()]),
+            ],
+        };
+        assert_eq!(encoding::decode::<Lock>(&encoding::encode(&lock)), Ok(lock.clone()));
+        let json = serde_json::to_string(&lock).unwrap();
+        assert_eq!(serde_json::from_str::<Lock>(&json).unwrap(), lock);
+
+        let unlock = Unlock::Any {
+            branch: 1,
+            unlock: Box::new(Unlock::All {
+                unlocks: vec![Unlock::None, sign(&key(1), SIGHASH), multisig(&[(0, &key(1))])],
+            }),
+        };
+        assert_eq!(encoding::decode::<Unlock>(&encoding::encode(&unlock)), Ok(unlock.clone()));
+        let json = serde_json::to_string(&unlock).unwrap();
+        assert_eq!(serde_json::from_str::<Unlock>(&json).unwrap(), unlock);
+    }
+}
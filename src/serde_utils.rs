@@ -0,0 +1,122 @@
+// JSON 序列化辅助：哈希使用十六进制字符串，u128 使用十进制字符串以兼容 JavaScript
+
+// 哈希与字节串编码为十六进制字符串
+pub mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hex::decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+// 可选的字节串，存在时编码为十六进制字符串
+pub mod hex_bytes_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_some(&hex::encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| hex::decode(s).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+// 字节串列表编码为十六进制字符串数组
+pub mod hex_bytes_seq {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(items: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(items.iter().map(hex::encode))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error> {
+        let items = Vec::<String>::deserialize(deserializer)?;
+        items
+            .into_iter()
+            .map(|s| hex::decode(s).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+// u128 编码为十进制字符串
+pub mod u128_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transaction::Output;
+    use crate::{Block, Transaction};
+    use serde_json::json;
+
+    fn sample_block() -> Block {
+        let mut block = Block::new(
+            0,
+            1_700_000_000_000,
+            vec![0; 32],
+            vec![Transaction::coinbase(
+                0,
+                vec![Output {
+                    receiver: "Alice".to_owned(),
+                    value: 50,
+                    asset: None,
+                    lock: None,
+                }],
+            )],
+            0x00ffffffffffffffffffffffffffffff,
+        );
+        block.mine().unwrap();
+        block
+    }
+
+    #[test]
+    fn test_block_json_round_trip() {
+        let block = sample_block();
+        let text = serde_json::to_string(&block).unwrap();
+        let decoded: Block = serde_json::from_str(&text).unwrap();
+        assert_eq!(decoded, block);
+    }
+
+    #[test]
+    fn test_block_json_schema() {
+        let block = sample_block();
+        let value = serde_json::to_value(&block).unwrap();
+
+        assert_eq!(value["hash"], json!(hex::encode(&block.hash)));
+        assert_eq!(value["prev_block_hash"], json!("00".repeat(32)));
+        assert_eq!(value["timestamp"], json!("1700000000000"));
+        assert_eq!(value["difficulty"], json!(block.difficulty.to_string()));
+        assert_eq!(value["transactions"][0]["outputs"][0]["receiver"], json!("Alice"));
+    }
+
+    #[test]
+    fn test_rejects_malformed_fields() {
+        let mut value = serde_json::to_value(sample_block()).unwrap();
+        value["hash"] = json!("not-hex");
+        assert!(serde_json::from_value::<Block>(value.clone()).is_err());
+
+        value["hash"] = json!("00");
+        value["timestamp"] = json!(1);
+        assert!(serde_json::from_value::<Block>(value).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// 定义访问角色，按权限从低到高排列
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    ReadOnly,
+    Submitter,
+    #[serde(alias = "admin")]
+    Miner,
+}
+
+// 定义单个 API Key 配置
+#[derive(Debug, Deserialize)]
+pub struct ApiKey {
+    pub key: String,
+    pub role: Role,
+    #[serde(default)]
+    pub name: String,
+}
+
+// 定义鉴权配置，从 JSON 文件加载
+#[derive(Debug, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub keys: Vec<ApiKey>,
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    #[serde(default = "default_anonymous_read")]
+    pub anonymous_read: bool,
+}
+
+fn default_anonymous_read() -> bool {
+    true
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            keys: vec![],
+            cors_origins: vec![],
+            anonymous_read: default_anonymous_read(),
+        }
+    }
+}
+
+// 鉴权结果，记录调用方身份
+#[derive(Clone, Debug)]
+pub struct Caller {
+    // API Key 在配置中的序号，用于限流
+    pub id: usize,
+    pub name: String,
+    pub role: Role,
+}
+
+// 鉴权器，按 key 索引角色
+#[derive(Debug)]
+pub struct Authenticator {
+    keys: HashMap<String, Caller>,
+    anonymous_read: bool,
+    pub cors_origins: Vec<String>,
+}
+
+impl Authenticator {
+    // 从配置创建鉴权器
+    pub fn new(config: AuthConfig) -> Self {
+        let keys = config
+            .keys
+            .into_iter()
+            .enumerate()
+            .map(|(i, k)| {
+                // 未命名的 key 以序号区分，用于日志
+                let name = if k.name.is_empty() {
+                    format!("key-{}", i)
+                } else {
+                    k.name
+                };
+                let caller = Caller { id: i, name, role: k.role };
+                (k.key, caller)
+            })
+            .collect();
+
+        Authenticator {
+            keys,
+            anonymous_read: config.anonymous_read,
+            cors_origins: config.cors_origins,
+        }
+    }
+
+    // 从文件加载鉴权配置
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_json(&content)
+    }
+
+    // 解析 JSON 鉴权配置，重复的 key 视为配置错误，避免后出现的条目静默覆盖前面的角色
+    fn from_json(content: &str) -> std::io::Result<Self> {
+        let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let config: AuthConfig = serde_json::from_str(content).map_err(|e| invalid(e.to_string()))?;
+        let mut seen = HashSet::new();
+        for (i, k) in config.keys.iter().enumerate() {
+            if !seen.insert(k.key.as_str()) {
+                return Err(invalid(format!("第 {} 个 API Key 与前面的条目重复", i + 1)));
+            }
+        }
+        Ok(Self::new(config))
+    }
+
+    // 从请求头中提取 API Key，支持 `Authorization: Bearer <key>` 与 `X-API-Key`；
+    // `Authorization` 不是 Bearer 方案时（如代理附加的 Basic 认证）回退到 `X-API-Key`
+    fn extract_key(req: &HttpRequest) -> Option<&str> {
+        let bearer = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim);
+        if bearer.is_some() {
+            return bearer;
+        }
+        req.headers()
+            .get("X-API-Key")
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+    }
+
+    // 识别调用方身份，未携带 key 时返回 None
+    pub fn identify(&self, req: &HttpRequest) -> Result<Option<Caller>, HttpResponse> {
+        match Self::extract_key(req) {
+            Some(key) => match self.keys.get(key) {
+                Some(caller) => Ok(Some(caller.clone())),
+                None => Err(HttpResponse::Unauthorized().json(
+                    json!({ "success": false, "message": "无效的 API Key" })
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+
+    // 校验调用方是否具备所需角色
+    pub fn authorize(&self, req: &HttpRequest, required: Role) -> Result<Option<Caller>, HttpResponse> {
+        let caller = self.identify(req)?;
+        match &caller {
+            Some(c) if c.role >= required => Ok(caller),
+            Some(_) => Err(HttpResponse::Forbidden().json(
+                json!({ "success": false, "message": "权限不足" })
+            )),
+            None if required == Role::ReadOnly && self.anonymous_read => Ok(None),
+            None => Err(HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .json(json!({ "success": false, "message": "需要 API Key" }))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    fn authenticator(anonymous_read: bool) -> Authenticator {
+        let key = |key: &str, role| ApiKey { key: key.to_string(), role, name: String::new() };
+        Authenticator::new(AuthConfig {
+            keys: vec![key("read", Role::ReadOnly), key("submit", Role::Submitter), key("mine", Role::Miner)],
+            anonymous_read,
+            ..AuthConfig::default()
+        })
+    }
+
+    fn status(result: Result<Option<Caller>, HttpResponse>) -> StatusCode {
+        match result {
+            Ok(_) => StatusCode::OK,
+            Err(response) => response.status(),
+        }
+    }
+
+    #[test]
+    fn test_role_order() {
+        assert!(Role::ReadOnly < Role::Submitter);
+        assert!(Role::Submitter < Role::Miner);
+    }
+
+    #[test]
+    fn test_authorize() {
+        let auth = authenticator(true);
+        let anonymous = TestRequest::default().to_http_request();
+        let bearer = |key: &str| {
+            TestRequest::default()
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", key)))
+                .to_http_request()
+        };
+        let api_key = |key: &str| TestRequest::default().insert_header(("X-API-Key", key)).to_http_request();
+
+        // 未携带 key：只读接口允许匿名访问，写接口返回 401
+        assert_eq!(status(auth.authorize(&anonymous, Role::ReadOnly)), StatusCode::OK);
+        assert_eq!(status(auth.authorize(&anonymous, Role::Submitter)), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(authenticator(false).authorize(&anonymous, Role::ReadOnly)), StatusCode::UNAUTHORIZED);
+
+        // 两种请求头均可携带 key，角色不足返回 403，未知 key 返回 401
+        for request in [bearer("submit"), api_key("submit")] {
+            let caller = auth.authorize(&request, Role::Submitter).unwrap().unwrap();
+            assert_eq!(caller.role, Role::Submitter);
+            assert_eq!(status(auth.authorize(&request, Role::Miner)), StatusCode::FORBIDDEN);
+        }
+        assert_eq!(status(auth.authorize(&bearer("read"), Role::Submitter)), StatusCode::FORBIDDEN);
+        assert_eq!(status(auth.authorize(&bearer("mine"), Role::Miner)), StatusCode::OK);
+        assert_eq!(status(auth.authorize(&api_key("unknown"), Role::ReadOnly)), StatusCode::UNAUTHORIZED);
+
+        // 非 Bearer 的 Authorization 头回退到 X-API-Key
+        let request = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Basic dXNlcjpwYXNz"))
+            .insert_header(("X-API-Key", "mine"))
+            .to_http_request();
+        assert_eq!(status(auth.authorize(&request, Role::Miner)), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let config = r#"{"keys": [{"key": "a", "role": "read_only"}, {"key": "a", "role": "miner"}]}"#;
+        assert_eq!(Authenticator::from_json(config).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        let config = r#"{"keys": [{"key": "a", "role": "read_only"}, {"key": "b", "role": "miner"}]}"#;
+        assert_eq!(Authenticator::from_json(config).unwrap().keys.len(), 2);
+    }
+}
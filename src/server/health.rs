@@ -0,0 +1,183 @@
+use actix_web::HttpResponse;
+use crate::snapshot_verify::VerificationStatus;
+use blockchainlib::Blockchain;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// 探测文件的序号，使并发的检查使用不同的文件
+static PROBE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// 定义健康检查配置
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    // 数据目录，用于检查存储是否可写
+    pub data_dir: PathBuf,
+    // 允许落后于最高节点的区块数
+    pub max_lag: usize,
+    // 至少需要连接的节点数
+    pub min_peers: usize,
+}
+
+// 定义单项检查结果
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: Value,
+}
+
+// 检查数据目录是否可写
+pub fn storage_writable(config: &HealthConfig) -> Check {
+    let probe = config.data_dir.join(format!(
+        ".health_probe.{}.{}",
+        std::process::id(),
+        PROBE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = fs::create_dir_all(&config.data_dir)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| match fs::remove_file(&probe) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        });
+
+    Check {
+        name: "storage_writable",
+        ok: result.is_ok(),
+        detail: json!({
+            "data_dir": config.data_dir.display().to_string(),
+            "error": result.err().map(|e| e.to_string()),
+        }),
+    }
+}
+
+// 检查区块链是否已加载
+pub fn chain_loaded(blockchain: &Blockchain) -> Check {
+    Check {
+        name: "chain_loaded",
+        ok: blockchain.height() > 0,
+        detail: json!({ "height": blockchain.height() }),
+    }
+}
+
+// 检查是否已与对端同步，以对端高度的中位数（偶数个时取较低者）为准，单个对端上报的虚高高度不影响就绪
+pub fn synced(config: &HealthConfig, height: usize, peers: usize, peer_heights: &[usize]) -> Check {
+    let mut heights = peer_heights.to_vec();
+    heights.sort_unstable();
+    let peer_height = heights.get(heights.len().saturating_sub(1) / 2).copied();
+    let (ok, lag) = match peer_height {
+        Some(best) => {
+            let lag = best.saturating_sub(height);
+            (lag <= config.max_lag, Some(lag))
+        }
+        // 已有连接但尚未收到任何高度时视为未同步
+        None => (peers == 0, None),
+    };
+
+    Check {
+        name: "synced",
+        ok,
+        detail: json!({
+            "height": height,
+            "peer_height": peer_height,
+            "lag": lag,
+            "max_lag": config.max_lag,
+        }),
+    }
+}
+
+// 检查已连接的节点数
+pub fn peers_connected(config: &HealthConfig, peers: usize) -> Check {
+    Check {
+        name: "peers_connected",
+        ok: peers >= config.min_peers,
+        detail: json!({ "peers": peers, "min_peers": config.min_peers }),
+    }
+}
+
+// 检查快照的后台验证结果，验证进行中不影响就绪
+pub fn snapshot_verified(status: &VerificationStatus) -> Check {
+    let detail = match status {
+        VerificationStatus::Pending { verified_height } => {
+            json!({ "status": "pending", "verified_height": verified_height })
+        }
+        VerificationStatus::Verified => json!({ "status": "verified" }),
+        VerificationStatus::Failed(error) => json!({ "status": "failed", "error": error }),
+    };
+
+    Check {
+        name: "snapshot_verified",
+        ok: !matches!(status, VerificationStatus::Failed(_)),
+        detail,
+    }
+}
+
+// 汇总检查结果，全部通过返回 200，否则返回 503
+pub fn report(checks: Vec<Check>) -> HttpResponse {
+    let ok = checks.iter().all(|check| check.ok);
+    let body = json!({
+        "success": ok,
+        "status": if ok { "ok" } else { "unavailable" },
+        "checks": checks,
+    });
+
+    if ok {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> HealthConfig {
+        HealthConfig {
+            data_dir: std::env::temp_dir(),
+            max_lag: 2,
+            min_peers: 1,
+        }
+    }
+
+    #[test]
+    fn test_synced() {
+        let config = config();
+        assert!(synced(&config, 10, 0, &[]).ok);
+        assert!(!synced(&config, 10, 1, &[]).ok);
+        assert!(synced(&config, 10, 1, &[12]).ok);
+        assert!(!synced(&config, 10, 1, &[13]).ok);
+        assert!(synced(&config, 10, 1, &[5]).ok);
+        // 少数对端上报的虚高高度不影响就绪
+        assert!(synced(&config, 10, 2, &[10, u32::MAX as usize]).ok);
+        assert!(synced(&config, 10, 3, &[u32::MAX as usize, 11, 10]).ok);
+        assert!(!synced(&config, 10, 3, &[20, 20, 10]).ok);
+    }
+
+    #[test]
+    fn test_peers_connected() {
+        let config = config();
+        assert!(!peers_connected(&config, 0).ok);
+        assert!(peers_connected(&config, 1).ok);
+    }
+
+    #[test]
+    fn test_concurrent_storage_probes() {
+        let config = HealthConfig {
+            data_dir: std::env::temp_dir().join(format!("health-probe-test-{}", std::process::id())),
+            ..config()
+        };
+        let handles: Vec<_> = (0..16)
+            .map(|_| {
+                let config = config.clone();
+                std::thread::spawn(move || (0..20).all(|_| storage_writable(&config).ok))
+            })
+            .collect();
+        assert!(handles.into_iter().all(|handle| handle.join().unwrap()));
+        assert_eq!(fs::read_dir(&config.data_dir).unwrap().count(), 0);
+        fs::remove_dir(&config.data_dir).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// HTTP 请求耗时直方图的分桶上限（秒）
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0,
+];
+
+// 定义直方图
+#[derive(Debug, Default)]
+struct Histogram {
+    counts: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+            if value <= *bound {
+                self.counts[i] += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+// 定义节点运行指标
+#[derive(Debug, Default)]
+struct Counters {
+    blocks_received: u64,
+    blocks_rejected: BTreeMap<String, u64>,
+    transactions_received: u64,
+    transactions_rejected: BTreeMap<String, u64>,
+    hashrate: f64,
+    http_requests: BTreeMap<(String, String, u16), Histogram>,
+}
+
+// 定义抓取时采集的链上状态
+#[derive(Debug, Default)]
+pub struct ChainSnapshot {
+    pub height: usize,
+    pub tip_timestamp: u128,
+    pub difficulty: u128,
+    pub utxo_count: usize,
+    pub mempool_transactions: usize,
+    pub mempool_bytes: usize,
+    pub peers: usize,
+}
+
+// Prometheus 指标注册表
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 记录收到的区块
+    pub fn block_received(&self) {
+        self.counters.lock().unwrap().blocks_received += 1;
+    }
+
+    // 记录被拒绝的区块，原因按错误的变体名称统计
+    pub fn block_rejected(&self, error: &impl Debug) {
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .blocks_rejected
+            .entry(variant_name(error))
+            .or_default() += 1;
+    }
+
+    // 记录收到的交易
+    pub fn transactions_received(&self, count: usize) {
+        self.counters.lock().unwrap().transactions_received += count as u64;
+    }
+
+    // 记录被拒绝的交易，原因按错误的变体名称统计
+    pub fn transactions_rejected(&self, error: &impl Debug, count: usize) {
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .transactions_rejected
+            .entry(variant_name(error))
+            .or_default() += count as u64;
+    }
+
+    // 记录最近一次挖矿的算力
+    pub fn mining_finished(&self, attempts: u64, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.counters.lock().unwrap().hashrate = attempts as f64 / secs;
+        }
+    }
+
+    // 记录 HTTP 请求耗时
+    pub fn http_request(&self, method: &str, path: &str, status: u16, elapsed: Duration) {
+        self.counters
+            .lock()
+            .unwrap()
+            .http_requests
+            .entry((method.to_string(), path.to_string(), status))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    // 以 Prometheus 文本格式导出全部指标
+    pub fn render(&self, chain: &ChainSnapshot) -> String {
+        let counters = self.counters.lock().unwrap();
+        let mut out = String::new();
+
+        gauge(&mut out, "blockchain_height", "Number of blocks in the chain", chain.height as f64);
+        gauge(
+            &mut out,
+            "blockchain_tip_timestamp_milliseconds",
+            "Timestamp of the chain tip in milliseconds",
+            chain.tip_timestamp as f64,
+        );
+        gauge(&mut out, "blockchain_difficulty", "Difficulty target of the chain tip", chain.difficulty as f64);
+        gauge(&mut out, "blockchain_utxo_set_size", "Number of unspent transaction outputs", chain.utxo_count as f64);
+        gauge(
+            &mut out,
+            "blockchain_mempool_transactions",
+            "Number of transactions waiting in the mempool",
+            chain.mempool_transactions as f64,
+        );
+        gauge(&mut out, "blockchain_mempool_bytes", "Serialized size of the mempool", chain.mempool_bytes as f64);
+        gauge(&mut out, "blockchain_mining_hashrate", "Hashes per second of the last mining run", counters.hashrate);
+        gauge(&mut out, "blockchain_peers", "Number of connected P2P peers", chain.peers as f64);
+
+        header(&mut out, "blockchain_blocks_received_total", "Blocks submitted for validation", "counter");
+        let _ = writeln!(out, "blockchain_blocks_received_total {}", counters.blocks_received);
+        header(&mut out, "blockchain_blocks_rejected_total", "Blocks rejected by validation", "counter");
+        for (reason, count) in &counters.blocks_rejected {
+            let _ = writeln!(out, "blockchain_blocks_rejected_total{{reason=\"{}\"}} {}", escape(reason), count);
+        }
+
+        header(&mut out, "blockchain_transactions_received_total", "Transactions submitted to the node", "counter");
+        let _ = writeln!(out, "blockchain_transactions_received_total {}", counters.transactions_received);
+        header(&mut out, "blockchain_transactions_rejected_total", "Transactions rejected by validation", "counter");
+        for (reason, count) in &counters.transactions_rejected {
+            let _ = writeln!(out, "blockchain_transactions_rejected_total{{reason=\"{}\"}} {}", escape(reason), count);
+        }
+
+        header(&mut out, "http_request_duration_seconds", "HTTP request latency", "histogram");
+        for ((method, path, status), histogram) in &counters.http_requests {
+            let labels = format!("method=\"{}\",path=\"{}\",status=\"{}\"", escape(method), escape(path), status);
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.counts.iter()) {
+                let _ = writeln!(out, "http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}", labels, bound, count);
+            }
+            let _ = writeln!(out, "http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, histogram.count);
+            let _ = writeln!(out, "http_request_duration_seconds_sum{{{}}} {}", labels, histogram.sum);
+            let _ = writeln!(out, "http_request_duration_seconds_count{{{}}} {}", labels, histogram.count);
+        }
+
+        out
+    }
+}
+
+// 错误的变体名称，不含携带的数据，使标签取值有限
+fn variant_name(error: &impl Debug) -> String {
+    let debug = format!("{:?}", error);
+    let end = debug.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(debug.len());
+    debug[..end].to_string()
+}
+
+// 按文本格式转义标签值中的反斜杠、引号与换行
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, help, "gauge");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blockchainlib::blockchain::BlockValidationErr;
+    use blockchainlib::transaction::TransactionError;
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::new();
+        metrics.block_received();
+        metrics.block_rejected(&BlockValidationErr::InvalidHash);
+        // 携带数据的错误只按变体名称统计
+        metrics.transactions_rejected(&BlockValidationErr::InvalidTransaction(TransactionError::NoInputs), 2);
+        metrics.transactions_rejected(&BlockValidationErr::InvalidTransaction(TransactionError::TooLarge), 1);
+        metrics.http_request("GET", "/a\"b\\", 404, Duration::from_millis(20));
+        metrics.http_request("GET", "/scan", 200, Duration::from_millis(20));
+
+        let out = metrics.render(&ChainSnapshot {
+            height: 3,
+            ..Default::default()
+        });
+
+        assert!(out.contains("blockchain_height 3\n"));
+        assert!(out.contains("blockchain_blocks_rejected_total{reason=\"InvalidHash\"} 1\n"));
+        assert!(out.contains("blockchain_transactions_rejected_total{reason=\"InvalidTransaction\"} 3\n"));
+        assert!(out.contains("path=\"/a\\\"b\\\\\",status=\"404\""));
+        assert!(out.contains(
+            "http_request_duration_seconds_bucket{method=\"GET\",path=\"/scan\",status=\"200\",le=\"0.01\"} 0\n"
+        ));
+        assert!(out.contains(
+            "http_request_duration_seconds_bucket{method=\"GET\",path=\"/scan\",status=\"200\",le=\"0.025\"} 1\n"
+        ));
+    }
+}
@@ -1 +1,6 @@
+pub mod auth;
+pub mod health;
+pub mod metrics;
+pub mod rate_limit;
+#[allow(clippy::module_inception)]
 pub mod server;
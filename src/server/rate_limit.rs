@@ -0,0 +1,183 @@
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use serde_json::json;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::auth::Caller;
+
+// 超过该数量时清理已回满的令牌桶，避免内存无限增长
+const MAX_IDLE_BUCKETS: usize = 10_000;
+// 两次清理之间的最短间隔，避免令牌桶较多时每个请求都遍历全部令牌桶
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+// 定义令牌桶
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// 令牌桶与上次清理的时间
+#[derive(Debug)]
+struct Buckets<K> {
+    buckets: HashMap<K, Bucket>,
+    pruned: Option<Instant>,
+}
+
+// 定义限流器，每个 key 对应一个令牌桶
+#[derive(Debug)]
+pub struct RateLimiter<K = String> {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<Buckets<K>>,
+}
+
+impl<K: Hash + Eq + Clone> RateLimiter<K> {
+    // 创建限流器，`capacity` 为突发容量，`refill_per_sec` 为每秒补充的令牌数
+    pub fn new(capacity: u32, refill_per_sec: f64) -> Self {
+        RateLimiter {
+            capacity: capacity as f64,
+            refill_per_sec,
+            buckets: Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                pruned: None,
+            }),
+        }
+    }
+
+    // 尝试消耗一个令牌，失败时返回需要等待的时长
+    pub fn check(&self, key: &K) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &K, now: Instant) -> Result<(), Duration> {
+        let mut state = self.buckets.lock().unwrap();
+
+        if state.buckets.len() > MAX_IDLE_BUCKETS
+            && state.pruned.is_none_or(|pruned| now.saturating_duration_since(pruned) >= PRUNE_INTERVAL)
+        {
+            let capacity = self.capacity;
+            let refill = self.refill_per_sec;
+            state.buckets.retain(|_, b| {
+                b.tokens + now.saturating_duration_since(b.updated).as_secs_f64() * refill < capacity
+            });
+            state.pruned = Some(now);
+        }
+
+        let bucket = state.buckets.entry(key.clone()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+
+        // 按时间补充令牌
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if self.refill_per_sec > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+}
+
+// 定义请求限制配置
+#[derive(Debug)]
+pub struct RequestLimits {
+    pub per_ip: RateLimiter,
+    // 按 API Key 在配置中的序号限流，名称可能重复
+    pub per_key: RateLimiter<usize>,
+    pub max_payload_bytes: usize,
+    pub max_transactions: usize,
+}
+
+impl RequestLimits {
+    // 按客户端 IP 限流
+    pub fn check_ip(&self, req: &HttpRequest) -> Result<(), HttpResponse> {
+        let ip = req
+            .peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_default();
+        self.per_ip.check(&ip).map_err(too_many_requests)
+    }
+
+    // 按 API Key 限流
+    pub fn check_key(&self, caller: &Caller) -> Result<(), HttpResponse> {
+        self.per_key.check(&caller.id).map_err(too_many_requests)
+    }
+
+    // 检查单次请求的交易数量
+    pub fn check_transactions(&self, count: usize) -> Result<(), HttpResponse> {
+        if count > self.max_transactions {
+            return Err(HttpResponse::PayloadTooLarge().json(json!({
+                "success": false,
+                "message": format!("单次请求最多包含 {} 个交易", self.max_transactions),
+                "max_transactions": self.max_transactions,
+            })));
+        }
+        Ok(())
+    }
+}
+
+// 构造 429 响应，附带重试时间
+fn too_many_requests(wait: Duration) -> HttpResponse {
+    let secs = wait.as_secs_f64().ceil().min(u32::MAX as f64) as u64;
+    HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, secs.to_string()))
+        .json(json!({
+            "success": false,
+            "message": "请求过于频繁",
+            "retry_after_secs": secs,
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refill() {
+        let limiter = RateLimiter::new(2, 1.0);
+        let start = Instant::now();
+
+        assert!(limiter.check_at(&"a", start).is_ok());
+        assert!(limiter.check_at(&"a", start).is_ok());
+        let wait = limiter.check_at(&"a", start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+
+        // 不同的 key 互不影响
+        assert!(limiter.check_at(&"b", start).is_ok());
+
+        // 一秒后补充一个令牌
+        assert!(limiter.check_at(&"a", start + Duration::from_secs(1)).is_ok());
+        assert!(limiter.check_at(&"a", start + Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn test_prune_interval() {
+        let limiter = RateLimiter::new(1, 1.0);
+        let start = Instant::now();
+        let count = |limiter: &RateLimiter<usize>| limiter.buckets.lock().unwrap().buckets.len();
+        for key in 0..=MAX_IDLE_BUCKETS {
+            limiter.check_at(&key, start).unwrap();
+        }
+
+        // 已回满的令牌桶被清理，随后一个间隔内不再清理
+        let later = start + Duration::from_secs(1);
+        limiter.check_at(&0, later).unwrap();
+        assert_eq!(count(&limiter), 1);
+        for key in 1..=MAX_IDLE_BUCKETS + 1 {
+            limiter.check_at(&key, later).unwrap();
+        }
+        limiter.check_at(&0, later).unwrap_err();
+        assert_eq!(count(&limiter), MAX_IDLE_BUCKETS + 2);
+        limiter.check_at(&0, later + PRUNE_INTERVAL).unwrap();
+        assert_eq!(count(&limiter), 1);
+    }
+}
@@ -1,126 +1,859 @@
 use actix_cors::Cors;
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::dev::Service;
+use actix_web::{error, http::header, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use serde_json::json;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{error, info, info_span, warn, Instrument};
 use serde::{Deserialize, Serialize};
 use blockchainlib::{Blockchain, Block};
+use blockchainlib::blockchain::fill_block;
+use blockchainlib::transaction::TransactionError;
+use blockchainlib::notary::{self, Notary, NotaryError, DOCUMENT_HASH_SIZE};
+use blockchainlib::script::MAX_DATA_SIZE;
 use blockchainlib::*;
+use super::auth::{Authenticator, Caller, Role};
+use super::health::{self, HealthConfig};
+use super::metrics::{ChainSnapshot, Metrics};
+use super::rate_limit::RequestLimits;
+use crate::p2p::p2p_node::P2PNode;
+use crate::snapshot_verify::SharedStatus;
 
 // 新增交易请求结构
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransactionRequest {
-    inputs: Vec<transaction::Output>,
+    inputs: Vec<transaction::Input>,
     outputs: Vec<transaction::Output>,
+    #[serde(default)]
+    lock_time: Option<transaction::LockTime>,
+    #[serde(default)]
+    issuance: Option<transaction::Issuance>,
+    // `/mine` 请求中第一个交易需要提交区块索引
+    #[serde(default)]
+    coinbase: Option<transaction::Coinbase>,
+}
+
+impl From<TransactionRequest> for Transaction {
+    fn from(t: TransactionRequest) -> Self {
+        Transaction {
+            inputs: t.inputs,
+            outputs: t.outputs,
+            coinbase: t.coinbase,
+            lock_time: t.lock_time,
+            issuance: t.issuance,
+        }
+    }
+}
+
+// 单次生成区块数量上限
+const MAX_GENERATE_BLOCKS: usize = 1000;
+
+// 回归测试网生成区块请求
+#[derive(Debug, Deserialize)]
+pub struct GenerateRequest {
+    blocks: usize,
+    #[serde(default = "default_generate_address")]
+    address: String,
+}
+
+fn default_generate_address() -> String {
+    "miner".to_string()
+}
+
+// 手续费估算请求，`target_blocks` 为期望在多少个区块内被打包
+#[derive(Debug, Deserialize)]
+pub struct FeeEstimateQuery {
+    #[serde(default = "default_target_blocks")]
+    target_blocks: usize,
+}
+
+fn default_target_blocks() -> usize {
+    6
+}
+
+// 数据输出查询请求，`format` 指定路径中数据的写法：十六进制（默认）或 UTF-8 文本
+#[derive(Debug, Default, Deserialize)]
+pub struct MemoQuery {
+    #[serde(default)]
+    format: MemoFormat,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoFormat {
+    #[default]
+    Hex,
+    Text,
+}
+
+// 存证请求：待打包的文档 SHA-256 哈希
+#[derive(Debug, Deserialize)]
+pub struct NotarizeRequest {
+    #[serde(with = "serde_utils::hex_bytes_seq")]
+    hashes: Vec<Vec<u8>>,
+}
+
+// 回归测试网模拟时间请求，`timestamp` 为空时恢复系统时间
+#[derive(Debug, Deserialize)]
+pub struct MockTimeRequest {
+    timestamp: Option<u64>,
 }
 
 #[derive(Clone)]
 pub(crate) struct MyServer {
     pub(crate) address: String,
     pub(crate) blockchain: Arc<Mutex<Blockchain>>,
+    pub(crate) mempool: Arc<Mutex<Mempool>>,
+    pub(crate) auth: Arc<Authenticator>,
+    pub(crate) limits: Arc<RequestLimits>,
+    pub(crate) metrics: Arc<Metrics>,
+    pub(crate) p2p: Option<Arc<P2PNode>>,
+    pub(crate) health: Arc<HealthConfig>,
+    pub(crate) store: Option<Arc<Mutex<BlockStore>>>,
+    pub(crate) snapshot_status: Option<SharedStatus>,
+    pub(crate) notary: Arc<Mutex<Notary>>,
 }
 
 impl MyServer {
-    pub fn new(address: &str, blockchain: Arc<Mutex<Blockchain>>) -> Self {
+    pub fn new(
+        address: &str,
+        blockchain: Arc<Mutex<Blockchain>>,
+        mempool: Arc<Mutex<Mempool>>,
+        auth: Authenticator,
+        limits: RequestLimits,
+        health: HealthConfig,
+    ) -> Self {
         MyServer {
             address: address.to_string(),
             blockchain,
+            mempool,
+            auth: Arc::new(auth),
+            limits: Arc::new(limits),
+            metrics: Arc::new(Metrics::new()),
+            p2p: None,
+            health: Arc::new(health),
+            store: None,
+            snapshot_status: None,
+            notary: Arc::new(Mutex::new(Notary::new())),
         }
     }
 
+    // 关联区块存储，新挖出的区块会写入磁盘
+    pub fn with_store(mut self, store: Arc<Mutex<BlockStore>>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    // 关联快照后台验证状态，验证失败时节点不再就绪
+    pub fn with_snapshot_status(mut self, status: SharedStatus) -> Self {
+        self.snapshot_status = Some(status);
+        self
+    }
+
+    // 关联持久化的存证服务，未关联时存证记录只保存在内存中
+    pub fn with_notary(mut self, notary: Arc<Mutex<Notary>>) -> Self {
+        self.notary = notary;
+        self
+    }
+
+    // 关联 P2P 节点，用于上报连接数
+    pub fn with_p2p(mut self, node: Arc<P2PNode>) -> Self {
+        self.p2p = Some(node);
+        self
+    }
+
+    // 限流并鉴权：先按 IP 限流，鉴权通过后再按 API Key 限流
+    fn admit(
+        req: &HttpRequest,
+        auth: &Authenticator,
+        limits: &RequestLimits,
+        required: Role,
+    ) -> Result<Option<Caller>, HttpResponse> {
+        limits.check_ip(req)?;
+        let caller = auth.authorize(req, required)?;
+        if let Some(caller) = &caller {
+            limits.check_key(caller)?;
+        }
+        Ok(caller)
+    }
+
     pub(crate) async fn run(self) -> std::io::Result<()> {
         let blockchain = self.blockchain.clone();
+        let mempool = self.mempool.clone();
+        let auth = web::Data::from(self.auth.clone());
+        let limits = web::Data::from(self.limits.clone());
+        let metrics = web::Data::from(self.metrics.clone());
+        let p2p = web::Data::new(self.p2p.clone());
+        let health = web::Data::from(self.health.clone());
+        let store = web::Data::new(self.store.clone());
+        let snapshot_status = web::Data::new(self.snapshot_status.clone());
+        let notary = web::Data::new(self.notary.clone());
         HttpServer::new(move || {
-            let cors = Cors::default()
+            let mut cors = Cors::default()
                 .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
-                .allowed_headers(vec!["Content-Type", "Authorization", "X-Requested-With"]);
+                .allowed_headers(vec!["Content-Type", "Authorization", "X-API-Key", "X-Requested-With"]);
+            for origin in &auth.cors_origins {
+                cors = if origin == "*" {
+                    cors.allow_any_origin()
+                } else {
+                    cors.allowed_origin(origin)
+                };
+            }
+
+            // 请求体超过上限时返回 413
+            let json_config = web::JsonConfig::default()
+                .limit(limits.max_payload_bytes)
+                .error_handler(|err, _req| {
+                    let response = match &err {
+                        error::JsonPayloadError::Overflow { limit }
+                        | error::JsonPayloadError::OverflowKnownLength { limit, .. } => {
+                            HttpResponse::PayloadTooLarge().json(json!({
+                                "success": false,
+                                "message": format!("请求体不能超过 {} 字节", limit),
+                                "max_payload_bytes": limit,
+                            }))
+                        }
+                        _ => HttpResponse::BadRequest().json(
+                            json!({ "success": false, "message": format!("请求格式错误: {}", err) })
+                        ),
+                    };
+                    error::InternalError::from_response(err, response).into()
+                });
+            let query_config = web::QueryConfig::default().error_handler(|err, _req| {
+                let response = HttpResponse::BadRequest().json(
+                    json!({ "success": false, "message": format!("查询参数错误: {}", err) })
+                );
+                error::InternalError::from_response(err, response).into()
+            });
 
             App::new()
                 .app_data(web::Data::new(blockchain.clone()))
+                .app_data(web::Data::new(mempool.clone()))
+                .app_data(auth.clone())
+                .app_data(limits.clone())
+                .app_data(metrics.clone())
+                .app_data(p2p.clone())
+                .app_data(health.clone())
+                .app_data(store.clone())
+                .app_data(snapshot_status.clone())
+                .app_data(notary.clone())
+                .app_data(json_config)
+                .app_data(query_config)
                 .wrap(cors)
+                .wrap_fn({
+                    // 记录每个请求的耗时
+                    let metrics = metrics.clone();
+                    move |req, srv| {
+                        let start = Instant::now();
+                        let method = req.method().to_string();
+                        let metrics = metrics.clone();
+                        let span = info_span!(
+                            "http_request",
+                            method = %method,
+                            path = %req.path(),
+                            peer = ?req.peer_addr(),
+                        );
+                        let fut = span.in_scope(|| srv.call(req));
+                        async move {
+                            let res = fut.await?;
+                            let path = res.request().match_pattern().unwrap_or_else(|| "unmatched".to_string());
+                            let elapsed = start.elapsed();
+                            metrics.http_request(&method, &path, res.status().as_u16(), elapsed);
+                            info!(status = res.status().as_u16(), elapsed_ms = elapsed.as_millis() as u64, "request completed");
+                            Ok(res)
+                        }
+                        .instrument(span)
+                    }
+                })
+                .route("/health", web::get().to(Self::health))
+                .route("/ready", web::get().to(Self::ready))
+                .route("/metrics", web::get().to(Self::metrics))
                 .route("/scan", web::get().to(Self::scan))
                 .route("/data", web::get().to(Self::data))
+                .route("/blocks/{index}", web::get().to(Self::block))
+                .route("/balance/{address}", web::get().to(Self::balance))
+                .route("/assets/{asset}", web::get().to(Self::asset))
+                .route("/utxos/{address}", web::get().to(Self::utxos))
+                .route("/fees/estimate", web::get().to(Self::estimate_fee))
+                .route("/memos/{data}", web::get().to(Self::memos))
+                .route("/notarize/proof/{hash}", web::get().to(Self::notarize_proof))
+                .route("/transactions", web::post().to(Self::submit))
+                .route("/psbt", web::post().to(Self::psbt_create))
+                .route("/psbt/combine", web::post().to(Self::psbt_combine))
+                .route("/psbt/finalize", web::post().to(Self::psbt_finalize))
+                .route("/notarize", web::post().to(Self::notarize))
                 .route("/mine", web::post().to(Self::mine))
+                .route("/generate", web::post().to(Self::generate))
+                .route("/mocktime", web::post().to(Self::mock_time))
         })
             .bind(&self.address)?
             .run()
             .await
     }
 
-    async fn scan(data: web::Data<Arc<Mutex<Blockchain>>>) -> impl Responder {
+    // 存活检查：进程存活且存储可写，不需要鉴权以便编排系统探测
+    async fn health(config: web::Data<HealthConfig>) -> impl Responder {
+        health::report(vec![health::storage_writable(&config)])
+    }
+
+    // 就绪检查：区块链已加载、与最高节点同步且连接了足够的节点
+    async fn ready(
+        config: web::Data<HealthConfig>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        p2p: web::Data<Option<Arc<P2PNode>>>,
+        snapshot_status: web::Data<Option<SharedStatus>>,
+    ) -> impl Responder {
+        let (chain_check, height) = {
+            let blockchain = data.lock().unwrap();
+            (health::chain_loaded(&blockchain), blockchain.height())
+        };
+        let node = p2p.as_ref().as_ref();
+        let peers = node.map_or(0, |node| node.peer_count());
+        let peer_heights = node.map_or_else(Vec::new, |node| node.peer_heights());
+
+        let mut checks = vec![
+            chain_check,
+            health::synced(&config, height, peers, &peer_heights),
+            health::peers_connected(&config, peers),
+        ];
+        if let Some(status) = snapshot_status.as_ref() {
+            checks.push(health::snapshot_verified(&status.lock().unwrap()));
+        }
+        health::report(checks)
+    }
+
+    // 导出 Prometheus 指标
+    async fn metrics(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        mempool: web::Data<Arc<Mutex<Mempool>>>,
+        metrics: web::Data<Metrics>,
+        p2p: web::Data<Option<Arc<P2PNode>>>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
+
+        let mut snapshot = ChainSnapshot::default();
+        {
+            let blockchain = data.lock().unwrap();
+            snapshot.height = blockchain.height();
+            snapshot.utxo_count = blockchain.utxo_count();
+            if let Some(tip) = blockchain.tip() {
+                snapshot.tip_timestamp = tip.timestamp;
+                snapshot.difficulty = tip.difficulty;
+            }
+        }
+        {
+            let mempool = mempool.lock().unwrap();
+            snapshot.mempool_transactions = mempool.len();
+            snapshot.mempool_bytes = mempool.transactions().map(|t| t.bytes().len()).sum();
+        }
+        snapshot.peers = p2p.as_ref().as_ref().map_or(0, |node| node.peer_count());
+
+        HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, "text/plain; version=0.0.4"))
+            .body(metrics.render(&snapshot))
+    }
+
+    async fn scan(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
         let blockchain = data.lock().unwrap();
         let response = json!({
             "success": true,
             "message": "scan",
-            "blocks": blockchain.blocks.len(),
+            "blocks": blockchain.height(),
         });
         HttpResponse::Ok().json(response)
     }
 
-    async fn mine(
+    // 提交交易到交易池
+    async fn submit(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
         data: web::Data<Arc<Mutex<Blockchain>>>,
-        transactions: web::Json<Vec<TransactionRequest>>, // 接收交易数据
+        mempool: web::Data<Arc<Mutex<Mempool>>>,
+        metrics: web::Data<Metrics>,
+        transactions: web::Json<Vec<TransactionRequest>>,
     ) -> impl Responder {
-        let mut blockchain = match data.lock() {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::Submitter) {
+            return resp;
+        }
+
+        let transactions = transactions.into_inner();
+        if let Err(resp) = limits.check_transactions(transactions.len()) {
+            return resp;
+        }
+        if transactions.is_empty() {
+            return HttpResponse::BadRequest().json(
+                json!({ "success": false, "message": "至少需要包含一个交易" })
+            );
+        }
+
+        let transactions: Vec<Transaction> = transactions.into_iter().map(Transaction::from).collect();
+        // 任一交易未通过检查时拒绝整个请求
+        for (index, transaction) in transactions.iter().enumerate() {
+            if let Err(e) = transaction.check() {
+                let reason = format!("{:?}", e);
+                metrics.transactions_rejected(&e, 1);
+                let body = json!({
+                    "success": false,
+                    "message": format!("交易 {} 无效: {}", index, reason),
+                    "index": index,
+                    "reason": reason,
+                });
+                return match e {
+                    TransactionError::TooLarge => HttpResponse::PayloadTooLarge().json(body),
+                    _ => HttpResponse::BadRequest().json(body),
+                };
+            }
+        }
+
+        // 交易池只接收可以打包进下一个区块的交易，时间锁未到期的交易由客户端稍后重新提交
+        let blockchain = data.lock().unwrap();
+
+        let mut mempool = match mempool.lock() {
             Ok(lock) => lock,
             Err(_) => return HttpResponse::InternalServerError().json(
-                json!({ "success": false, "message": "无法获取区块链锁" })
+                json!({ "success": false, "message": "无法获取交易池锁" })
             )
         };
 
-        // 转换交易格式
-        let transactions: Vec<Transaction> = transactions
-            .into_inner()
-            .into_iter()
-            .map(|t| Transaction {
-                inputs: t.inputs,
-                outputs: t.outputs,
-            })
-            .collect();
+        // 在交易池的副本上依次加入，任一交易被拒绝时交易池保持不变
+        let accepted = transactions.len();
+        let txids: Vec<String> = transactions.iter().map(|t| hex::encode(t.txid())).collect();
+        let mut staged = mempool.clone();
+        let mut replaced = vec![];
+        for (index, transaction) in transactions.into_iter().enumerate() {
+            match staged.add(transaction, &blockchain) {
+                Ok(evicted) => replaced.extend(evicted.iter().map(|t| hex::encode(t.txid()))),
+                Err(e) => {
+                    let reason = format!("{:?}", e);
+                    metrics.transactions_rejected(&e, 1);
+                    return HttpResponse::BadRequest().json(json!({
+                        "success": false,
+                        "message": format!("交易 {} 未被交易池接收: {}", index, reason),
+                        "index": index,
+                        "reason": reason,
+                    }));
+                }
+            }
+        }
+        *mempool = staged;
+        metrics.transactions_received(accepted);
+        if !replaced.is_empty() {
+            info!(count = replaced.len(), "交易已被替换");
+        }
 
-        // 验证至少有一个交易
-        if transactions.is_empty() {
+        HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "交易已提交",
+            "accepted": accepted,
+            "txids": txids,
+            "replaced": replaced,
+            "mempool_size": mempool.len(),
+        }))
+    }
+
+    // 由未签名交易创建部分签名交易，并从 UTXO 集合补充被花费输出的信息
+    async fn psbt_create(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        transaction: web::Json<TransactionRequest>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::Submitter) {
+            return resp;
+        }
+        let transaction = Transaction::from(transaction.into_inner());
+        if let Err(e) = transaction.check() {
+            return HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "交易无效",
+                "reason": format!("{:?}", e),
+            }));
+        }
+        let mut psbt = psbt::Psbt::new(transaction);
+        let missing = psbt.update(&data.lock().unwrap());
+        HttpResponse::Ok().json(json!({
+            "success": true,
+            "txid": hex::encode(psbt.transaction.txid()),
+            "missing_utxos": missing,
+            "psbt": psbt,
+        }))
+    }
+
+    // 合并多个签名方的部分签名交易
+    async fn psbt_combine(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        psbts: web::Json<Vec<psbt::Psbt>>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::Submitter) {
+            return resp;
+        }
+        let mut psbts = psbts.into_inner().into_iter();
+        let Some(mut combined) = psbts.next() else {
             return HttpResponse::BadRequest().json(
-                json!({ "success": false, "message": "至少需要包含一个交易" })
+                json!({ "success": false, "message": "至少需要包含一个部分签名交易" })
             );
+        };
+        for (index, other) in psbts.enumerate() {
+            if let Err(e) = combined.validate().and_then(|_| combined.combine(&other)) {
+                return HttpResponse::BadRequest().json(json!({
+                    "success": false,
+                    "message": format!("部分签名交易 {} 无法合并", index + 1),
+                    "reason": format!("{:?}", e),
+                }));
+            }
         }
+        HttpResponse::Ok().json(json!({ "success": true, "psbt": combined }))
+    }
 
-        let last_block = match blockchain.blocks.last() {
-            Some(b) => b,
-            None => return HttpResponse::InternalServerError().json(
-                json!({ "success": false, "message": "区块链尚未初始化" })
-            )
+    // 生成解锁数据并提取最终交易，提取的交易通过 `POST /transactions` 提交
+    async fn psbt_finalize(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        psbt: web::Json<psbt::Psbt>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::Submitter) {
+            return resp;
+        }
+        let mut psbt = psbt.into_inner();
+        match psbt.finalize().and_then(|_| psbt.extract()) {
+            Ok(transaction) => HttpResponse::Ok().json(json!({
+                "success": true,
+                "txid": hex::encode(transaction.txid()),
+                "transaction": transaction,
+                "psbt": psbt,
+            })),
+            Err(e) => HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "无法生成最终交易",
+                "reason": format!("{:?}", e),
+                "psbt": psbt,
+            })),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn mine(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        mempool: web::Data<Arc<Mutex<Mempool>>>,
+        metrics: web::Data<Metrics>,
+        store: web::Data<Option<Arc<Mutex<BlockStore>>>>,
+        notary: web::Data<Arc<Mutex<Notary>>>,
+        transactions: web::Json<Vec<TransactionRequest>>, // 接收交易数据
+    ) -> impl Responder {
+        let caller = match Self::admit(&req, &auth, &limits, Role::Miner) {
+            Ok(caller) => caller,
+            Err(resp) => return resp,
         };
+        if let Err(resp) = limits.check_transactions(transactions.len()) {
+            return resp;
+        }
+
+        // 在锁内构造区块，工作量证明在锁外进行，避免挖矿期间阻塞其他请求
+        let (mut new_block, pending, batch) = {
+            let blockchain = match data.lock() {
+                Ok(lock) => lock,
+                Err(_) => return HttpResponse::InternalServerError().json(
+                    json!({ "success": false, "message": "无法获取区块链锁" })
+                )
+            };
+            let mut mempool = match mempool.lock() {
+                Ok(lock) => lock,
+                Err(_) => return HttpResponse::InternalServerError().json(
+                    json!({ "success": false, "message": "无法获取交易池锁" })
+                )
+            };
+
+            let last_block = match blockchain.tip() {
+                Some(b) => b,
+                None => return HttpResponse::InternalServerError().json(
+                    json!({ "success": false, "message": "区块链尚未初始化" })
+                )
+            };
+
+            // 转换交易格式，请求中的交易在前，随后在区块限制内打包交易池中的交易，其余交易留在交易池
+            let transactions: Vec<Transaction> = transactions
+                .into_inner()
+                .into_iter()
+                .map(Transaction::from)
+                .collect();
+            let request_count = transactions.len();
+            let mut new_block = Block::new(
+                last_block.index + 1,
+                blockchain.next_timestamp(),
+                last_block.hash.clone(),
+                transactions, // 使用传入的交易
+                last_block.difficulty,
+            );
+
+            // 请求中的 Coinbase 交易没有数据输出时附加待打包的存证批次，在打包交易池之前附加以计入区块大小
+            let mut notary = notary.lock().unwrap();
+            let batch = match new_block.transactions.first_mut() {
+                Some(coinbase)
+                    if coinbase.is_coinbase() && coinbase.outputs.iter().all(|o| o.carried_data().is_none()) =>
+                {
+                    notary.take_batch().inspect(|documents| {
+                        coinbase.outputs.push(notary::commitment_output(documents));
+                    })
+                }
+                _ => None,
+            };
+
+            // 挖矿之前验证请求中的交易，包括输入、解锁脚本与 Coinbase 交易
+            if let Err(e) = blockchain.check_block_transactions(&new_block) {
+                notary.restore_batch(batch.unwrap_or_default());
+                metrics.transactions_rejected(&e, 1);
+                return HttpResponse::BadRequest().json(json!({
+                    "success": false,
+                    "message": "交易无效",
+                    "reason": format!("{:?}", e),
+                }));
+            }
 
-        let mut new_block = Block::new(
-            last_block.index + 1,
-            now(),
-            last_block.hash.clone(),
-            transactions, // 使用传入的交易
-            last_block.difficulty,
-        );
+            let remaining = fill_block(&mut new_block, blockchain.package_order(mempool.take_all()));
+            mempool.restore(remaining, &blockchain);
+            let pending = new_block.transactions[request_count..].to_vec();
+
+            // 验证至少有一个交易
+            if new_block.transactions.is_empty() {
+                return HttpResponse::BadRequest().json(
+                    json!({ "success": false, "message": "至少需要包含一个交易" })
+                );
+            }
+            (new_block, pending, batch)
+        };
 
         // 执行挖矿
+        let mining_start = Instant::now();
         let mining_result = new_block.mine();
-        if !mining_result.is_ok() {
+        metrics.mining_finished(new_block.nonce.saturating_add(1), mining_start.elapsed());
+
+        let mut blockchain = match data.lock() {
+            Ok(lock) => lock,
+            Err(_) => return HttpResponse::InternalServerError().json(
+                json!({ "success": false, "message": "无法获取区块链锁" })
+            )
+        };
+        let mut mempool = match mempool.lock() {
+            Ok(lock) => lock,
+            Err(_) => return HttpResponse::InternalServerError().json(
+                json!({ "success": false, "message": "无法获取交易池锁" })
+            )
+        };
+        let mut notary = notary.lock().unwrap();
+        if mining_result.is_err() {
+            mempool.restore(pending, &blockchain);
+            notary.restore_batch(batch.unwrap_or_default());
             return HttpResponse::InternalServerError().json(
                 json!({ "success": false, "message": "挖矿失败" })
             );
         }
 
-        // 添加新区块
-        match blockchain.update_with_block(new_block) {
-            Ok(_) => HttpResponse::Ok().json(
-                json!({ "success": true, "message": "新区块已挖出" })
-            ),
-            Err(e) => HttpResponse::InternalServerError().json(
-                json!({ "success": false, "message": format!("添加区块失败: {:?}", e) })
-            )
+        // 挖矿期间链顶已变化时区块作废，交易与存证批次放回
+        if blockchain.tip().map(|tip| &tip.hash) != Some(&new_block.prev_block_hash) {
+            mempool.restore(pending, &blockchain);
+            notary.restore_batch(batch.unwrap_or_default());
+            return HttpResponse::Conflict().json(
+                json!({ "success": false, "message": "挖矿期间链顶已变化，请重试" })
+            );
+        }
+
+        // 添加新区块，失败时将交易池中的交易放回
+        metrics.block_received();
+        match blockchain.update_with_block(new_block.clone()) {
+            Ok(_) => {
+                // 挖矿期间提交的交易可能与新区块冲突，重新检查交易池
+                let queued = mempool.take_all();
+                mempool.restore(queued, &blockchain);
+                // 持久化新区块；区块已在内存中的链上，持久化失败时也记录存证批次，避免之后的区块重复打包
+                let stored = store.as_ref().as_ref().map_or(Ok(()), |store| store.lock().unwrap().append(&new_block));
+                if let Some(documents) = batch
+                    && let Err(e) = notary.commit(&new_block, documents)
+                {
+                    error!(error = %e, "存证批次记录失败");
+                }
+                if let Err(e) = stored {
+                    error!(error = %e, "区块持久化失败");
+                    return HttpResponse::InternalServerError().json(
+                        json!({ "success": false, "message": format!("区块已接受但持久化失败: {}", e) })
+                    );
+                }
+                info!(
+                    index = blockchain.height() - 1,
+                    caller = caller.as_ref().map(|c| c.name.as_str()),
+                    "新区块已挖出"
+                );
+                HttpResponse::Ok().json(
+                    json!({ "success": true, "message": "新区块已挖出" })
+                )
+            }
+            Err(e) => {
+                let reason = format!("{:?}", e);
+                warn!(reason = %reason, "区块被拒绝");
+                metrics.block_rejected(&e);
+                // 验证在第一个无效交易处停止，只计入该交易
+                if e.is_transaction_error() {
+                    metrics.transactions_rejected(&e, 1);
+                }
+                mempool.restore(pending, &blockchain);
+                notary.restore_batch(batch.unwrap_or_default());
+                HttpResponse::InternalServerError().json(
+                    json!({ "success": false, "message": format!("添加区块失败: {:?}", e) })
+                )
+            }
+        }
+    }
+
+    // 回归测试网：立即挖出若干区块，交易池中的交易打包进第一个区块
+    #[allow(clippy::too_many_arguments)]
+    async fn generate(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        mempool: web::Data<Arc<Mutex<Mempool>>>,
+        metrics: web::Data<Metrics>,
+        store: web::Data<Option<Arc<Mutex<BlockStore>>>>,
+        notary: web::Data<Arc<Mutex<Notary>>>,
+        request: web::Json<GenerateRequest>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::Miner) {
+            return resp;
+        }
+        let request = request.into_inner();
+        if request.blocks == 0 || request.blocks > MAX_GENERATE_BLOCKS {
+            return HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": format!("区块数量必须在 1 到 {} 之间", MAX_GENERATE_BLOCKS),
+            }));
+        }
+
+        // 回归测试网难度最低，工作量证明可以在锁内进行
+        let mut blockchain = data.lock().unwrap();
+        if !blockchain.params().is_regtest() {
+            return HttpResponse::BadRequest().json(
+                json!({ "success": false, "message": "仅回归测试网可以生成区块" })
+            );
+        }
+        let mut mempool = mempool.lock().unwrap();
+        let start_height = blockchain.height();
+        let mut remaining = mempool.take_all();
+
+        // 待打包的存证批次写入第一个区块的 Coinbase 交易
+        let mut notary = notary.lock().unwrap();
+        let batch = notary.take_batch();
+        let commitment = batch.as_deref().map(notary::commitment_output);
+
+        let result = blockchain.generate_with_output(request.blocks, &request.address, &mut remaining, commitment.clone());
+        let generated: Vec<Block> = (start_height..blockchain.height())
+            .filter_map(|index| blockchain.block(index).cloned())
+            .collect();
+        for _ in &generated {
+            metrics.block_received();
+        }
+
+        // 持久化已添加的区块，生成中途失败时也保留已添加的部分
+        let stored = store.as_ref().as_ref().map_or(Ok(()), |store| {
+            let mut store = store.lock().unwrap();
+            generated.iter().try_for_each(|block| store.append(block))
+        });
+
+        // 记录存证批次，承诺所在的区块未被添加时批次留待之后的区块；
+        // 区块已在内存中的链上，持久化失败时也照常记录，避免之后的区块重复打包
+        if let (Some(documents), Some(commitment)) = (batch, commitment) {
+            match generated.iter().find(|block| block.transactions[0].outputs.contains(&commitment)) {
+                Some(block) => {
+                    if let Err(e) = notary.commit(block, documents) {
+                        error!(error = %e, "存证批次记录失败");
+                    }
+                }
+                None => notary.restore_batch(documents),
+            }
+        }
+        if let Err(e) = &stored {
+            error!(error = %e, "区块持久化失败");
         }
+
+        let blocks = match result {
+            Ok(blocks) => {
+                let dropped = mempool.restore(remaining, &blockchain);
+                if dropped > 0 {
+                    info!(dropped, "已失效的交易已移出交易池");
+                }
+                blocks
+            }
+            Err(e) => {
+                let reason = format!("{:?}", e);
+                warn!(reason = %reason, "区块被拒绝");
+                metrics.block_received();
+                metrics.block_rejected(&e);
+                // 未被打包的交易放回交易池
+                mempool.restore(remaining, &blockchain);
+                return HttpResponse::InternalServerError().json(
+                    json!({ "success": false, "message": format!("生成区块失败: {:?}", e) })
+                );
+            }
+        };
+        if let Err(e) = stored {
+            return HttpResponse::InternalServerError().json(
+                json!({ "success": false, "message": format!("区块已接受但持久化失败: {}", e) })
+            );
+        }
+
+        info!(count = blocks.len(), height = blockchain.height(), "回归测试网区块已生成");
+        let hashes: Vec<String> = blocks.iter().map(|b| hex::encode(&b.hash)).collect();
+        HttpResponse::Ok().json(json!({
+            "success": true,
+            "height": blockchain.height(),
+            "hashes": hashes,
+        }))
+    }
+
+    // 回归测试网：设置或清除模拟时间
+    async fn mock_time(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        request: web::Json<MockTimeRequest>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::Miner) {
+            return resp;
+        }
+        if !data.lock().unwrap().params().is_regtest() {
+            return HttpResponse::BadRequest().json(
+                json!({ "success": false, "message": "仅回归测试网可以设置模拟时间" })
+            );
+        }
+
+        set_mock_time(request.timestamp);
+        info!(timestamp = ?request.timestamp, "模拟时间已更新");
+        HttpResponse::Ok().json(json!({ "success": true, "timestamp": request.timestamp, "now": now().to_string() }))
     }
 
     // async fn mine(data: web::Data<Arc<Mutex<Blockchain>>>) -> impl Responder {
     //     let mut blockchain = data.lock().unwrap();
-    //     let last_block = blockchain.blocks.last().unwrap();
+    //     let last_block = blockchain.tip().unwrap();
     //     let difficulty = last_block.difficulty;
     //
     //     let mut new_block = Block::new(
@@ -137,7 +870,15 @@ impl MyServer {
     // }
 
 
-    async fn data(data: web::Data<Arc<Mutex<Blockchain>>>) -> impl Responder {
+    async fn data(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
         let blockchain = match data.lock() {
             Ok(lock) => lock,
             Err(_) => return HttpResponse::InternalServerError().json(
@@ -153,12 +894,291 @@ impl MyServer {
 
         HttpResponse::Ok().json(json!({
             "success": true,
-            "block_count": blockchain.blocks.len(),
+            "block_count": blockchain.height(),
             "total_transactions": all_transactions.len(),
             "transactions": all_transactions
         }))
     }
 
+    // 按索引获取区块的完整 JSON，可直接导入其他节点或工具
+    async fn block(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        index: web::Path<usize>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
+        let blockchain = data.lock().unwrap();
+        match blockchain.block(index.into_inner()) {
+            Some(block) => HttpResponse::Ok().json(block),
+            None => HttpResponse::NotFound().json(
+                json!({ "success": false, "message": "区块不存在" })
+            ),
+        }
+    }
+
+    // 查询地址余额，未成熟的 Coinbase 奖励单独列出
+    async fn balance(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        address: web::Path<String>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
+        let blockchain = data.lock().unwrap();
+        let balance = blockchain.balance(&address);
+        let assets: Vec<_> = blockchain
+            .asset_balances(&address)
+            .into_iter()
+            .map(|(asset, value)| json!({
+                "asset": hex::encode(&asset),
+                "name": blockchain.asset(&asset).map(|info| info.name.as_str()),
+                "value": value,
+            }))
+            .collect();
+        HttpResponse::Ok().json(json!({
+            "success": true,
+            "address": address.into_inner(),
+            "height": blockchain.height(),
+            "spendable": balance.spendable,
+            "immature": balance.immature,
+            "assets": assets,
+        }))
+    }
+
+    // 查询已发行资产的名称、发行方与累计发行数量
+    async fn asset(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        asset: web::Path<String>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
+        let Ok(asset) = hex::decode(asset.as_str()) else {
+            return HttpResponse::BadRequest().json(
+                json!({ "success": false, "message": "资产 ID 不是有效的十六进制" })
+            );
+        };
+        let blockchain = data.lock().unwrap();
+        match blockchain.asset(&asset) {
+            Some(info) => HttpResponse::Ok().json(json!({
+                "success": true,
+                "asset": hex::encode(&asset),
+                "name": info.name,
+                "issuer": info.issuer,
+                "issuer_address": info.issuer.address(),
+                "issued": info.issued,
+                "height": info.height,
+            })),
+            None => HttpResponse::NotFound().json(
+                json!({ "success": false, "message": "未找到该资产" })
+            ),
+        }
+    }
+
+    // 列出地址的未花费输出，交易输入按其中的 txid 与 index 引用
+    async fn utxos(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        address: web::Path<String>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
+        let blockchain = data.lock().unwrap();
+        let height = blockchain.height();
+        let maturity = blockchain.params().coinbase_maturity;
+        let utxos: Vec<_> = blockchain
+            .utxos_for(&address)
+            .into_iter()
+            .map(|entry| json!({
+                "txid": hex::encode(&entry.outpoint.txid),
+                "index": entry.outpoint.index,
+                "value": entry.output.value,
+                "asset": entry.output.asset.as_ref().map(hex::encode),
+                "lock": entry.output.lock,
+                "height": entry.height,
+                "spendable": entry.is_mature(height, maturity),
+            }))
+            .collect();
+        HttpResponse::Ok().json(json!({
+            "success": true,
+            "address": address.into_inner(),
+            "height": height,
+            "utxos": utxos,
+        }))
+    }
+
+    // 根据最近区块中已确认交易的费率估算手续费率（每 1000 字节）
+    async fn estimate_fee(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        query: web::Query<FeeEstimateQuery>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
+        let target = query.target_blocks;
+        if target == 0 || target > fees::MAX_CONFIRMATION_TARGET {
+            return HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": format!("target_blocks 必须在 1 到 {} 之间", fees::MAX_CONFIRMATION_TARGET),
+            }));
+        }
+        let blockchain = data.lock().unwrap();
+        let estimator = blockchain.fee_estimator();
+        match estimator.estimate(target) {
+            Some(fee_rate) => HttpResponse::Ok().json(json!({
+                "success": true,
+                "target_blocks": target,
+                "fee_rate": fee_rate,
+                "median_fee_rate": estimator.median_fee_rate(target),
+                "blocks": estimator.len(),
+            })),
+            None => HttpResponse::ServiceUnavailable().json(json!({
+                "success": false,
+                "message": format!("最近记录的区块不足 {} 个，无法估算", target),
+                "blocks": estimator.len(),
+            })),
+        }
+    }
+
+    // 按附带的数据查找已确认的数据输出
+    async fn memos(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        data: web::Data<Arc<Mutex<Blockchain>>>,
+        memo: web::Path<String>,
+        query: web::Query<MemoQuery>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
+        let memo = match query.format {
+            MemoFormat::Hex => match hex::decode(memo.as_str()) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return HttpResponse::BadRequest().json(json!({
+                        "success": false,
+                        "message": "数据不是有效的十六进制",
+                        "reason": format!("{:?}", e),
+                    }));
+                }
+            },
+            MemoFormat::Text => memo.into_inner().into_bytes(),
+        };
+        if memo.len() > MAX_DATA_SIZE {
+            return HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": format!("数据不能超过 {} 字节", MAX_DATA_SIZE),
+            }));
+        }
+        let blockchain = data.lock().unwrap();
+        HttpResponse::Ok().json(json!({
+            "success": true,
+            "data": hex::encode(&memo),
+            "records": blockchain.data_records(&memo),
+        }))
+    }
+
+    // 提交文档哈希，由节点挖出的下一个区块打包
+    async fn notarize(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        notary: web::Data<Arc<Mutex<Notary>>>,
+        request: web::Json<NotarizeRequest>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::Submitter) {
+            return resp;
+        }
+        let hashes = request.into_inner().hashes;
+        if hashes.is_empty() {
+            return HttpResponse::BadRequest().json(
+                json!({ "success": false, "message": "至少需要包含一个文档哈希" })
+            );
+        }
+
+        let mut notary = notary.lock().unwrap();
+        let accepted = match notary.submit(hashes) {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                let message = match e {
+                    NotaryError::InvalidDocumentHash => format!("文档哈希必须为 {} 字节", DOCUMENT_HASH_SIZE),
+                    _ => "待打包的文档哈希过多".to_string(),
+                };
+                return HttpResponse::BadRequest().json(json!({
+                    "success": false,
+                    "message": message,
+                    "reason": format!("{:?}", e),
+                }));
+            }
+        };
+        if let Err(e) = notary.save_pending() {
+            error!(error = %e, "待打包的文档哈希保存失败");
+        }
+        HttpResponse::Ok().json(json!({
+            "success": true,
+            "accepted": accepted,
+            "pending": notary.pending_len(),
+        }))
+    }
+
+    // 获取文档哈希的存证证明，可以用 `notary::verify` 离线验证
+    async fn notarize_proof(
+        req: HttpRequest,
+        auth: web::Data<Authenticator>,
+        limits: web::Data<RequestLimits>,
+        notary: web::Data<Arc<Mutex<Notary>>>,
+        hash: web::Path<String>,
+    ) -> impl Responder {
+        if let Err(resp) = Self::admit(&req, &auth, &limits, Role::ReadOnly) {
+            return resp;
+        }
+        let document = match hex::decode(hash.as_str()) {
+            Ok(document) if document.len() == DOCUMENT_HASH_SIZE => document,
+            _ => {
+                return HttpResponse::BadRequest().json(json!({
+                    "success": false,
+                    "message": format!("文档哈希必须为 {} 字节的十六进制", DOCUMENT_HASH_SIZE),
+                }));
+            }
+        };
+
+        let notary = notary.lock().unwrap();
+        match notary.proof(&document) {
+            Some(proof) => HttpResponse::Ok().json(json!({
+                "success": true,
+                "attestation": notary::verify(&proof).ok(),
+                "proof": proof,
+            })),
+            None if notary.is_pending(&document) => HttpResponse::Accepted().json(json!({
+                "success": false,
+                "pending": true,
+                "message": "文档哈希等待打包",
+            })),
+            None => HttpResponse::NotFound().json(json!({
+                "success": false,
+                "pending": false,
+                "message": "未找到文档哈希的存证记录",
+            })),
+        }
+    }
+
     // async fn data(data: web::Data<Arc<Mutex<Blockchain>>>) -> impl Responder {
     //     let blockchain = data.lock().unwrap();
     //     HttpResponse::Ok().json(json!({
@@ -167,3 +1187,167 @@ impl MyServer {
     //     }))
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::auth::{ApiKey, AuthConfig};
+    use super::super::rate_limit::RateLimiter;
+    use actix_web::test::TestRequest;
+    use blockchainlib::blockchain::MAX_BLOCK_SIZE;
+    use blockchainlib::transaction::{Input, Output};
+
+    fn spend(block: &Block, receiver: &str, value: u64) -> Transaction {
+        Transaction {
+            inputs: vec![Input::from(block.transactions[0].outpoints().next().unwrap().0)],
+            outputs: vec![Output {
+                receiver: receiver.to_owned(),
+                value,
+                asset: None,
+                lock: None,
+            }],
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        }
+    }
+
+    fn miner_auth() -> web::Data<Authenticator> {
+        web::Data::new(Authenticator::new(AuthConfig {
+            keys: vec![ApiKey {
+                key: "miner-key".to_owned(),
+                role: Role::Miner,
+                name: String::new(),
+            }],
+            ..AuthConfig::default()
+        }))
+    }
+
+    fn limits() -> web::Data<RequestLimits> {
+        web::Data::new(RequestLimits {
+            per_ip: RateLimiter::new(10, 1.0),
+            per_key: RateLimiter::new(10, 1.0),
+            max_payload_bytes: 1 << 20,
+            max_transactions: 100,
+        })
+    }
+
+    fn miner_request() -> HttpRequest {
+        TestRequest::default().insert_header(("X-API-Key", "miner-key")).to_http_request()
+    }
+
+    #[actix_web::test]
+    async fn test_mine_reserves_notary_commitment() {
+        let mut params = ChainParams::regtest();
+        params.coinbase_maturity = 1;
+        let mut blockchain = Blockchain::new(params);
+        let funding = blockchain.generate(12, "miner", &mut vec![]).unwrap();
+        let tip = blockchain.tip().unwrap().clone();
+
+        // 请求中的 Coinbase 交易，以及交易池中恰好装满区块剩余空间的交易
+        let request = json!([{
+            "inputs": [],
+            "outputs": [{ "receiver": "miner", "value": 50 }],
+            "coinbase": { "height": tip.index + 1 },
+        }]);
+        let requests: Vec<TransactionRequest> = serde_json::from_value(request.clone()).unwrap();
+        let coinbase = Transaction::from(requests.into_iter().next().unwrap());
+        let base = Block::new(tip.index + 1, 0, tip.hash.clone(), vec![coinbase], tip.difficulty).size();
+        let overhead = spend(&funding[0], "", 49).size();
+        let mut budget = MAX_BLOCK_SIZE - base;
+        let mut mempool = Mempool::new();
+        for (i, block) in funding[..11].iter().enumerate() {
+            let size = if i == 10 { budget } else { (MAX_BLOCK_SIZE - base) / 11 };
+            budget -= size;
+            let transaction = spend(block, &"x".repeat(size - overhead), 49);
+            assert_eq!(transaction.size(), size);
+            mempool.add(transaction, &blockchain).unwrap();
+        }
+        let mut notary = Notary::new();
+        notary.submit(vec![vec![7; DOCUMENT_HASH_SIZE]]).unwrap();
+
+        let blockchain = Arc::new(Mutex::new(blockchain));
+        let mempool = Arc::new(Mutex::new(mempool));
+        let notary = Arc::new(Mutex::new(notary));
+        let req = miner_request();
+        let response = MyServer::mine(
+            req.clone(),
+            miner_auth(),
+            limits(),
+            web::Data::new(blockchain.clone()),
+            web::Data::new(mempool.clone()),
+            web::Data::new(Metrics::new()),
+            web::Data::new(None),
+            web::Data::new(notary.clone()),
+            web::Json(serde_json::from_value(request).unwrap()),
+        )
+        .await
+        .respond_to(&req);
+        assert_eq!(response.status(), 200);
+
+        // 承诺计入区块大小，装不下的交易留在交易池
+        let blockchain = blockchain.lock().unwrap();
+        let mined = blockchain.tip().unwrap();
+        assert_eq!(mined.index, tip.index + 1);
+        assert!(mined.size() <= MAX_BLOCK_SIZE);
+        assert_eq!(mined.transactions[0].outputs.len(), 2);
+        assert_eq!(mempool.lock().unwrap().len(), 1);
+        assert!(notary.lock().unwrap().proof(&[7; DOCUMENT_HASH_SIZE]).is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_generate_counts_each_block() {
+        let mut blockchain = Blockchain::new(ChainParams::regtest());
+        blockchain.generate(0, "miner", &mut vec![]).unwrap();
+        let metrics = web::Data::new(Metrics::new());
+        let req = miner_request();
+        let response = MyServer::generate(
+            req.clone(),
+            miner_auth(),
+            limits(),
+            web::Data::new(Arc::new(Mutex::new(blockchain))),
+            web::Data::new(Arc::new(Mutex::new(Mempool::new()))),
+            metrics.clone(),
+            web::Data::new(None),
+            web::Data::new(Arc::new(Mutex::new(Notary::new()))),
+            web::Json(serde_json::from_value(json!({ "blocks": 3 })).unwrap()),
+        )
+        .await
+        .respond_to(&req);
+        assert_eq!(response.status(), 200);
+        let out = metrics.render(&ChainSnapshot::default());
+        assert!(out.contains("blockchain_blocks_received_total 3\n"));
+    }
+
+    #[actix_web::test]
+    async fn test_mine_rejects_invalid_transactions() {
+        let mut blockchain = Blockchain::new(ChainParams::regtest());
+        let funding = blockchain.generate(1, "miner", &mut vec![]).unwrap();
+        let tip = blockchain.tip().unwrap().clone();
+        let blockchain = Arc::new(Mutex::new(blockchain));
+
+        // 花费尚未成熟的 Coinbase 输出
+        let mut transaction = serde_json::to_value(spend(&funding[0], "Alice", 10)).unwrap();
+        transaction.as_object_mut().unwrap().remove("txid");
+        let request = json!([
+            { "inputs": [], "outputs": [{ "receiver": "miner", "value": 50 }], "coinbase": { "height": tip.index + 1 } },
+            transaction,
+        ]);
+        let req = miner_request();
+        let response = MyServer::mine(
+            req.clone(),
+            miner_auth(),
+            limits(),
+            web::Data::new(blockchain.clone()),
+            web::Data::new(Arc::new(Mutex::new(Mempool::new()))),
+            web::Data::new(Metrics::new()),
+            web::Data::new(None),
+            web::Data::new(Arc::new(Mutex::new(Notary::new()))),
+            web::Json(serde_json::from_value(request).unwrap()),
+        )
+        .await
+        .respond_to(&req);
+        assert_eq!(response.status(), 400);
+        assert_eq!(blockchain.lock().unwrap().tip().unwrap().hash, tip.hash);
+    }
+}
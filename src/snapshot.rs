@@ -0,0 +1,228 @@
+use super::*;
+use crate::blockchain::AssetInfo;
+use crate::encoding::{self, Decode, DecodeError, Decoder, Encode, Encoder};
+use crate::transaction::OutPoint;
+use crate::utxo::UtxoEntry;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+// 快照附带的最近区块数，用于继续验证后续区块
+pub const SNAPSHOT_RECENT_BLOCKS: usize = 11;
+
+// 数据目录中快照文件的名称
+pub const SNAPSHOT_FILE_NAME: &str = "utxo_snapshot.dat";
+
+// 定义快照错误
+#[derive(Debug, PartialEq)]
+pub enum SnapshotError {
+    CommitmentMismatch,
+    InvalidRecentBlocks,
+}
+
+// 计算 UTXO 集合的承诺：对高度、链顶区块哈希、按输出位置排序的 UTXO 条目与按资产 ID 排序的发行记录做规范编码后取哈希
+pub fn utxo_commitment<'a>(
+    height: u32,
+    tip_hash: &[u8],
+    utxos: impl Iterator<Item = &'a UtxoEntry>,
+    assets: impl Iterator<Item = (&'a Hash, &'a AssetInfo)>,
+) -> Hash {
+    let sorted: BTreeMap<&OutPoint, &UtxoEntry> = utxos.map(|entry| (&entry.outpoint, entry)).collect();
+    let assets: BTreeMap<&Hash, &AssetInfo> = assets.collect();
+
+    let mut enc = Encoder::new();
+    enc.put_u8(encoding::ENCODING_VERSION);
+    enc.put_u32(height);
+    enc.put_bytes(tip_hash);
+    // 与 `put_seq` 的布局一致，避免复制整个集合
+    enc.put_u32(sorted.len() as u32);
+    for entry in sorted.values() {
+        entry.encode(&mut enc);
+    }
+    enc.put_u32(assets.len() as u32);
+    for (asset, info) in assets {
+        enc.put_bytes(asset);
+        info.encode(&mut enc);
+    }
+    crypto_hash::digest(crypto_hash::Algorithm::SHA256, &enc.into_bytes())
+}
+
+// 定义快照中的资产发行记录
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetRecord {
+    pub asset: Hash,
+    pub info: AssetInfo,
+}
+
+impl Encode for AssetRecord {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_bytes(&self.asset);
+        self.info.encode(enc);
+    }
+}
+
+impl Decode for AssetRecord {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(AssetRecord {
+            asset: dec.get_bytes()?,
+            info: AssetInfo::decode(dec)?,
+        })
+    }
+}
+
+// 定义 UTXO 快照
+#[derive(Clone, Debug, PartialEq)]
+pub struct UtxoSnapshot {
+    // 快照包含的区块数量
+    pub height: u32,
+    // 链顶及之前的若干区块
+    pub recent_blocks: Vec<Block>,
+    // 按输出位置排序的未花费输出
+    pub utxos: Vec<UtxoEntry>,
+    // 按资产 ID 排序的发行记录
+    pub assets: Vec<AssetRecord>,
+    pub commitment: Hash,
+}
+
+impl UtxoSnapshot {
+    pub fn new(height: u32, recent_blocks: Vec<Block>, mut utxos: Vec<UtxoEntry>, mut assets: Vec<AssetRecord>) -> Self {
+        utxos.sort_by(|a, b| a.outpoint.cmp(&b.outpoint));
+        assets.sort_by(|a, b| a.asset.cmp(&b.asset));
+        let mut snapshot = UtxoSnapshot {
+            height,
+            recent_blocks,
+            utxos,
+            assets,
+            commitment: Hash::new(),
+        };
+        snapshot.commitment = snapshot.compute_commitment();
+        snapshot
+    }
+
+    // 根据快照内容重新计算承诺
+    pub fn compute_commitment(&self) -> Hash {
+        utxo_commitment(
+            self.height,
+            self.tip_hash(),
+            self.utxos.iter(),
+            self.assets.iter().map(|record| (&record.asset, &record.info)),
+        )
+    }
+
+    // 快照链顶区块的哈希
+    pub fn tip_hash(&self) -> &[u8] {
+        self.recent_blocks.last().map(|block| block.hash.as_slice()).unwrap_or_default()
+    }
+
+    // 写入快照文件
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, encoding::encode(self))?;
+        std::fs::rename(tmp, path)
+    }
+
+    // 读取快照文件
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(encoding::decode(&std::fs::read(path)?)?)
+    }
+}
+
+impl Encode for UtxoSnapshot {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_u32(self.height);
+        enc.put_seq(&self.recent_blocks);
+        enc.put_seq(&self.utxos);
+        enc.put_seq(&self.assets);
+        enc.put_bytes(&self.commitment);
+    }
+}
+
+impl Decode for UtxoSnapshot {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(UtxoSnapshot {
+            height: dec.get_u32()?,
+            recent_blocks: dec.get_seq()?,
+            utxos: dec.get_seq()?,
+            assets: dec.get_seq()?,
+            commitment: dec.get_bytes()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::regtest_chain;
+
+    #[test]
+    fn test_snapshot_round_trip_and_continue() {
+        let (full, blocks) = regtest_chain(14);
+        let snapshot = full.snapshot();
+        assert_eq!(snapshot.height, 15);
+        assert_eq!(snapshot.recent_blocks.len(), SNAPSHOT_RECENT_BLOCKS);
+
+        let decoded: UtxoSnapshot = encoding::decode(&encoding::encode(&snapshot)).unwrap();
+        assert_eq!(decoded, snapshot);
+
+        let mut restored = Blockchain::from_snapshot(decoded, ChainParams::regtest()).unwrap();
+        assert_eq!(restored.height(), 15);
+        assert_eq!(restored.utxo_commitment(), full.utxo_commitment());
+        assert!(restored.block(3).is_none());
+        assert_eq!(restored.block(14), blocks.last());
+
+        // 从快照继续验证新区块
+        restored.generate(1, "next", &mut vec![]).unwrap();
+        assert_eq!(restored.height(), 16);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_tampering() {
+        let (full, _) = regtest_chain(2);
+
+        let mut snapshot = full.snapshot();
+        snapshot.utxos.pop();
+        assert_eq!(
+            Blockchain::from_snapshot(snapshot, ChainParams::regtest()).err(),
+            Some(SnapshotError::CommitmentMismatch)
+        );
+
+        let mut snapshot = full.snapshot();
+        snapshot.recent_blocks.remove(1);
+        assert_eq!(
+            Blockchain::from_snapshot(snapshot, ChainParams::regtest()).err(),
+            Some(SnapshotError::InvalidRecentBlocks)
+        );
+
+        // 替换链顶区块后承诺不再匹配
+        let mut snapshot = full.snapshot();
+        let tip = snapshot.recent_blocks.last_mut().unwrap();
+        tip.transactions[0].outputs[0].receiver = "attacker".to_owned();
+        tip.mine().unwrap();
+        assert_eq!(
+            Blockchain::from_snapshot(snapshot, ChainParams::regtest()).err(),
+            Some(SnapshotError::CommitmentMismatch)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_checks_recent_blocks_against_params() {
+        let (full, _) = regtest_chain(14);
+
+        // 声明的哈希与区块头不一致，即使承诺重新计算也会被拒绝
+        let mut snapshot = full.snapshot();
+        snapshot.recent_blocks.last_mut().unwrap().hash = vec![0; 32];
+        let snapshot = UtxoSnapshot::new(snapshot.height, snapshot.recent_blocks, snapshot.utxos, snapshot.assets);
+        assert_eq!(
+            Blockchain::from_snapshot(snapshot, ChainParams::regtest()).err(),
+            Some(SnapshotError::InvalidRecentBlocks)
+        );
+
+        // 区块难度低于链参数的要求
+        let mut params = ChainParams::regtest();
+        params.difficulty = 0x00ffffffffffffffffffffffffffffff;
+        assert_eq!(
+            Blockchain::from_snapshot(full.snapshot(), params).err(),
+            Some(SnapshotError::InvalidRecentBlocks)
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::commands;
+use blockchainlib::{Blockchain, ChainParams, UtxoSnapshot};
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::{error, info, info_span};
+
+// 定义快照后台验证状态
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerificationStatus {
+    Pending { verified_height: usize },
+    Verified,
+    Failed(String),
+}
+
+pub type SharedStatus = Arc<Mutex<VerificationStatus>>;
+
+// 在后台重放历史区块，验证其 UTXO 集合与快照承诺一致
+pub fn spawn(snapshot: UtxoSnapshot, params: ChainParams, blocks_file: PathBuf) -> SharedStatus {
+    let status = Arc::new(Mutex::new(VerificationStatus::Pending { verified_height: 0 }));
+    let shared = status.clone();
+
+    thread::spawn(move || {
+        let _span = info_span!("snapshot_verify", height = snapshot.height).entered();
+        let result = verify(&snapshot, params, &blocks_file, &shared);
+        let mut status = shared.lock().unwrap();
+        *status = match result {
+            Ok(()) => {
+                info!("历史区块与快照承诺一致");
+                VerificationStatus::Verified
+            }
+            Err(e) => {
+                error!(error = %e, "快照验证失败");
+                VerificationStatus::Failed(e.to_string())
+            }
+        };
+    });
+
+    status
+}
+
+fn verify(snapshot: &UtxoSnapshot, params: ChainParams, blocks_file: &PathBuf, status: &SharedStatus) -> io::Result<()> {
+    let height = snapshot.height as usize;
+    let reader = BufReader::new(File::open(blocks_file)?);
+    let mut blockchain = Blockchain::new(params);
+
+    for block in commands::read_blocks(reader)? {
+        if blockchain.height() == height {
+            break;
+        }
+        let block = block?;
+        let index = block.index;
+        blockchain
+            .update_with_block(block)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("block {} is invalid: {:?}", index, e)))?;
+        *status.lock().unwrap() = VerificationStatus::Pending {
+            verified_height: blockchain.height(),
+        };
+    }
+
+    if blockchain.height() != height {
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "blocks file ends before the snapshot height"));
+    }
+    if blockchain.tip().map(|b| &b.hash) != snapshot.recent_blocks.last().map(|b| &b.hash) {
+        return Err(io::Error::new(ErrorKind::InvalidData, "snapshot tip does not match the historical chain"));
+    }
+    if blockchain.utxo_commitment() != snapshot.commitment {
+        return Err(io::Error::new(ErrorKind::InvalidData, "UTXO commitment mismatch"));
+    }
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use super::*;
+use crate::encoding;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// 区块文件头：魔数与格式版本
+pub const BLOCK_FILE_MAGIC: &[u8; 4] = b"JLBK";
+pub const BLOCK_FILE_VERSION: u8 = 1;
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e)
+}
+
+// 写入区块文件头
+pub fn write_header(writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(BLOCK_FILE_MAGIC)?;
+    writer.write_all(&[BLOCK_FILE_VERSION])
+}
+
+// 校验区块文件头
+pub fn read_header(reader: &mut impl Read) -> io::Result<()> {
+    let mut header = [0u8; 5];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != BLOCK_FILE_MAGIC {
+        return Err(invalid_data("not a block file"));
+    }
+    if header[4] != BLOCK_FILE_VERSION {
+        return Err(invalid_data(format!("unsupported block file version {}", header[4])));
+    }
+    Ok(())
+}
+
+// 写入一条区块记录：u32 长度前缀 + 规范编码
+pub fn write_block(writer: &mut impl Write, block: &Block) -> io::Result<()> {
+    let bytes = encoding::encode(block);
+    let len = u32::try_from(bytes.len()).map_err(invalid_data)?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&bytes)
+}
+
+// 读取一条原始记录，文件结束时返回 None
+fn read_record(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut bytes = vec![];
+    reader
+        .take(u32::from_le_bytes(len) as u64)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() != u32::from_le_bytes(len) as usize {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Some(bytes))
+}
+
+// 读取一条区块记录，文件结束时返回 None
+pub fn read_block(reader: &mut impl Read) -> io::Result<Option<Block>> {
+    match read_record(reader)? {
+        Some(bytes) => Ok(Some(encoding::decode(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+// 区块记录迭代器
+pub struct BlockReader<R> {
+    reader: R,
+}
+
+impl<R: Read> BlockReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        read_header(&mut reader)?;
+        Ok(BlockReader { reader })
+    }
+}
+
+impl<R: Read> Iterator for BlockReader<R> {
+    type Item = io::Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        read_block(&mut self.reader).transpose()
+    }
+}
+
+// 定义区块存储：数据目录下的只追加文件
+pub struct BlockStore {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl BlockStore {
+    pub const FILE_NAME: &'static str = "blocks.dat";
+
+    // 打开数据目录中的区块文件，不存在时创建；末尾写了一半的记录会被截断
+    pub fn open(data_dir: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(Self::FILE_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        if file.metadata()?.len() == 0 {
+            write_header(&mut file)?;
+        } else {
+            let valid_len = Self::valid_length(&path)?;
+            if valid_len < file.metadata()?.len() {
+                tracing::warn!(path = %path.display(), valid_len, "截断区块文件末尾不完整的记录");
+                file.set_len(valid_len)?;
+            }
+        }
+        file.seek(SeekFrom::End(0))?;
+
+        Ok(BlockStore {
+            path,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    // 计算文件中完整记录的总长度。只有末尾不完整的记录（写入中断）不计入；
+    // 长度完整却无法解码的记录（损坏或旧版本写入）返回错误，避免截断其后的全部区块
+    fn valid_length(path: &Path) -> io::Result<u64> {
+        let mut reader = BufReader::new(File::open(path)?);
+        read_header(&mut reader)?;
+        let mut valid = (BLOCK_FILE_MAGIC.len() + 1) as u64;
+        loop {
+            match read_record(&mut reader) {
+                Ok(Some(bytes)) => {
+                    encoding::decode::<Block>(&bytes).map_err(|e| {
+                        invalid_data(format!("{}: invalid block record at offset {}: {}", path.display(), valid, e))
+                    })?;
+                    valid += 4 + bytes.len() as u64;
+                }
+                Ok(None) => return Ok(valid),
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(valid),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // 重放全部区块，逐个验证后构建区块链
+    pub fn load(&self, params: ChainParams) -> io::Result<Blockchain> {
+        self.replay_onto(Blockchain::new(params))
+    }
+
+    // 在已有区块链（如从快照恢复的区块链）之上重放存储中的区块
+    pub fn replay_onto(&self, mut blockchain: Blockchain) -> io::Result<Blockchain> {
+        for block in self.blocks()? {
+            let block = block?;
+            let index = block.index;
+            blockchain
+                .update_with_block(block)
+                .map_err(|e| invalid_data(format!("block {} is invalid: {:?}", index, e)))?;
+        }
+        Ok(blockchain)
+    }
+
+    // 存储中是否没有任何区块
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.blocks()?.next().is_none())
+    }
+
+    // 读取全部区块
+    pub fn blocks(&self) -> io::Result<BlockReader<BufReader<File>>> {
+        BlockReader::new(BufReader::new(File::open(&self.path)?))
+    }
+
+    // 追加一个区块并落盘
+    pub fn append(&mut self, block: &Block) -> io::Result<()> {
+        write_block(&mut self.writer, block)?;
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::Output;
+
+    fn block(index: u32) -> Block {
+        let mut block = Block::new(
+            index,
+            index as u128,
+            vec![0; 32],
+            vec![Transaction::coinbase(
+                index,
+                vec![Output {
+                    receiver: "Alice".to_owned(),
+                    value: index as u64,
+                    asset: None,
+                    lock: None,
+                }],
+            )],
+            u128::MAX,
+        );
+        block.hash = block.hash();
+        block
+    }
+
+    #[test]
+    fn test_store_append_and_reopen() {
+        let dir = std::env::temp_dir().join(format!("jl-store-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        {
+            let mut store = BlockStore::open(&dir).unwrap();
+            store.append(&block(0)).unwrap();
+            store.append(&block(1)).unwrap();
+        }
+
+        // 模拟写入中断：追加半条记录
+        let path = dir.join(BlockStore::FILE_NAME);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2]).unwrap();
+        drop(file);
+
+        let mut store = BlockStore::open(&dir).unwrap();
+        store.append(&block(2)).unwrap();
+        let blocks: Vec<Block> = store.blocks().unwrap().collect::<io::Result<_>>().unwrap();
+        assert_eq!(blocks, vec![block(0), block(1), block(2)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_keeps_undecodable_records() {
+        let dir = std::env::temp_dir().join(format!("jl-store-corrupt-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        {
+            let mut store = BlockStore::open(&dir).unwrap();
+            for index in 0..3 {
+                store.append(&block(index)).unwrap();
+            }
+        }
+
+        // 中间一条记录长度完整但内容损坏：打开失败且文件保持不变
+        let path = dir.join(BlockStore::FILE_NAME);
+        let mut bytes = std::fs::read(&path).unwrap();
+        let second = BLOCK_FILE_MAGIC.len() + 1 + 4 + encoding::encode(&block(0)).len() + 4;
+        bytes[second] = encoding::ENCODING_VERSION + 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let err = BlockStore::open(&dir).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+        // 上一编码版本写入的区块文件同样不会被截断
+        let old = include_bytes!("../tests/fixtures/block-v1.bin");
+        let mut bytes = vec![];
+        write_header(&mut bytes).unwrap();
+        bytes.extend((old.len() as u32).to_le_bytes());
+        bytes.extend(old);
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(BlockStore::open(&dir).err().unwrap().kind(), ErrorKind::InvalidData);
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+// 测试共用的链与交易构造
+use crate::params::ChainParams;
+use crate::transaction::{Input, OutPoint, Output, Transaction};
+use crate::{Block, Blockchain};
+
+// 奖励在下一个区块即可花费的回归测试网，在创世区块之后挖出 `blocks` 个区块，奖励发往 `miner`
+pub(crate) fn regtest_chain(blocks: usize) -> (Blockchain, Vec<Block>) {
+    let mut params = ChainParams::regtest();
+    params.coinbase_maturity = 1;
+    let mut blockchain = Blockchain::new(params);
+    let generated = blockchain.generate(blocks, "miner", &mut vec![]).unwrap();
+    (blockchain, generated)
+}
+
+// 未加锁的原生币输出
+pub(crate) fn pay(receiver: &str, value: u64) -> Output {
+    Output {
+        receiver: receiver.to_owned(),
+        value,
+        asset: None,
+        lock: None,
+    }
+}
+
+// 花费 `outpoints` 并创建 `outputs` 的交易，没有解锁数据与时间锁
+pub(crate) fn spend(outpoints: impl IntoIterator<Item = OutPoint>, outputs: Vec<Output>) -> Transaction {
+    Transaction {
+        inputs: outpoints.into_iter().map(Input::from).collect(),
+        outputs,
+        coinbase: None,
+        lock_time: None,
+        issuance: None,
+    }
+}
+
+// 将区块 Coinbase 交易的第一个输出支付给 `receiver`
+pub(crate) fn spend_reward(block: &Block, receiver: &str, value: u64) -> Transaction {
+    let (outpoint, _) = block.transactions[0].outpoints().next().unwrap();
+    spend([outpoint], vec![pay(receiver, value)])
+}
@@ -1,93 +1,609 @@
 use super::*;
+use crate::blockchain::MAX_TRANSACTION_SIZE;
+use crate::encoding::{self, Decode, DecodeError, Decoder, Encode, Encoder};
+use crate::script::{self, Lock, Unlock, MAX_DATA_SIZE, MAX_SCRIPT_DEPTH};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+
+// 资产 ID 为 SHA-256 摘要
+pub const ASSET_ID_SIZE: usize = 32;
+// 资产名称的最大字节数
+pub const MAX_ASSET_NAME_SIZE: usize = 32;
+
+// 定义与上下文无关的交易检查错误
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionError {
+    NoInputs,
+    NoOutputs,
+    ZeroValueOutput,
+    EmptyReceiver,
+    DuplicateInput,
+    CoinbaseWithInputs,
+    InvalidScript,
+    ValueOverflow,
+    TooLarge,
+    // 数据输出的金额不为 0，或同一交易包含多个数据输出
+    InvalidDataOutput,
+    DataTooLarge,
+    // 资产 ID 长度错误，或 Coinbase 交易包含资产输出或资产发行
+    InvalidAsset,
+    // 资产名称为空或过长、发行方锁定条件无效，或发行交易没有创建该资产的输出
+    InvalidIssuance,
+}
 
 // 定义交易输出结构体
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Output {
+    // 数据输出不需要地址与金额
+    #[serde(default)]
     pub receiver: Address,
+    #[serde(default)]
     pub value: u64,
+    // 发行的资产 ID，未设置时为原生币；`value` 为该资产的数量
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_utils::hex_bytes_option")]
+    pub asset: Option<Hash>,
+    // 花费条件；未设置时任何人都可以花费
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock: Option<Lock>,
+}
+
+impl Output {
+    // 加锁的输出，地址由锁定条件确定
+    pub fn locked(lock: Lock, value: u64) -> Self {
+        Output {
+            receiver: lock.address(),
+            value,
+            asset: None,
+            lock: Some(lock),
+        }
+    }
+
+    // 锁定到公钥哈希，地址为公钥哈希的十六进制
+    pub fn pay_to_public_key_hash(hash: Hash, value: u64) -> Self {
+        Self::locked(Lock::PubKeyHash { hash }, value)
+    }
+
+    // 携带数据的输出，金额为 0 且无法花费，不进入 UTXO 集合
+    pub fn data_carrier(data: Vec<u8>) -> Self {
+        Output {
+            receiver: Address::new(),
+            value: 0,
+            asset: None,
+            lock: Some(Lock::Data { data }),
+        }
+    }
+
+    // 数据输出携带的数据
+    pub fn carried_data(&self) -> Option<&[u8]> {
+        match &self.lock {
+            Some(Lock::Data { data }) => Some(data),
+            _ => None,
+        }
+    }
+
+    // 原生币金额，资产输出为 0
+    pub fn native_value(&self) -> u64 {
+        match self.asset {
+            Some(_) => 0,
+            None => self.value,
+        }
+    }
+}
+
+// 实现规范编码
+impl Encode for Output {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_str(&self.receiver);
+        enc.put_u64(self.value);
+        match &self.lock {
+            Some(lock) => {
+                enc.put_u8(1);
+                lock.encode(enc);
+            }
+            None => enc.put_u8(0),
+        }
+        match &self.asset {
+            Some(asset) => {
+                enc.put_u8(1);
+                enc.put_bytes(asset);
+            }
+            None => enc.put_u8(0),
+        }
+    }
+}
+
+impl Decode for Output {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(Output {
+            receiver: dec.get_str()?,
+            value: dec.get_u64()?,
+            lock: match dec.get_u8()? {
+                0 => None,
+                _ => Some(Lock::decode(dec)?),
+            },
+            asset: match dec.get_u8()? {
+                0 => None,
+                _ => Some(dec.get_bytes()?),
+            },
+        })
+    }
 }
 
 // 实现 Hashable 特性
 impl Hashable for Output {
     // 获取字节
     fn bytes(&self) -> Vec<u8> {
-        // 创建字节数组
-        let mut bytes = vec![];
+        encoding::encode(self)
+    }
+}
+
+// 定义输出位置：所在交易的 txid 与输出序号，唯一标识一个输出
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct OutPoint {
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub txid: Hash,
+    pub index: u32,
+}
+
+impl Encode for OutPoint {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_bytes(&self.txid);
+        enc.put_u32(self.index);
+    }
+}
+
+impl Decode for OutPoint {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(OutPoint {
+            txid: dec.get_bytes()?,
+            index: dec.get_u32()?,
+        })
+    }
+}
+
+// 定义绝对时间锁：交易只能打包进满足条件的区块
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockTime {
+    // 区块索引不小于该值
+    Height(u32),
+    // 区块之前的过去中位时间不早于该毫秒时间戳
+    Timestamp(u64),
+}
+
+// 定义相对时间锁：被花费的输出创建之后需要经过的区块数或时间
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelativeLock {
+    // 区块索引不小于输出所在区块索引加该值
+    Blocks(u32),
+    // 区块之前的过去中位时间不早于输出所在区块的时间戳加该毫秒数
+    Millis(u64),
+}
+
+// 时间锁的规范编码：首字节为类型标签
+impl Encode for LockTime {
+    fn encode(&self, enc: &mut Encoder) {
+        match self {
+            LockTime::Height(height) => {
+                enc.put_u8(0);
+                enc.put_u32(*height);
+            }
+            LockTime::Timestamp(timestamp) => {
+                enc.put_u8(1);
+                enc.put_u64(*timestamp);
+            }
+        }
+    }
+}
+
+impl Decode for LockTime {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        match dec.get_u8()? {
+            0 => Ok(LockTime::Height(dec.get_u32()?)),
+            1 => Ok(LockTime::Timestamp(dec.get_u64()?)),
+            tag => Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+}
+
+impl Encode for RelativeLock {
+    fn encode(&self, enc: &mut Encoder) {
+        match self {
+            RelativeLock::Blocks(blocks) => {
+                enc.put_u8(0);
+                enc.put_u32(*blocks);
+            }
+            RelativeLock::Millis(millis) => {
+                enc.put_u8(1);
+                enc.put_u64(*millis);
+            }
+        }
+    }
+}
+
+impl Decode for RelativeLock {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        match dec.get_u8()? {
+            0 => Ok(RelativeLock::Blocks(dec.get_u32()?)),
+            1 => Ok(RelativeLock::Millis(dec.get_u64()?)),
+            tag => Err(DecodeError::InvalidTag(tag)),
+        }
+    }
+}
+
+// 可选字段编码为标志字节加内容
+fn put_option<T: Encode>(enc: &mut Encoder, value: &Option<T>) {
+    match value {
+        Some(value) => {
+            enc.put_u8(1);
+            value.encode(enc);
+        }
+        None => enc.put_u8(0),
+    }
+}
+
+fn get_option<T: Decode>(dec: &mut Decoder) -> Result<Option<T>, DecodeError> {
+    match dec.get_u8()? {
+        0 => Ok(None),
+        _ => Ok(Some(T::decode(dec)?)),
+    }
+}
+
+// 按资产汇总输出数量（原生币不计入），溢出时返回 None
+fn asset_totals<'a>(outputs: impl IntoIterator<Item = &'a Output>) -> Option<BTreeMap<&'a [u8], u64>> {
+    let mut totals: BTreeMap<&[u8], u64> = BTreeMap::new();
+    let mut native = 0u64;
+    for output in outputs {
+        match &output.asset {
+            Some(asset) => {
+                let total = totals.entry(asset.as_slice()).or_default();
+                *total = total.checked_add(output.value)?;
+            }
+            None => native = native.checked_add(output.value)?,
+        }
+    }
+    Some(totals)
+}
+
+// 定义交易输入：引用一个未花费的输出，并提供满足其锁定条件的解锁数据
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Input {
+    #[serde(with = "serde_utils::hex_bytes")]
+    pub txid: Hash,
+    pub index: u32,
+    #[serde(default)]
+    pub unlock: Unlock,
+    // 相对时间锁
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<RelativeLock>,
+}
+
+impl Input {
+    // 引用的输出位置
+    pub fn outpoint(&self) -> OutPoint {
+        OutPoint {
+            txid: self.txid.clone(),
+            index: self.index,
+        }
+    }
+}
+
+impl From<OutPoint> for Input {
+    fn from(outpoint: OutPoint) -> Self {
+        Input {
+            txid: outpoint.txid,
+            index: outpoint.index,
+            unlock: Unlock::None,
+            sequence: None,
+        }
+    }
+}
+
+impl Input {
+    // 不含解锁数据的编码
+    fn encode_unsigned(&self, enc: &mut Encoder) {
+        enc.put_bytes(&self.txid);
+        enc.put_u32(self.index);
+        put_option(enc, &self.sequence);
+    }
+}
+
+impl Encode for Input {
+    fn encode(&self, enc: &mut Encoder) {
+        self.encode_unsigned(enc);
+        self.unlock.encode(enc);
+    }
+}
+
+impl Decode for Input {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        let txid = dec.get_bytes()?;
+        let index = dec.get_u32()?;
+        let sequence = get_option(dec)?;
+        Ok(Input {
+            txid,
+            index,
+            unlock: Unlock::decode(dec)?,
+            sequence,
+        })
+    }
+}
+
+// 定义 Coinbase 数据：提交区块索引，使不同区块的 Coinbase 交易 txid 不同
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coinbase {
+    pub height: u32,
+    // 矿工可附带的任意数据
+    #[serde(default, with = "serde_utils::hex_bytes")]
+    pub extra: Vec<u8>,
+}
 
-        // 添加地址字节
-        bytes.extend(self.receiver.as_bytes());
-        bytes.extend(&u64_bytes(&self.value));
+// 定义资产发行：发行方锁定条件与名称确定资产 ID，同一发行方可以用相同的名称增发
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Issuance {
+    pub issuer: Lock,
+    pub name: String,
+    // 满足发行方锁定条件的解锁数据，与输入的解锁数据一样不计入 txid
+    #[serde(default)]
+    pub unlock: Unlock,
+}
 
-        bytes
+impl Issuance {
+    // 资产 ID：发行方锁定条件与名称的规范编码的哈希
+    pub fn asset_id(&self) -> Hash {
+        let mut enc = Encoder::new();
+        self.issuer.encode(&mut enc);
+        enc.put_str(&self.name);
+        script::sha256(&enc.into_bytes())
     }
 }
 
 // 定义交易结构体
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Transaction {
-    pub inputs: Vec<Output>,
+    pub inputs: Vec<Input>,
     pub outputs: Vec<Output>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coinbase: Option<Coinbase>,
+    // 绝对时间锁
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock_time: Option<LockTime>,
+    // 资产发行，交易中该资产的输出可以多于输入
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuance: Option<Issuance>,
 }
 
 // 实现交易结构体
 impl Transaction {
-    // 获取输入金额
-    pub fn input_value(&self) -> u64 {
-        self.inputs.iter().map(|input| input.value).sum()
+    // 创建索引为 `height` 的区块中的 Coinbase 交易
+    pub fn coinbase(height: u32, outputs: Vec<Output>) -> Self {
+        Transaction {
+            inputs: vec![],
+            outputs,
+            coinbase: Some(Coinbase {
+                height,
+                extra: vec![],
+            }),
+            lock_time: None,
+            issuance: None,
+        }
+    }
+
+    // 交易 ID，即不含解锁数据的规范编码的哈希，也是签名的消息
+    pub fn txid(&self) -> Hash {
+        self.hash()
     }
 
-    // 获取输出金额
+    // 获取原生币输出金额，溢出时饱和；经过 `check` 的交易不会溢出
     pub fn output_value(&self) -> u64 {
-        self.outputs.iter().map(|output| output.value).sum()
+        self.outputs.iter().fold(0, |sum, output| sum.saturating_add(output.native_value()))
     }
 
-    // 获取输入哈希
-    pub fn input_hashes(&self) -> HashSet<Hash> {
-        self.inputs
-            .iter()
-            .map(|input| input.hash())
-            .collect::<HashSet<Hash>>()
+    // 检查每种资产的输入与输出数量（`spent` 为被花费的输出）：本交易发行的资产可以增发，
+    // 其他资产的输出必须等于输入；原生币由手续费规则检查
+    pub fn assets_balanced<'a>(&self, spent: impl IntoIterator<Item = &'a Output>) -> bool {
+        let (Some(inputs), Some(outputs)) = (asset_totals(spent), asset_totals(&self.outputs)) else {
+            return false;
+        };
+        let issued = self.issuance.as_ref().map(Issuance::asset_id);
+        inputs.keys().chain(outputs.keys()).all(|asset| {
+            let input = inputs.get(asset).copied().unwrap_or(0);
+            let output = outputs.get(asset).copied().unwrap_or(0);
+            input == output || (issued.as_deref() == Some(*asset) && output > input)
+        })
     }
 
-    // 获取输出哈希
-    pub fn output_hashes(&self) -> HashSet<Hash> {
-        self.outputs
+    // 本交易发行的资产数量：发行资产的输出减去输入，没有发行时返回 None
+    pub fn issued_amount<'a>(&self, spent: impl IntoIterator<Item = &'a Output>) -> Option<(Hash, u64)> {
+        let asset = self.issuance.as_ref()?.asset_id();
+        let total = |outputs: Option<BTreeMap<&[u8], u64>>| {
+            outputs.and_then(|totals| totals.get(asset.as_slice()).copied()).unwrap_or(0)
+        };
+        let amount = total(asset_totals(&self.outputs)).saturating_sub(total(asset_totals(spent)));
+        Some((asset, amount))
+    }
+
+    // 不依赖链上状态的检查，交易池与区块验证都会先执行
+    pub fn check(&self) -> Result<(), TransactionError> {
+        match (self.is_coinbase(), self.inputs.is_empty()) {
+            (true, false) => return Err(TransactionError::CoinbaseWithInputs),
+            (false, true) => return Err(TransactionError::NoInputs),
+            _ => {}
+        }
+        if self.outputs.is_empty() {
+            return Err(TransactionError::NoOutputs);
+        }
+        // 数据输出之外的输出需要金额与地址
+        let (data_outputs, outputs): (Vec<&Output>, Vec<&Output>) =
+            self.outputs.iter().partition(|output| output.carried_data().is_some());
+        if outputs.iter().any(|output| output.value == 0) {
+            return Err(TransactionError::ZeroValueOutput);
+        }
+        if outputs.iter().any(|output| output.receiver.is_empty()) {
+            return Err(TransactionError::EmptyReceiver);
+        }
+        if data_outputs.len() > 1 || data_outputs.iter().any(|output| output.value != 0 || output.asset.is_some()) {
+            return Err(TransactionError::InvalidDataOutput);
+        }
+        if data_outputs
             .iter()
-            .map(|output| output.hash())
-            .collect::<HashSet<Hash>>()
+            .any(|output| output.carried_data().is_some_and(|data| data.len() > MAX_DATA_SIZE))
+        {
+            return Err(TransactionError::DataTooLarge);
+        }
+        if self.outputs.iter().any(|output| output.lock.as_ref().is_some_and(|lock| !lock.is_well_formed()))
+            || self.inputs.iter().any(|input| input.unlock.depth() > MAX_SCRIPT_DEPTH)
+        {
+            return Err(TransactionError::InvalidScript);
+        }
+
+        // 资产只能由普通交易转移与发行
+        if self.outputs.iter().any(|output| output.asset.as_ref().is_some_and(|asset| asset.len() != ASSET_ID_SIZE))
+            || (self.is_coinbase()
+                && (self.issuance.is_some() || self.outputs.iter().any(|output| output.asset.is_some())))
+        {
+            return Err(TransactionError::InvalidAsset);
+        }
+        if let Some(issuance) = &self.issuance {
+            let asset = issuance.asset_id();
+            if issuance.name.is_empty()
+                || issuance.name.len() > MAX_ASSET_NAME_SIZE
+                || !issuance.issuer.is_well_formed()
+                // 发行方的解锁数据不计入 txid，只有签名能把发行绑定到本交易，原像与时间锁公开后任何人都可以增发
+                || !issuance.issuer.requires_signature()
+                || issuance.unlock.depth() > MAX_SCRIPT_DEPTH
+                || !self.outputs.iter().any(|output| output.asset.as_ref() == Some(&asset))
+            {
+                return Err(TransactionError::InvalidIssuance);
+            }
+        }
+
+        // 重复引用同一输出会重复计算输入金额
+        if self.input_outpoints().len() != self.inputs.len() {
+            return Err(TransactionError::DuplicateInput);
+        }
+
+        if asset_totals(&self.outputs).is_none() {
+            return Err(TransactionError::ValueOverflow);
+        }
+
+        if self.size() > MAX_TRANSACTION_SIZE {
+            return Err(TransactionError::TooLarge);
+        }
+        Ok(())
+    }
+
+    // 获取输入引用的输出位置
+    pub fn input_outpoints(&self) -> HashSet<OutPoint> {
+        self.inputs.iter().map(Input::outpoint).collect()
+    }
+
+    // 获取本交易创建的输出及其位置
+    pub fn outpoints(&self) -> impl Iterator<Item = (OutPoint, &Output)> {
+        let txid = self.txid();
+        self.outputs.iter().enumerate().map(move |(index, output)| {
+            let outpoint = OutPoint {
+                txid: txid.clone(),
+                index: index as u32,
+            };
+            (outpoint, output)
+        })
     }
 
     // 是否为 Coinbase 交易
     pub fn is_coinbase(&self) -> bool {
-        self.inputs.len() == 0
+        self.coinbase.is_some()
+    }
+
+    // 规范编码后的字节数
+    pub fn size(&self) -> usize {
+        encoding::encode(self).len()
+    }
+}
+
+impl Transaction {
+    // 不含解锁数据的编码，签名与交易 ID 都基于它；与 `put_seq` 的布局一致
+    fn encode_unsigned(&self, enc: &mut Encoder) {
+        enc.put_u32(self.inputs.len() as u32);
+        for input in &self.inputs {
+            input.encode_unsigned(enc);
+        }
+        self.encode_body(enc);
+    }
+
+    fn encode_body(&self, enc: &mut Encoder) {
+        enc.put_seq(&self.outputs);
+        match &self.coinbase {
+            Some(coinbase) => {
+                enc.put_u8(1);
+                enc.put_u32(coinbase.height);
+                enc.put_bytes(&coinbase.extra);
+            }
+            None => enc.put_u8(0),
+        }
+        put_option(enc, &self.lock_time);
+        match &self.issuance {
+            Some(issuance) => {
+                enc.put_u8(1);
+                issuance.issuer.encode(enc);
+                enc.put_str(&issuance.name);
+            }
+            None => enc.put_u8(0),
+        }
     }
 }
 
-// 为交易结构体实现 Hashable 特性
+// 实现规范编码，发行方的解锁数据位于末尾
+impl Encode for Transaction {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.put_seq(&self.inputs);
+        self.encode_body(enc);
+        if let Some(issuance) = &self.issuance {
+            issuance.unlock.encode(enc);
+        }
+    }
+}
+
+impl Decode for Transaction {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        let inputs = dec.get_seq()?;
+        let outputs = dec.get_seq()?;
+        let coinbase = match dec.get_u8()? {
+            0 => None,
+            _ => Some(Coinbase {
+                height: dec.get_u32()?,
+                extra: dec.get_bytes()?,
+            }),
+        };
+        let lock_time = get_option(dec)?;
+        // 发行方的解锁数据紧随交易主体之后
+        let issuance = match dec.get_u8()? {
+            0 => None,
+            _ => Some(Issuance {
+                issuer: Lock::decode(dec)?,
+                name: dec.get_str()?,
+                unlock: Unlock::decode(dec)?,
+            }),
+        };
+        Ok(Transaction {
+            inputs,
+            outputs,
+            coinbase,
+            lock_time,
+            issuance,
+        })
+    }
+}
+
+// 为交易结构体实现 Hashable 特性，哈希不包含解锁数据
 impl Hashable for Transaction {
     // 获取字节
     fn bytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
-
-        // 添加输入字节
-        bytes.extend(
-            self.inputs
-                .iter()
-                .flat_map(|input| input.bytes())
-                .collect::<Vec<u8>>(),
-        );
-
-        // 添加输出字节
-        bytes.extend(
-            self.outputs
-                .iter()
-                .flat_map(|output| output.bytes())
-                .collect::<Vec<u8>>(),
-        );
-
-        bytes
+        let mut enc = Encoder::new();
+        enc.put_u8(encoding::ENCODING_VERSION);
+        self.encode_unsigned(&mut enc);
+        enc.into_bytes()
     }
 }
 
@@ -95,28 +611,234 @@ impl Hashable for Transaction {
 mod tests {
     use super::*;
 
+    fn output(receiver: &str, value: u64) -> Output {
+        Output {
+            receiver: receiver.to_owned(),
+            value,
+            asset: None,
+            lock: None,
+        }
+    }
+
+    fn input(index: u32) -> Input {
+        Input {
+            txid: vec![1; 32],
+            index,
+            unlock: Unlock::None,
+            sequence: None,
+        }
+    }
+
+    fn spend(inputs: Vec<Input>, outputs: Vec<Output>) -> Transaction {
+        Transaction {
+            inputs,
+            outputs,
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        }
+    }
+
     #[test]
     fn test_transaction() {
-        // 创建两个输出
-        let output1 = Output {
-            receiver: Address::new(),
-            value: 100,
+        // 相同的输出位于不同的位置，不会互相覆盖
+        let transaction = spend(vec![input(0), input(1)], vec![output("Alice", 100), output("Alice", 100)]);
+
+        assert_eq!(transaction.output_value(), 200);
+        assert_eq!(transaction.input_outpoints().len(), 2);
+        let outpoints: Vec<OutPoint> = transaction.outpoints().map(|(outpoint, _)| outpoint).collect();
+        assert_eq!(outpoints[0].txid, transaction.txid());
+        assert_ne!(outpoints[0], outpoints[1]);
+        assert!(!transaction.is_coinbase());
+    }
+
+    #[test]
+    fn test_txid_excludes_unlock() {
+        let mut transaction = spend(vec![input(0)], vec![output("Bob", 30)]);
+        let txid = transaction.txid();
+        transaction.inputs[0].unlock = Unlock::Preimage {
+            preimage: b"secret".to_vec(),
         };
-        let output2 = Output {
-            receiver: Address::new(),
-            value: 200,
+        assert_eq!(transaction.txid(), txid);
+        assert_ne!(encoding::encode(&transaction), encoding::encode(&spend(vec![input(0)], vec![output("Bob", 30)])));
+
+        // 时间锁属于签名内容
+        transaction.inputs[0].sequence = Some(RelativeLock::Blocks(1));
+        assert_ne!(transaction.txid(), txid);
+        let decoded: Transaction = encoding::decode(&encoding::encode(&transaction)).unwrap();
+        assert_eq!(decoded, transaction);
+        transaction.inputs[0].sequence = None;
+        transaction.lock_time = Some(LockTime::Timestamp(1));
+        assert_ne!(transaction.txid(), txid);
+
+        transaction.lock_time = None;
+        transaction.inputs[0].index = 1;
+        assert_ne!(transaction.txid(), txid);
+    }
+
+    #[test]
+    fn test_coinbase_txid_commits_height() {
+        let first = Transaction::coinbase(1, vec![output("Alice", 50)]);
+        let second = Transaction::coinbase(2, vec![output("Alice", 50)]);
+        assert!(first.is_coinbase());
+        assert_ne!(first.txid(), second.txid());
+
+        let mut tagged = first.clone();
+        tagged.coinbase.as_mut().unwrap().extra = b"pool".to_vec();
+        assert_ne!(first.txid(), tagged.txid());
+    }
+
+    #[test]
+    fn test_check() {
+        let valid = spend(vec![input(0)], vec![output("Bob", 30), output("Alice", 20)]);
+        assert_eq!(valid.check(), Ok(()));
+        assert_eq!(Transaction::coinbase(1, vec![output("Alice", 50)]).check(), Ok(()));
+
+        let cases = [
+            (vec![], vec![output("Bob", 50)], TransactionError::NoInputs),
+            (vec![input(0)], vec![], TransactionError::NoOutputs),
+            (vec![input(0)], vec![output("Bob", 0)], TransactionError::ZeroValueOutput),
+            (vec![input(0)], vec![output("", 50)], TransactionError::EmptyReceiver),
+            (vec![input(0), input(0)], vec![output("Bob", 100)], TransactionError::DuplicateInput),
+            (
+                vec![input(0)],
+                vec![output("Bob", u64::MAX), output("Carol", u64::MAX)],
+                TransactionError::ValueOverflow,
+            ),
+            (
+                vec![input(0)],
+                vec![output(&"x".repeat(MAX_TRANSACTION_SIZE), 50)],
+                TransactionError::TooLarge,
+            ),
+        ];
+        for (inputs, outputs, expected) in cases {
+            assert_eq!(spend(inputs, outputs).check(), Err(expected));
+        }
+
+        let mut locked = valid.clone();
+        locked.outputs[0].lock = Some(Lock::Any { locks: vec![] });
+        assert_eq!(locked.check(), Err(TransactionError::InvalidScript));
+
+        // 数据输出金额为 0 且无需地址，每个交易至多一个
+        let memo = || Output::data_carrier(b"INV-0042".to_vec());
+        assert_eq!(spend(vec![input(0)], vec![output("Bob", 30), memo()]).check(), Ok(()));
+        assert_eq!(spend(vec![input(0)], vec![memo()]).check(), Ok(()));
+        let mut valued = memo();
+        valued.value = 1;
+        assert_eq!(
+            spend(vec![input(0)], vec![valued]).check(),
+            Err(TransactionError::InvalidDataOutput)
+        );
+        assert_eq!(
+            spend(vec![input(0)], vec![memo(), memo()]).check(),
+            Err(TransactionError::InvalidDataOutput)
+        );
+        assert_eq!(
+            spend(vec![input(0)], vec![Output::data_carrier(vec![0; MAX_DATA_SIZE + 1])]).check(),
+            Err(TransactionError::DataTooLarge)
+        );
+        assert_eq!(memo().carried_data(), Some(&b"INV-0042"[..]));
+        assert_eq!(output("Bob", 1).carried_data(), None);
+
+        let mut coinbase = Transaction::coinbase(1, vec![output("Alice", 50)]);
+        coinbase.inputs.push(input(0));
+        assert_eq!(coinbase.check(), Err(TransactionError::CoinbaseWithInputs));
+
+        // 溢出时金额饱和而不是 panic
+        let overflow = spend(vec![input(0)], vec![output("Bob", u64::MAX), output("Carol", 1)]);
+        assert_eq!(overflow.output_value(), u64::MAX);
+    }
+
+    #[test]
+    fn test_assets() {
+        let issuer = Lock::PubKeyHash {
+            hash: script::public_key_hash(&[7; 32]),
         };
-        // 创建交易
-        let transaction = Transaction {
-            inputs: vec![output1.clone(), output2.clone()],
-            outputs: vec![output1.clone(), output2.clone()],
+        let issuance = Issuance {
+            issuer: issuer.clone(),
+            name: "points".to_owned(),
+            unlock: Unlock::None,
         };
+        let asset = issuance.asset_id();
+        let token = |value| Output { asset: Some(asset.clone()), ..output("Bob", value) };
+        let mut issue = spend(vec![input(0)], vec![output("Alice", 10), token(100)]);
+        issue.issuance = Some(issuance.clone());
+        assert_eq!(issue.check(), Ok(()));
+        assert_eq!(issue.output_value(), 10);
+        assert_eq!(token(100).native_value(), 0);
+
+        // 发行方的解锁数据不计入 txid，发行信息与资产 ID 计入
+        let txid = issue.txid();
+        let mut unlocked = issue.clone();
+        unlocked.issuance.as_mut().unwrap().unlock = Unlock::Signature {
+            public_key: vec![7; 32],
+            signature: vec![1; 64],
+        };
+        assert_eq!(unlocked.txid(), txid);
+        let decoded: Transaction = encoding::decode(&encoding::encode(&unlocked)).unwrap();
+        assert_eq!(decoded, unlocked);
+        let mut renamed = issue.clone();
+        renamed.issuance.as_mut().unwrap().name = "vouchers".to_owned();
+        assert_ne!(renamed.txid(), txid);
+        assert_ne!(renamed.issuance.unwrap().asset_id(), asset);
+        let mut plain = issue.clone();
+        plain.outputs[1].asset = None;
+        plain.issuance = None;
+        assert_ne!(plain.txid(), txid);
+
+        // 发行的资产可以增发，其他资产的输入与输出必须相等
+        let spent = [output("Alice", 50), token(40)];
+        assert!(issue.assets_balanced(&spent));
+        assert_eq!(issue.issued_amount(&spent), Some((asset.clone(), 60)));
+        let transfer = spend(vec![input(0), input(1)], vec![output("Alice", 50), token(30), token(10)]);
+        assert!(transfer.assets_balanced(&spent));
+        assert_eq!(transfer.issued_amount(&spent), None);
+        let inflated = spend(vec![input(0), input(1)], vec![token(41)]);
+        assert!(!inflated.assets_balanced(&spent));
+        let burned = spend(vec![input(0), input(1)], vec![token(39)]);
+        assert!(!burned.assets_balanced(&spent));
+
+        let mut invalid = issue.clone();
+        invalid.outputs[1].asset = Some(vec![1; 4]);
+        assert_eq!(invalid.check(), Err(TransactionError::InvalidAsset));
+        let mut coinbase = Transaction::coinbase(1, vec![token(1)]);
+        assert_eq!(coinbase.check(), Err(TransactionError::InvalidAsset));
+        coinbase.outputs = vec![output("Alice", 50)];
+        coinbase.issuance = Some(issuance.clone());
+        assert_eq!(coinbase.check(), Err(TransactionError::InvalidAsset));
+        let mut memo = Output::data_carrier(b"memo".to_vec());
+        memo.asset = Some(asset.clone());
+        assert_eq!(
+            spend(vec![input(0)], vec![memo]).check(),
+            Err(TransactionError::InvalidDataOutput)
+        );
+
+        let mut unnamed = issue.clone();
+        unnamed.issuance.as_mut().unwrap().name = String::new();
+        assert_eq!(unnamed.check(), Err(TransactionError::InvalidIssuance));
+        let mut long_name = issue.clone();
+        long_name.issuance.as_mut().unwrap().name = "x".repeat(MAX_ASSET_NAME_SIZE + 1);
+        assert_eq!(long_name.check(), Err(TransactionError::InvalidIssuance));
+        let mut unissued = issue.clone();
+        unissued.outputs.truncate(1);
+        assert_eq!(unissued.check(), Err(TransactionError::InvalidIssuance));
+
+        // 发行方必须在每条满足路径上签名，原像与时间锁不能单独授权发行
+        let hash_lock = Lock::HashLock { hash: script::sha256(b"issuer") };
+        let after_height = Lock::AfterHeight { height: 0 };
+        let signed_or_open = Lock::Any { locks: vec![issuer.clone(), after_height.clone()] };
+        let signed_and_timed = Lock::All { locks: vec![after_height.clone(), issuer.clone()] };
+        for (lock, valid) in [(hash_lock, false), (after_height, false), (signed_or_open, false), (signed_and_timed, true)] {
+            let mut issue = issue.clone();
+            issue.issuance.as_mut().unwrap().issuer = lock;
+            let asset = issue.issuance.as_ref().unwrap().asset_id();
+            issue.outputs[1].asset = Some(asset);
+            let expected = if valid { Ok(()) } else { Err(TransactionError::InvalidIssuance) };
+            assert_eq!(issue.check(), expected);
+        }
 
-        // 测试交易的输入和输出金额
-        assert_eq!(transaction.input_value(), 300);
-        assert_eq!(transaction.output_value(), 300);
-        assert_eq!(transaction.input_hashes().len(), 2);
-        assert_eq!(transaction.output_hashes().len(), 2);
-        assert_eq!(transaction.is_coinbase(), false);
+        // 资产数量同样不能溢出
+        let overflow = spend(vec![input(0)], vec![token(u64::MAX), token(1)]);
+        assert_eq!(overflow.check(), Err(TransactionError::ValueOverflow));
     }
 }
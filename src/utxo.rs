@@ -0,0 +1,52 @@
+use crate::encoding::{Decode, DecodeError, Decoder, Encode, Encoder};
+use crate::transaction::{OutPoint, Output};
+use serde::Serialize;
+
+// 定义未花费输出条目，记录创建高度以检查 Coinbase 成熟度
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UtxoEntry {
+    pub outpoint: OutPoint,
+    pub output: Output,
+    // 创建该输出的区块索引
+    pub height: u32,
+    // 创建该输出的区块时间戳，用于相对时间锁
+    pub time: u128,
+    // 是否由 Coinbase 交易创建
+    pub is_coinbase: bool,
+}
+
+impl UtxoEntry {
+    // 在索引为 `spend_height` 的区块中是否可以花费
+    pub fn is_mature(&self, spend_height: usize, maturity: u32) -> bool {
+        !self.is_coinbase || spend_height >= self.height as usize + maturity as usize
+    }
+}
+
+impl Encode for UtxoEntry {
+    fn encode(&self, enc: &mut Encoder) {
+        self.outpoint.encode(enc);
+        self.output.encode(enc);
+        enc.put_u32(self.height);
+        enc.put_u128(self.time);
+        enc.put_u8(self.is_coinbase as u8);
+    }
+}
+
+impl Decode for UtxoEntry {
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(UtxoEntry {
+            outpoint: OutPoint::decode(dec)?,
+            output: Output::decode(dec)?,
+            height: dec.get_u32()?,
+            time: dec.get_u128()?,
+            is_coinbase: dec.get_u8()? != 0,
+        })
+    }
+}
+
+// 定义地址余额：可花费部分与尚未成熟的 Coinbase 奖励分开统计
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Balance {
+    pub spendable: u64,
+    pub immature: u64,
+}
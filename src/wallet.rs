@@ -0,0 +1,310 @@
+use super::*;
+use crate::script::{self, KeySignature, Lock, Unlock};
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+// 定义钱包错误
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WalletError {
+    InputCountMismatch,
+    NotMultisig(usize),
+    TransactionMismatch,
+    InvalidSignature(usize),
+    NotEnoughSignatures { input: usize, have: usize, need: usize },
+}
+
+// 定义钱包：持有若干 ed25519 私钥
+#[derive(Debug, Default)]
+pub struct Wallet {
+    keys: Vec<SigningKey>,
+}
+
+// 钱包文件格式，私钥以十六进制保存
+#[derive(Serialize, Deserialize)]
+struct WalletFile {
+    #[serde(with = "serde_utils::hex_bytes_seq")]
+    secret_keys: Vec<Vec<u8>>,
+}
+
+impl Wallet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 添加私钥
+    pub fn add_key(&mut self, key: SigningKey) -> VerifyingKey {
+        let public_key = key.verifying_key();
+        self.keys.push(key);
+        public_key
+    }
+
+    // 随机生成新私钥，返回对应的公钥
+    pub fn generate_key(&mut self) -> VerifyingKey {
+        self.add_key(SigningKey::generate(&mut OsRng))
+    }
+
+    // 钱包中全部公钥
+    pub fn public_keys(&self) -> Vec<VerifyingKey> {
+        self.keys.iter().map(SigningKey::verifying_key).collect()
+    }
+
+    // 按公钥字节查找私钥
+    fn key_for(&self, public_key: &[u8]) -> Option<&SigningKey> {
+        self.keys
+            .iter()
+            .find(|key| key.verifying_key().as_bytes().as_slice() == public_key)
+    }
+
+    // 用指定公钥对应的私钥签名，钱包中没有该私钥时返回 None
+    pub fn sign(&self, public_key: &[u8], message: &[u8]) -> Option<Vec<u8>> {
+        self.key_for(public_key).map(|key| key.sign(message).to_bytes().to_vec())
+    }
+
+    // 用钱包中的私钥为多重签名交易补充签名，返回新增的签名数量
+    pub fn sign_multisig(&self, spend: &mut MultisigSpend) -> usize {
+        let txid = spend.transaction.txid();
+        let mut added = 0;
+        for (lock, signatures) in spend.locks.iter().zip(&mut spend.signatures) {
+            let Lock::Multisig { public_keys, .. } = lock else {
+                continue;
+            };
+            for (index, public_key) in public_keys.iter().enumerate() {
+                let index = index as u32;
+                if signatures.iter().any(|sig| sig.key == index) {
+                    continue;
+                }
+                if let Some(signature) = self.sign(public_key, &txid) {
+                    signatures.push(KeySignature { key: index, signature });
+                    added += 1;
+                }
+            }
+            signatures.sort_by_key(|sig| sig.key);
+        }
+        added
+    }
+
+    // 读取钱包文件
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file: WalletFile = serde_json::from_slice(&std::fs::read(path)?)?;
+        let keys = file
+            .secret_keys
+            .iter()
+            .map(|bytes| {
+                <[u8; 32]>::try_from(bytes.as_slice())
+                    .map(|bytes| SigningKey::from_bytes(&bytes))
+                    .map_err(|_| io::Error::new(ErrorKind::InvalidData, "secret key must be 32 bytes"))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Wallet { keys })
+    }
+
+    // 写入钱包文件
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let file = WalletFile {
+            secret_keys: self.keys.iter().map(|key| key.to_bytes().to_vec()).collect(),
+        };
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&file)?)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+// 定义待签名的多重签名交易：各签名方分别签名后合并，签名足够时生成最终交易
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigSpend {
+    pub transaction: Transaction,
+    // 每个输入花费的输出的锁定条件
+    pub locks: Vec<Lock>,
+    // 每个输入已收集的签名，按公钥序号排序
+    pub signatures: Vec<Vec<KeySignature>>,
+}
+
+impl MultisigSpend {
+    // 创建多重签名交易，每个输入都必须花费多重签名输出
+    pub fn new(transaction: Transaction, locks: Vec<Lock>) -> Result<Self, WalletError> {
+        if locks.len() != transaction.inputs.len() {
+            return Err(WalletError::InputCountMismatch);
+        }
+        if let Some(input) = locks.iter().position(|lock| !matches!(lock, Lock::Multisig { .. })) {
+            return Err(WalletError::NotMultisig(input));
+        }
+        let signatures = vec![vec![]; locks.len()];
+        Ok(MultisigSpend {
+            transaction,
+            locks,
+            signatures,
+        })
+    }
+
+    // 合并其他签名方的签名，只接受对同一交易的有效签名
+    pub fn combine(&mut self, other: &MultisigSpend) -> Result<(), WalletError> {
+        if other.transaction.txid() != self.transaction.txid() || other.locks != self.locks {
+            return Err(WalletError::TransactionMismatch);
+        }
+        let txid = self.transaction.txid();
+        for (input, (lock, theirs)) in self.locks.iter().zip(&other.signatures).enumerate() {
+            let Lock::Multisig { public_keys, .. } = lock else {
+                return Err(WalletError::NotMultisig(input));
+            };
+            let ours = &mut self.signatures[input];
+            for sig in theirs {
+                let valid = public_keys
+                    .get(sig.key as usize)
+                    .is_some_and(|public_key| script::verify_signature(public_key, &sig.signature, &txid).is_ok());
+                if !valid {
+                    return Err(WalletError::InvalidSignature(input));
+                }
+                if !ours.iter().any(|existing| existing.key == sig.key) {
+                    ours.push(sig.clone());
+                }
+            }
+            ours.sort_by_key(|sig| sig.key);
+        }
+        Ok(())
+    }
+
+    // 生成最终交易：每个输入按公钥序号取前 `threshold` 个签名
+    pub fn finalize(self) -> Result<Transaction, WalletError> {
+        let mut transaction = self.transaction;
+        for (input, (lock, mut signatures)) in self.locks.iter().zip(self.signatures).enumerate() {
+            let Lock::Multisig { threshold, .. } = lock else {
+                return Err(WalletError::NotMultisig(input));
+            };
+            let need = *threshold as usize;
+            if signatures.len() < need {
+                return Err(WalletError::NotEnoughSignatures {
+                    input,
+                    have: signatures.len(),
+                    need,
+                });
+            }
+            signatures.truncate(need);
+            transaction.inputs[input].unlock = Unlock::Multisig { signatures };
+        }
+        Ok(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::ScriptContext;
+    use crate::transaction::{Input, Output};
+
+    fn wallet(seed: u8) -> Wallet {
+        let mut wallet = Wallet::new();
+        wallet.add_key(SigningKey::from_bytes(&[seed; 32]));
+        wallet
+    }
+
+    fn spend(lock: &Lock) -> MultisigSpend {
+        let transaction = Transaction {
+            inputs: vec![Input {
+                txid: vec![1; 32],
+                index: 0,
+                unlock: Unlock::None,
+                sequence: None,
+            }],
+            outputs: vec![Output {
+                receiver: "Bob".to_owned(),
+                value: 10,
+                asset: None,
+                lock: None,
+            }],
+            coinbase: None,
+            lock_time: None,
+            issuance: None,
+        };
+        MultisigSpend::new(transaction, vec![lock.clone()]).unwrap()
+    }
+
+    #[test]
+    fn test_sign() {
+        let wallet = wallet(1);
+        let public_key = wallet.public_keys()[0];
+        let signature = wallet.sign(public_key.as_bytes(), b"message").unwrap();
+        assert_eq!(script::verify_signature(public_key.as_bytes(), &signature, b"message"), Ok(()));
+        assert_eq!(wallet.sign(&[2; 32], b"message"), None);
+    }
+
+    #[test]
+    fn test_sign_combine_finalize() {
+        let wallets = [wallet(1), wallet(2), wallet(3)];
+        let public_keys: Vec<VerifyingKey> = wallets.iter().flat_map(Wallet::public_keys).collect();
+        let lock = Lock::multisig(2, &public_keys);
+
+        // 两个签名方分别签名，合并后生成最终交易
+        let mut first = spend(&lock);
+        let mut second = spend(&lock);
+        assert_eq!(wallets[2].sign_multisig(&mut first), 1);
+        assert_eq!(wallets[0].sign_multisig(&mut second), 1);
+        assert_eq!(
+            first.clone().finalize(),
+            Err(WalletError::NotEnoughSignatures { input: 0, have: 1, need: 2 })
+        );
+        first.combine(&second).unwrap();
+        assert_eq!(first.signatures[0].iter().map(|sig| sig.key).collect::<Vec<_>>(), vec![0, 2]);
+        // 重复签名与重复合并不会增加签名
+        assert_eq!(wallets[0].sign_multisig(&mut first), 0);
+        first.combine(&second).unwrap();
+        assert_eq!(first.signatures[0].len(), 2);
+
+        let transaction = first.finalize().unwrap();
+        let txid = transaction.txid();
+        let ctx = ScriptContext {
+            sighash: &txid,
+            height: 0,
+            time: 0,
+        };
+        assert_eq!(lock.evaluate(&transaction.inputs[0].unlock, &ctx), Ok(()));
+    }
+
+    #[test]
+    fn test_combine_rejects_mismatch() {
+        let wallets = [wallet(1), wallet(2)];
+        let public_keys: Vec<VerifyingKey> = wallets.iter().flat_map(Wallet::public_keys).collect();
+        let lock = Lock::multisig(2, &public_keys);
+
+        let mut ours = spend(&lock);
+        let mut other = spend(&lock);
+        other.transaction.outputs[0].value = 20;
+        assert_eq!(ours.combine(&other), Err(WalletError::TransactionMismatch));
+
+        // 伪造的签名不会被合并
+        let mut forged = spend(&lock);
+        forged.signatures[0].push(KeySignature {
+            key: 1,
+            signature: vec![0; 64],
+        });
+        assert_eq!(ours.combine(&forged), Err(WalletError::InvalidSignature(0)));
+
+        let p2pkh = Lock::pay_to_public_key(&public_keys[0]);
+        assert_eq!(
+            MultisigSpend::new(spend(&lock).transaction, vec![p2pkh]),
+            Err(WalletError::NotMultisig(0))
+        );
+        assert_eq!(
+            MultisigSpend::new(spend(&lock).transaction, vec![]),
+            Err(WalletError::InputCountMismatch)
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("jl-wallet-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("wallet.json");
+
+        let mut wallet = Wallet::new();
+        wallet.generate_key();
+        wallet.generate_key();
+        wallet.save(&path).unwrap();
+        let loaded = Wallet::load(&path).unwrap();
+        assert_eq!(loaded.public_keys(), wallet.public_keys());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}